@@ -39,6 +39,11 @@ fn rank_eval_module(_py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(idcg_at_k_py, m)?)?;
     m.add_function(wrap_pyfunction!(ndcg_at_k_py, m)?)?;
     m.add_function(wrap_pyfunction!(average_precision_py, m)?)?;
+    m.add_function(wrap_pyfunction!(err_at_k_py, m)?)?;
+    m.add_function(wrap_pyfunction!(rbp_at_k_py, m)?)?;
+    m.add_function(wrap_pyfunction!(f_measure_at_k_py, m)?)?;
+    m.add_function(wrap_pyfunction!(success_at_k_py, m)?)?;
+    m.add_function(wrap_pyfunction!(r_precision_py, m)?)?;
     
     // Graded relevance metrics
     m.add_function(wrap_pyfunction!(compute_ndcg_py, m)?)?;
@@ -114,6 +119,12 @@ fn dcg_at_k_py(ranked: &Bound<'_, PyList>, relevant: &Bound<'_, PySet>, k: usize
 /// Ideal DCG at rank k.
 #[pyfunction]
 fn idcg_at_k_py(ranked: &Bound<'_, PyList>, relevant: &Bound<'_, PySet>, k: usize) -> PyResult<f64> {
+    // IDCG only depends on the number of relevant documents, but `ranked` is
+    // still checked so bad input raises the same TypeError as the other metrics.
+    for v in ranked.iter() {
+        v.extract::<String>()?;
+    }
+
     let relevant_set: std::collections::HashSet<String> = relevant
         .iter()
         .map(|v| v.extract::<String>())
@@ -1,21 +1,243 @@
 //! Batch evaluation utilities for processing multiple queries efficiently.
 
 use crate::binary::*;
-use crate::trec::{Qrel, TrecRun};
+use crate::graded;
+use crate::statistics::{paired_metric_values, paired_t_test, TTestResult};
+use crate::trec::{OrderBy, Qrel, TrecRun};
+use crate::validation::ValidationError;
 use std::collections::{HashMap, HashSet};
+use std::path::Path;
 
 /// Results for a single query evaluation.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct QueryResults {
     pub query_id: String,
     pub metrics: HashMap<String, f64>,
 }
 
 /// Batch evaluation results across multiple queries.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BatchResults {
     pub query_results: Vec<QueryResults>,
     pub aggregated: HashMap<String, f64>, // Mean across queries
+    /// Number of queries averaged into each aggregate, i.e. its denominator.
+    /// Skipped queries (see `skipped`) are not counted; in complete mode they
+    /// are, as zero-valued queries.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub effective_query_count: HashMap<String, usize>,
+    /// Queries excluded from evaluation, sorted by query ID. Only populated by
+    /// [`evaluate_trec_batch`] and [`evaluate_trec_batch_with_options`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub skipped: Vec<SkippedQuery>,
+}
+
+/// Why a query was excluded from a batch evaluation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SkipReason {
+    /// The query has judgments but no run entries (not reported in
+    /// [`TrecBatchOptions::complete`] mode, where it is evaluated as empty).
+    MissingFromRuns,
+    /// The query has run entries but no judgments, so its qrels are empty and
+    /// no metric is defined.
+    MissingFromQrels,
+}
+
+/// A query excluded from a batch evaluation, with the reason.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SkippedQuery {
+    pub query_id: String,
+    pub reason: SkipReason,
+}
+
+/// Compute a binary relevance metric by name, or `None` if the name is unknown.
+fn compute_binary_metric<I: Eq + std::hash::Hash>(
+    metric_name: &str,
+    ranked: &[I],
+    relevant: &HashSet<I>,
+) -> Option<f64> {
+    let value = match metric_name {
+        "ndcg@10" => ndcg_at_k(ranked, relevant, 10),
+        "ndcg@5" => ndcg_at_k(ranked, relevant, 5),
+        "precision@10" => precision_at_k(ranked, relevant, 10),
+        "precision@5" => precision_at_k(ranked, relevant, 5),
+        "precision@1" => precision_at_k(ranked, relevant, 1),
+        "recall@10" => recall_at_k(ranked, relevant, 10),
+        "recall@5" => recall_at_k(ranked, relevant, 5),
+        "mrr" => mrr(ranked, relevant),
+        "ap" | "map" => average_precision(ranked, relevant),
+        "err@10" => err_at_k(ranked, relevant, 10),
+        "rbp@10" => rbp_at_k(ranked, relevant, 10, 0.95),
+        "f1@10" => f_measure_at_k(ranked, relevant, 10, 1.0),
+        "success@10" => success_at_k(ranked, relevant, 10),
+        "r_precision" => r_precision(ranked, relevant),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Compute a rank-cutoff binary metric by base name at cutoff `k`, or `None`
+/// if the base name is unknown.
+fn compute_binary_metric_at_k<I: Eq + std::hash::Hash>(
+    metric_base: &str,
+    ranked: &[I],
+    relevant: &HashSet<I>,
+    k: usize,
+) -> Option<f64> {
+    let value = match metric_base {
+        "ndcg" => ndcg_at_k(ranked, relevant, k),
+        "precision" => precision_at_k(ranked, relevant, k),
+        "recall" => recall_at_k(ranked, relevant, k),
+        "err" => err_at_k(ranked, relevant, k),
+        "rbp" => rbp_at_k(ranked, relevant, k, 0.95),
+        "f1" => f_measure_at_k(ranked, relevant, k, 1.0),
+        "success" => success_at_k(ranked, relevant, k),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Compute a graded relevance metric by name, or `None` if the name is unknown.
+fn compute_graded_metric(
+    metric_name: &str,
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+) -> Option<f64> {
+    let value = match metric_name {
+        "graded_ndcg@10" => graded::compute_ndcg(ranked, qrels, 10),
+        "graded_ndcg@5" => graded::compute_ndcg(ranked, qrels, 5),
+        "graded_map" => graded::compute_map(ranked, qrels),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Mean of each metric across the queries that report it, and the number of
+/// those queries.
+fn mean_aggregates(
+    query_results: &[QueryResults],
+) -> (HashMap<String, f64>, HashMap<String, usize>) {
+    let mut metric_sums: HashMap<String, f64> = HashMap::new();
+    let mut metric_counts: HashMap<String, usize> = HashMap::new();
+
+    for query_result in query_results {
+        for (name, value) in &query_result.metrics {
+            *metric_sums.entry(name.clone()).or_insert(0.0) += value;
+            *metric_counts.entry(name.clone()).or_insert(0) += 1;
+        }
+    }
+
+    let means = metric_sums
+        .into_iter()
+        .map(|(name, sum)| {
+            let count = metric_counts.get(&name).copied().unwrap_or(1);
+            (name, sum / count as f64)
+        })
+        .collect();
+
+    (means, metric_counts)
+}
+
+/// A ranked run whose document IDs borrow from the input runs.
+type BorrowedRun<'a> = Vec<(&'a str, f32)>;
+
+/// Runs and qrels grouped by query, for functions that evaluate a single run
+/// per query. IDs borrow from the input runs and qrels rather than being cloned.
+struct GroupedQueries<'a> {
+    runs_by_query: HashMap<&'a str, HashMap<&'a str, BorrowedRun<'a>>>,
+    qrels_by_query: HashMap<&'a str, HashMap<&'a str, u32>>,
+}
+
+/// One qrels query as seen by [`GroupedQueries::iter`].
+struct QueryView<'a> {
+    query_id: &'a str,
+    /// The run with the smallest run tag, or `None` if the query has no run.
+    ranked_run: Option<&'a BorrowedRun<'a>>,
+    /// Document IDs of `ranked_run` in order (empty without a run).
+    ranked_ids: Vec<&'a str>,
+    /// Documents judged relevant (grade > 0).
+    relevant: HashSet<&'a str>,
+    qrels: &'a HashMap<&'a str, u32>,
+}
+
+impl<'a> GroupedQueries<'a> {
+    fn new(runs: &'a [TrecRun], qrels: &'a [Qrel]) -> Self {
+        Self::new_ordered(runs, qrels, OrderBy::Score)
+    }
+
+    /// Group like [`group_runs_by_query_ordered`](crate::trec::group_runs_by_query_ordered)
+    /// and [`group_qrels_by_query`](crate::trec::group_qrels_by_query), where a
+    /// later judgment of the same document overrides an earlier one.
+    fn new_ordered(runs: &'a [TrecRun], qrels: &'a [Qrel], order_by: OrderBy) -> Self {
+        use crate::trec::sort_run_entries;
+
+        let mut grouped: HashMap<&str, HashMap<&str, Vec<&TrecRun>>> = HashMap::new();
+        for run in runs {
+            grouped
+                .entry(run.query_id.as_str())
+                .or_default()
+                .entry(run.run_tag.as_str())
+                .or_default()
+                .push(run);
+        }
+        let runs_by_query = grouped
+            .into_iter()
+            .map(|(query_id, query_runs)| {
+                let query_runs = query_runs
+                    .into_iter()
+                    .map(|(run_tag, mut entries)| {
+                        sort_run_entries(&mut entries, order_by);
+                        let ordered = entries
+                            .into_iter()
+                            .map(|run| (run.doc_id.as_str(), run.score))
+                            .collect();
+                        (run_tag, ordered)
+                    })
+                    .collect();
+                (query_id, query_runs)
+            })
+            .collect();
+
+        let mut qrels_by_query: HashMap<&str, HashMap<&str, u32>> = HashMap::new();
+        for qrel in qrels {
+            qrels_by_query
+                .entry(qrel.query_id.as_str())
+                .or_default()
+                .insert(qrel.doc_id.as_str(), qrel.relevance);
+        }
+
+        Self {
+            runs_by_query,
+            qrels_by_query,
+        }
+    }
+
+    /// Every qrels query with its ranking and relevant set. When a query has
+    /// several run tags, the lexicographically smallest is used so results do
+    /// not depend on `HashMap` iteration order.
+    fn iter(&self) -> impl Iterator<Item = QueryView<'_>> {
+        use crate::trec::first_tag_run;
+
+        self.qrels_by_query.iter().map(|(&query_id, query_qrels)| {
+            let ranked_run = self.runs_by_query.get(query_id).and_then(first_tag_run);
+            QueryView {
+                query_id,
+                ranked_run,
+                ranked_ids: ranked_run
+                    .map(|run| run.iter().map(|&(id, _)| id).collect())
+                    .unwrap_or_default(),
+                relevant: query_qrels
+                    .iter()
+                    .filter(|(_, &rel)| rel > 0)
+                    .map(|(&id, _)| id)
+                    .collect(),
+                qrels: query_qrels,
+            }
+        })
+    }
 }
 
 /// Evaluate a batch of rankings using binary relevance metrics.
@@ -60,37 +282,20 @@ pub fn evaluate_batch_binary<I: Eq + std::hash::Hash + Clone>(
     );
 
     let mut query_results = Vec::new();
-    let mut metric_sums: HashMap<String, f64> = HashMap::new();
-    let mut metric_counts: HashMap<String, usize> = HashMap::new();
 
     for (_i, (ranked, relevant)) in rankings.iter().zip(qrels.iter()).enumerate() {
         let mut query_metrics = HashMap::new();
 
         for metric_name in metrics {
-            let value = match *metric_name {
-                "ndcg@10" => ndcg_at_k(ranked, relevant, 10),
-                "ndcg@5" => ndcg_at_k(ranked, relevant, 5),
-                "precision@10" => precision_at_k(ranked, relevant, 10),
-                "precision@5" => precision_at_k(ranked, relevant, 5),
-                "precision@1" => precision_at_k(ranked, relevant, 1),
-                "recall@10" => recall_at_k(ranked, relevant, 10),
-                "recall@5" => recall_at_k(ranked, relevant, 5),
-                "mrr" => mrr(ranked, relevant),
-                "ap" | "map" => average_precision(ranked, relevant),
-                "err@10" => err_at_k(ranked, relevant, 10),
-                "rbp@10" => rbp_at_k(ranked, relevant, 10, 0.95),
-                "f1@10" => f_measure_at_k(ranked, relevant, 10, 1.0),
-                "success@10" => success_at_k(ranked, relevant, 10),
-                "r_precision" => r_precision(ranked, relevant),
-                _ => {
+            let value = match compute_binary_metric(metric_name, ranked, relevant) {
+                Some(value) => value,
+                None => {
                     eprintln!("Unknown metric: {}", metric_name);
                     continue;
                 }
             };
 
             query_metrics.insert(metric_name.to_string(), value);
-            *metric_sums.entry(metric_name.to_string()).or_insert(0.0) += value;
-            *metric_counts.entry(metric_name.to_string()).or_insert(0) += 1;
         }
 
         query_results.push(QueryResults {
@@ -99,24 +304,41 @@ pub fn evaluate_batch_binary<I: Eq + std::hash::Hash + Clone>(
         });
     }
 
-    // Compute aggregated means
-    let aggregated: HashMap<String, f64> = metric_sums
-        .into_iter()
-        .map(|(name, sum)| {
-            let count = metric_counts.get(&name).copied().unwrap_or(1);
-            (name, sum / count as f64)
-        })
-        .collect();
+    let (aggregated, effective_query_count) = mean_aggregates(&query_results);
 
     BatchResults {
         query_results,
         aggregated,
+        effective_query_count,
+        skipped: Vec::new(),
     }
 }
 
+/// Options for [`evaluate_trec_batch_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct TrecBatchOptions {
+    /// Count qrels queries with no retrieved documents as zero-valued
+    /// (trec_eval's `-c` "complete" mode). By default such queries are skipped,
+    /// which excludes them from the aggregate denominator.
+    pub complete: bool,
+    /// Column that determines document order within each run. Defaults to
+    /// score; use [`OrderBy::Rank`] for runs with constant or unreliable scores,
+    /// or [`OrderBy::Presorted`] to trust file order and skip sorting.
+    pub order_by: OrderBy,
+    /// For each requested `ndcg@k` metric, also report `ndcg@k_ceiling`: the
+    /// nDCG@k of the best reordering of the retrieved documents (relevant ones
+    /// first). The gap to `ndcg@k` is the query's reranking potential.
+    pub ndcg_ceiling: bool,
+}
+
 /// Evaluate TREC runs and qrels in batch.
 ///
 /// Groups runs and qrels by query, then evaluates each query.
+/// Queries without any retrieved documents are skipped; see
+/// [`evaluate_trec_batch_with_options`] to count them as zero instead.
+/// When a run file holds several run tags, each query is evaluated with the
+/// lexicographically smallest tag; use [`evaluate_all_systems`] to evaluate
+/// every tag.
 ///
 /// # Arguments
 ///
@@ -126,120 +348,2071 @@ pub fn evaluate_batch_binary<I: Eq + std::hash::Hash + Clone>(
 ///
 /// # Returns
 ///
-/// `BatchResults` with per-query results and aggregated means.
+/// `BatchResults` with per-query results, sorted by query ID, and aggregated means.
 pub fn evaluate_trec_batch(
     runs: &[TrecRun],
     qrels: &[Qrel],
     metrics: &[&str],
 ) -> BatchResults {
-    use crate::trec::{group_qrels_by_query, group_runs_by_query};
+    evaluate_trec_batch_with_options(runs, qrels, metrics, &TrecBatchOptions::default())
+}
 
-    let runs_by_query = group_runs_by_query(runs);
-    let qrels_by_query = group_qrels_by_query(qrels);
+/// Evaluate TREC runs and qrels in batch with explicit options.
+///
+/// Behaves like [`evaluate_trec_batch`], with the differences controlled by
+/// `options`.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::{evaluate_trec_batch_with_options, TrecBatchOptions};
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new().add("1", "doc1", 0.9, "run1").build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).add("2", "doc2", 1).build();
+///
+/// let options = TrecBatchOptions {
+///     complete: true,
+///     ..Default::default()
+/// };
+/// let results = evaluate_trec_batch_with_options(&runs, &qrels, &["mrr"], &options);
+/// assert_eq!(results.query_results.len(), 2);
+/// assert!((results.aggregated["mrr"] - 0.5).abs() < 1e-9);
+/// ```
+pub fn evaluate_trec_batch_with_options(
+    runs: &[TrecRun],
+    qrels: &[Qrel],
+    metrics: &[&str],
+    options: &TrecBatchOptions,
+) -> BatchResults {
+    let grouped = GroupedQueries::new_ordered(runs, qrels, options.order_by);
 
     let mut query_results = Vec::new();
-    let mut metric_sums: HashMap<String, f64> = HashMap::new();
-    let mut metric_counts: HashMap<String, usize> = HashMap::new();
-
-    for (query_id, query_qrels) in &qrels_by_query {
-        // Get first run for this query (or skip if no runs)
-        let query_runs = match runs_by_query.get(query_id) {
-            Some(runs) => runs,
-            None => continue,
-        };
+    let mut skipped: Vec<SkippedQuery> = grouped
+        .runs_by_query
+        .keys()
+        .filter(|query_id| !grouped.qrels_by_query.contains_key(*query_id))
+        .map(|query_id| SkippedQuery {
+            query_id: query_id.to_string(),
+            reason: SkipReason::MissingFromQrels,
+        })
+        .collect();
 
-        // Use first run tag (or combine all runs)
-        let first_run_tag = query_runs.keys().next();
-        if first_run_tag.is_none() {
+    for query in grouped.iter() {
+        // In complete mode a query without runs is evaluated as an empty
+        // ranking (all metrics 0). Runs are already ordered by `options.order_by`
+        if query.ranked_run.is_none() && !options.complete {
+            skipped.push(SkippedQuery {
+                query_id: query.query_id.to_string(),
+                reason: SkipReason::MissingFromRuns,
+            });
             continue;
         }
-
-        let run_tag = first_run_tag.unwrap();
-        let ranked_run = &query_runs[run_tag];
-
-        // Convert to ranked list
-        let mut ranked: Vec<(&String, f32)> = ranked_run.iter().map(|(id, score)| (id, *score)).collect();
-        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-        let ranked_ids: Vec<&String> = ranked.iter().map(|(id, _)| *id).collect();
-
-        // Convert qrels to HashSet
-        let relevant: HashSet<_> = query_qrels
-            .iter()
-            .filter(|(_, &rel)| rel > 0)
-            .map(|(id, _)| id)
-            .collect();
+        let QueryView { query_id, ranked_ids, relevant, .. } = query;
 
         let mut query_metrics = HashMap::new();
 
         for metric_name in metrics {
-            let value = match *metric_name {
-                "ndcg@10" => ndcg_at_k(&ranked_ids, &relevant, 10),
-                "ndcg@5" => ndcg_at_k(&ranked_ids, &relevant, 5),
-                "precision@10" => precision_at_k(&ranked_ids, &relevant, 10),
-                "precision@5" => precision_at_k(&ranked_ids, &relevant, 5),
-                "precision@1" => precision_at_k(&ranked_ids, &relevant, 1),
-                "recall@10" => recall_at_k(&ranked_ids, &relevant, 10),
-                "recall@5" => recall_at_k(&ranked_ids, &relevant, 5),
-                "mrr" => mrr(&ranked_ids, &relevant),
-                "ap" | "map" => average_precision(&ranked_ids, &relevant),
-                "err@10" => err_at_k(&ranked_ids, &relevant, 10),
-                "rbp@10" => rbp_at_k(&ranked_ids, &relevant, 10, 0.95),
-                "f1@10" => f_measure_at_k(&ranked_ids, &relevant, 10, 1.0),
-                "success@10" => success_at_k(&ranked_ids, &relevant, 10),
-                "r_precision" => r_precision(&ranked_ids, &relevant),
-                _ => {
+            let value = match compute_binary_metric(metric_name, &ranked_ids, &relevant) {
+                Some(value) => value,
+                None => {
                     eprintln!("Unknown metric: {}", metric_name);
                     continue;
                 }
             };
 
             query_metrics.insert(metric_name.to_string(), value);
-            *metric_sums.entry(metric_name.to_string()).or_insert(0.0) += value;
-            *metric_counts.entry(metric_name.to_string()).or_insert(0) += 1;
+
+            if options.ndcg_ceiling {
+                if let Some(k) = metric_name.strip_prefix("ndcg@").and_then(|k| k.parse().ok()) {
+                    // Best reordering: retrieved relevant documents first
+                    let (mut reranked, rest): (Vec<&str>, Vec<&str>) =
+                        ranked_ids.iter().partition(|id| relevant.contains(*id));
+                    reranked.extend(rest);
+                    query_metrics.insert(
+                        format!("{}_ceiling", metric_name),
+                        ndcg_at_k(&reranked, &relevant, k),
+                    );
+                }
+            }
         }
 
         query_results.push(QueryResults {
-            query_id: query_id.clone(),
+            query_id: query_id.to_string(),
             metrics: query_metrics,
         });
     }
 
-    // Compute aggregated means
-    let aggregated: HashMap<String, f64> = metric_sums
+    query_results.sort_by(|a, b| a.query_id.cmp(&b.query_id));
+    let (aggregated, effective_query_count) = mean_aggregates(&query_results);
+    skipped.sort_by(|a, b| a.query_id.cmp(&b.query_id));
+
+    BatchResults {
+        query_results,
+        aggregated,
+        effective_query_count,
+        skipped,
+    }
+}
+
+/// Evaluate every system in a multi-system run file.
+///
+/// This is the primary entry point for a full experiment: runs are split by
+/// `run_tag` and each system is evaluated independently with
+/// [`evaluate_trec_batch`] against the same qrels. Use [`aggregate_table`] to
+/// flatten the result into one row per (system, metric).
+///
+/// # Returns
+///
+/// Map from run tag to that system's `BatchResults`.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::evaluate_all_systems;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new()
+///     .add("1", "doc1", 0.9, "bm25")
+///     .add("1", "doc2", 0.9, "dense")
+///     .build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).build();
+///
+/// let systems = evaluate_all_systems(&runs, &qrels, &["mrr"]);
+/// assert_eq!(systems["bm25"].aggregated["mrr"], 1.0);
+/// assert_eq!(systems["dense"].aggregated["mrr"], 0.0);
+/// ```
+pub fn evaluate_all_systems(
+    runs: &[TrecRun],
+    qrels: &[Qrel],
+    metrics: &[&str],
+) -> HashMap<String, BatchResults> {
+    let mut runs_by_tag: HashMap<&str, Vec<TrecRun>> = HashMap::new();
+    for run in runs {
+        runs_by_tag
+            .entry(run.run_tag.as_str())
+            .or_default()
+            .push(run.clone());
+    }
+
+    runs_by_tag
         .into_iter()
-        .map(|(name, sum)| {
-            let count = metric_counts.get(&name).copied().unwrap_or(1);
-            (name, sum / count as f64)
+        .map(|(tag, tag_runs)| (tag.to_string(), evaluate_trec_batch(&tag_runs, qrels, metrics)))
+        .collect()
+}
+
+/// Flatten per-system results into a tidy `(system, metric, mean)` table.
+///
+/// Rows are sorted by system, then metric, so the output is deterministic and
+/// ready to write out as CSV or load into a dataframe.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::{aggregate_table, evaluate_all_systems};
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new().add("1", "doc1", 0.9, "bm25").build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).build();
+///
+/// let table = aggregate_table(&evaluate_all_systems(&runs, &qrels, &["mrr", "precision@1"]));
+/// assert_eq!(table[0], ("bm25".to_string(), "mrr".to_string(), 1.0));
+/// assert_eq!(table.len(), 2);
+/// ```
+pub fn aggregate_table(systems: &HashMap<String, BatchResults>) -> Vec<(String, String, f64)> {
+    let mut rows: Vec<(String, String, f64)> = systems
+        .iter()
+        .flat_map(|(system, results)| {
+            results
+                .aggregated
+                .iter()
+                .map(move |(metric, &value)| (system.clone(), metric.clone(), value))
         })
         .collect();
+    rows.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+    rows
+}
+
+/// Evaluate TREC runs with a per-query cutoff derived from the number of relevant documents.
+///
+/// For each query, `k = k_fn(R)` where `R` is the number of documents with
+/// grade > 0 in `qrels`, and the metric `metric_base` is computed at that
+/// cutoff. With `k_fn = |r| r` and `metric_base = "precision"` this is
+/// R-precision. Queries without runs are skipped, and the smallest run tag is
+/// used for each query (as in [`evaluate_trec_batch`]).
+///
+/// Supported bases: `ndcg`, `precision`, `recall`, `err`, `rbp`, `f1`, `success`.
+///
+/// # Returns
+///
+/// `BatchResults` with a single metric named `metric_base`. Empty if the base
+/// is unknown.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::evaluate_with_dynamic_k;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new()
+///     .add("1", "doc1", 0.9, "run")
+///     .add("1", "doc2", 0.8, "run")
+///     .add("1", "doc3", 0.7, "run")
+///     .build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).add("1", "doc3", 1).build();
+///
+/// // Precision at twice the number of relevant documents
+/// let results = evaluate_with_dynamic_k(&runs, &qrels, "precision", |r| 2 * r);
+/// assert!((results.aggregated["precision"] - 0.5).abs() < 1e-9);
+/// ```
+pub fn evaluate_with_dynamic_k(
+    runs: &[TrecRun],
+    qrels: &[Qrel],
+    metric_base: &str,
+    k_fn: impl Fn(usize) -> usize,
+) -> BatchResults {
+    let grouped = GroupedQueries::new(runs, qrels);
+
+    let mut query_results = Vec::new();
+
+    for query in grouped.iter() {
+        if query.ranked_run.is_none() {
+            continue;
+        }
+        let QueryView { query_id, ranked_ids, relevant, .. } = query;
+        let k = k_fn(relevant.len());
+
+        let value = match compute_binary_metric_at_k(metric_base, &ranked_ids, &relevant, k) {
+            Some(value) => value,
+            None => {
+                eprintln!("Unknown metric: {}", metric_base);
+                break;
+            }
+        };
+
+        let mut query_metrics = HashMap::new();
+        query_metrics.insert(metric_base.to_string(), value);
+
+        query_results.push(QueryResults {
+            query_id: query_id.to_string(),
+            metrics: query_metrics,
+        });
+    }
+
+    let (aggregated, effective_query_count) = mean_aggregates(&query_results);
 
     BatchResults {
         query_results,
         aggregated,
+        effective_query_count,
+        skipped: Vec::new(),
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::collections::HashSet;
+/// Evaluate TREC runs for one metric at several cutoffs in a single pass.
+///
+/// Produces `"{metric_base}@{k}"` entries for each `k` in `ks`, with per-query
+/// and aggregated values. Each query's ranking and relevant set are built once
+/// and shared across all cutoffs. Queries without runs are skipped, and the
+/// smallest run tag is used for each query (as in [`evaluate_trec_batch`]).
+///
+/// Supports the same bases as [`evaluate_with_dynamic_k`].
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::evaluate_multi_k;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new()
+///     .add("1", "doc1", 0.9, "run")
+///     .add("1", "doc2", 0.8, "run")
+///     .build();
+/// let qrels = QrelBuilder::new().add("1", "doc2", 1).build();
+///
+/// let results = evaluate_multi_k(&runs, &qrels, "precision", &[1, 2]);
+/// assert_eq!(results.aggregated["precision@1"], 0.0);
+/// assert_eq!(results.aggregated["precision@2"], 0.5);
+/// ```
+pub fn evaluate_multi_k(
+    runs: &[TrecRun],
+    qrels: &[Qrel],
+    metric_base: &str,
+    ks: &[usize],
+) -> BatchResults {
+    let grouped = GroupedQueries::new(runs, qrels);
 
-    #[test]
-    fn test_evaluate_batch_binary() {
-        let rankings = vec![
-            vec!["doc1", "doc2", "doc3"],
-            vec!["doc4", "doc5", "doc6"],
-        ];
-        let qrels = vec![
-            ["doc1", "doc3"].into_iter().collect::<HashSet<_>>(),
-            ["doc4"].into_iter().collect::<HashSet<_>>(),
-        ];
+    let mut query_results = Vec::new();
 
-        let results = evaluate_batch_binary(&rankings, &qrels, &["ndcg@10", "precision@5"]);
+    for query in grouped.iter() {
+        if query.ranked_run.is_none() {
+            continue;
+        }
+        let QueryView { query_id, ranked_ids, relevant, .. } = query;
 
-        assert_eq!(results.query_results.len(), 2);
-        assert!(results.aggregated.contains_key("ndcg@10"));
-        assert!(results.aggregated.contains_key("precision@5"));
+        let mut query_metrics = HashMap::new();
+        for &k in ks {
+            match compute_binary_metric_at_k(metric_base, &ranked_ids, &relevant, k) {
+                Some(value) => {
+                    query_metrics.insert(format!("{}@{}", metric_base, k), value);
+                }
+                None => {
+                    eprintln!("Unknown metric: {}", metric_base);
+                    break;
+                }
+            }
+        }
+
+        query_results.push(QueryResults {
+            query_id: query_id.to_string(),
+            metrics: query_metrics,
+        });
+    }
+
+    let (aggregated, effective_query_count) = mean_aggregates(&query_results);
+
+    BatchResults {
+        query_results,
+        aggregated,
+        effective_query_count,
+        skipped: Vec::new(),
+    }
+}
+
+/// Mean of one metric under each qrels version, from [`qrels_sensitivity`].
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct QrelsSensitivity {
+    /// Qrels version name -> mean of the metric under that version.
+    pub means: HashMap<String, f64>,
+    /// Max − min of `means` (0.0 for fewer than two versions).
+    pub range: f64,
+}
+
+/// Evaluate a run against several versions of the qrels to gauge judgment sensitivity.
+///
+/// Runs are evaluated with [`evaluate_trec_batch`] under each named qrels
+/// version, giving the mean of `metric` per version and the range of those
+/// means. A small range means conclusions are stable as judgments change.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::qrels_sensitivity;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new().add("1", "doc1", 0.9, "run").add("1", "doc2", 0.8, "run").build();
+/// let v1 = QrelBuilder::new().add("1", "doc1", 1).build();
+/// let v2 = QrelBuilder::new().add("1", "doc1", 0).add("1", "doc2", 1).build();
+///
+/// let sensitivity = qrels_sensitivity(&runs, &[("v1".to_string(), v1), ("v2".to_string(), v2)], "mrr");
+/// assert!((sensitivity.range - 0.5).abs() < 1e-9);
+/// ```
+pub fn qrels_sensitivity(
+    runs: &[TrecRun],
+    qrels_versions: &[(String, Vec<Qrel>)],
+    metric: &str,
+) -> QrelsSensitivity {
+    let mut means = HashMap::new();
+    for (version, qrels) in qrels_versions {
+        let results = evaluate_trec_batch(runs, qrels, &[metric]);
+        if let Some(&mean) = results.aggregated.get(metric) {
+            means.insert(version.clone(), mean);
+        }
+    }
+
+    let range = if means.is_empty() {
+        0.0
+    } else {
+        let max = means.values().copied().fold(f64::NEG_INFINITY, f64::max);
+        let min = means.values().copied().fold(f64::INFINITY, f64::min);
+        max - min
+    };
+
+    QrelsSensitivity { means, range }
+}
+
+/// Fraction of queries with a relevant document at each rank position.
+///
+/// Returns a vector of length `max_rank` whose entry `i` is the share of
+/// evaluated queries whose ranking has a relevant document (grade > 0) at rank
+/// `i + 1`. Rankings shorter than `max_rank` count as non-relevant at the
+/// missing positions. Queries without runs are skipped, and the smallest run tag
+/// is used for each query (as in [`evaluate_trec_batch`]).
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::relevance_by_rank;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new().add("1", "doc1", 0.9, "run").add("1", "doc2", 0.8, "run").build();
+/// let qrels = QrelBuilder::new().add("1", "doc2", 1).build();
+///
+/// assert_eq!(relevance_by_rank(&runs, &qrels, 3), vec![0.0, 1.0, 0.0]);
+/// ```
+pub fn relevance_by_rank(runs: &[TrecRun], qrels: &[Qrel], max_rank: usize) -> Vec<f64> {
+    let grouped = GroupedQueries::new(runs, qrels);
+
+    let mut relevant_counts = vec![0usize; max_rank];
+    let mut num_queries = 0;
+
+    for query in grouped.iter() {
+        if query.ranked_run.is_none() {
+            continue;
+        }
+        num_queries += 1;
+
+        for (count, doc_id) in relevant_counts.iter_mut().zip(&query.ranked_ids) {
+            if query.relevant.contains(doc_id) {
+                *count += 1;
+            }
+        }
     }
+
+    if num_queries == 0 {
+        return vec![0.0; max_rank];
+    }
+
+    relevant_counts
+        .into_iter()
+        .map(|count| count as f64 / num_queries as f64)
+        .collect()
+}
+
+/// Comparison of one metric between a new run and a baseline.
+#[derive(Debug, Clone)]
+pub struct MetricComparison {
+    pub new_mean: f64,
+    pub baseline_mean: f64,
+    pub mean_delta: f64, // new_mean - baseline_mean
+    pub t_test: TTestResult,
+    pub wins: usize,   // queries where new > baseline
+    pub losses: usize, // queries where new < baseline
+    pub ties: usize,
+}
+
+/// Report produced by [`evaluate_and_compare`].
+#[derive(Debug, Clone)]
+pub struct ComparisonReport {
+    pub new: BatchResults,
+    pub baseline: BatchResults,
+    pub metrics: HashMap<String, MetricComparison>,
 }
 
+/// Evaluate a new run and a baseline run, and compare them metric by metric.
+///
+/// Both runs are evaluated with [`evaluate_trec_batch`]. For each metric the
+/// report holds both means, the mean delta, a paired t-test (`alpha = 0.05`),
+/// and per-query win/loss/tie counts. Per-query values are paired by query ID;
+/// the means are over each run's own evaluated queries.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::evaluate_and_compare;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let new = RunBuilder::new().add("1", "doc1", 0.9, "new").add("1", "doc2", 0.8, "new").build();
+/// let base = RunBuilder::new().add("1", "doc2", 0.9, "base").add("1", "doc1", 0.8, "base").build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).build();
+///
+/// let report = evaluate_and_compare(&new, &base, &qrels, &["mrr"]);
+/// assert_eq!(report.metrics["mrr"].wins, 1);
+/// assert!((report.metrics["mrr"].mean_delta - 0.5).abs() < 1e-9);
+/// ```
+pub fn evaluate_and_compare(
+    new_runs: &[TrecRun],
+    baseline_runs: &[TrecRun],
+    qrels: &[Qrel],
+    metrics: &[&str],
+) -> ComparisonReport {
+    let new = evaluate_trec_batch(new_runs, qrels, metrics);
+    let baseline = evaluate_trec_batch(baseline_runs, qrels, metrics);
+
+    let mut comparisons = HashMap::new();
+    for metric in metrics {
+        let (new_mean, baseline_mean) = match (
+            new.aggregated.get(*metric),
+            baseline.aggregated.get(*metric),
+        ) {
+            (Some(&new_mean), Some(&baseline_mean)) => (new_mean, baseline_mean),
+            _ => continue,
+        };
+
+        let (new_scores, baseline_scores) = paired_metric_values(&new, &baseline, metric);
+        let (mut wins, mut losses, mut ties) = (0, 0, 0);
+        for (a, b) in new_scores.iter().zip(&baseline_scores) {
+            match a.total_cmp(b) {
+                std::cmp::Ordering::Greater => wins += 1,
+                std::cmp::Ordering::Less => losses += 1,
+                std::cmp::Ordering::Equal => ties += 1,
+            }
+        }
+
+        comparisons.insert(
+            metric.to_string(),
+            MetricComparison {
+                new_mean,
+                baseline_mean,
+                mean_delta: new_mean - baseline_mean,
+                t_test: paired_t_test(&new_scores, &baseline_scores, 0.05),
+                wins,
+                losses,
+                ties,
+            },
+        );
+    }
+
+    ComparisonReport {
+        new,
+        baseline,
+        metrics: comparisons,
+    }
+}
+
+/// How [`aggregate_f1`] combines precision and recall across queries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum F1AggMode {
+    /// Macro-averaged F: compute F per query, then take the mean.
+    MeanOfPerQuery,
+    /// F of the mean precision and the mean recall.
+    OfMeans,
+}
+
+/// Aggregate F-measure at cutoff `k` across queries.
+///
+/// Formula: `F_β = (1 + β²) × P × R / (β² × P + R)`
+///
+/// Papers report "aggregate F1" in two incompatible ways:
+/// - [`F1AggMode::MeanOfPerQuery`]: `mean_q F_β(P_q, R_q)`. Every query
+///   counts equally, and a query with P or R of zero contributes 0.
+/// - [`F1AggMode::OfMeans`]: `F_β(mean_q P_q, mean_q R_q)`. Always at least
+///   the per-query mean, and higher when precision and recall peak on
+///   different queries.
+///
+/// Reads the per-query `precision@{k}` and `recall@{k}` metrics, so `results`
+/// must have been evaluated with both. Queries missing either are ignored.
+///
+/// # Returns
+///
+/// The aggregate F_β, or 0.0 if no query has both metrics.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::{aggregate_f1, evaluate_trec_batch, F1AggMode};
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new().add("1", "doc1", 0.9, "run").build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).build();
+/// let results = evaluate_trec_batch(&runs, &qrels, &["precision@5", "recall@5"]);
+///
+/// // P@5 = 0.2, R@5 = 1.0
+/// let f1 = aggregate_f1(&results, 5, 1.0, F1AggMode::MeanOfPerQuery);
+/// assert!((f1 - 2.0 * 0.2 / 1.2).abs() < 1e-9);
+/// ```
+pub fn aggregate_f1(results: &BatchResults, k: usize, beta: f64, mode: F1AggMode) -> f64 {
+    let precision_key = format!("precision@{}", k);
+    let recall_key = format!("recall@{}", k);
+
+    let pairs: Vec<(f64, f64)> = results
+        .query_results
+        .iter()
+        .filter_map(|qr| Some((*qr.metrics.get(&precision_key)?, *qr.metrics.get(&recall_key)?)))
+        .collect();
+    if pairs.is_empty() {
+        return 0.0;
+    }
+
+    let f_beta = |precision: f64, recall: f64| {
+        let beta_sq = beta * beta;
+        let denominator = beta_sq * precision + recall;
+        if denominator > 0.0 {
+            (1.0 + beta_sq) * precision * recall / denominator
+        } else {
+            0.0
+        }
+    };
+
+    let n = pairs.len() as f64;
+    match mode {
+        F1AggMode::MeanOfPerQuery => pairs.iter().map(|&(p, r)| f_beta(p, r)).sum::<f64>() / n,
+        F1AggMode::OfMeans => {
+            let mean_precision = pairs.iter().map(|&(p, _)| p).sum::<f64>() / n;
+            let mean_recall = pairs.iter().map(|&(_, r)| r).sum::<f64>() / n;
+            f_beta(mean_precision, mean_recall)
+        }
+    }
+}
+
+/// Aggregate per-query metrics weighted by each query's number of relevant documents.
+///
+/// Formula: `mean_w(m) = Σ_q R_q × m_q / Σ_q R_q`
+///
+/// Where `R_q` is the number of documents with grade > 0 in `qrels` for query
+/// q. Queries with many relevant documents count proportionally more, which
+/// suits recall-centric reporting; queries with none get zero weight. Every
+/// metric in `results.aggregated` appears in the output (0.0 if the total
+/// weight of the queries reporting it is 0).
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::{aggregate_by_relevant_count, evaluate_trec_batch};
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new().add("1", "a", 0.9, "run").add("2", "x", 0.9, "run").build();
+/// let qrels = QrelBuilder::new()
+///     .add("1", "a", 1)
+///     .add("2", "y", 1)
+///     .add("2", "z", 1)
+///     .add("2", "w", 1)
+///     .build();
+/// let results = evaluate_trec_batch(&runs, &qrels, &["mrr"]);
+///
+/// // Query 1 (mrr 1.0) has weight 1, query 2 (mrr 0.0) has weight 3
+/// let weighted = aggregate_by_relevant_count(&results, &qrels);
+/// assert!((weighted["mrr"] - 0.25).abs() < 1e-9);
+/// ```
+pub fn aggregate_by_relevant_count(results: &BatchResults, qrels: &[Qrel]) -> HashMap<String, f64> {
+    let mut relevant_counts: HashMap<&str, usize> = HashMap::new();
+    for qrel in qrels.iter().filter(|q| q.relevance > 0) {
+        *relevant_counts.entry(qrel.query_id.as_str()).or_insert(0) += 1;
+    }
+
+    results
+        .aggregated
+        .keys()
+        .map(|name| {
+            let (weighted_sum, total_weight) = results
+                .query_results
+                .iter()
+                .filter_map(|qr| {
+                    let value = qr.metrics.get(name)?;
+                    let weight = relevant_counts.get(qr.query_id.as_str()).copied().unwrap_or(0);
+                    Some((value * weight as f64, weight as f64))
+                })
+                .fold((0.0, 0.0), |acc, (v, w)| (acc.0 + v, acc.1 + w));
+            let mean = if total_weight > 0.0 {
+                weighted_sum / total_weight
+            } else {
+                0.0
+            };
+            (name.clone(), mean)
+        })
+        .collect()
+}
+
+/// Compute recall@k against the judged relevant pool for each query.
+///
+/// Formula: `pooled_recall@k = |retrieved@k ∩ judged_relevant| / |judged_relevant|`
+///
+/// The denominator is the number of documents with grade > 0 in `qrels` for
+/// the query, i.e. the relevant documents the assessors actually found. With
+/// shallow pools the true relevant set is larger and unknown, so this is an
+/// upper bound on true recall; when qrels are complete it equals standard
+/// recall@k. Queries without judged relevant documents are skipped, and the
+/// smallest run tag is used for each query (as in [`evaluate_trec_batch`]).
+///
+/// # Returns
+///
+/// `BatchResults` with a single metric named `pooled_recall@{k}`.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::pooled_recall_at_k;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new()
+///     .add("1", "doc1", 0.9, "run")
+///     .add("1", "doc2", 0.8, "run")
+///     .build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).add("1", "doc3", 1).build();
+///
+/// let results = pooled_recall_at_k(&runs, &qrels, 2);
+/// assert!((results.aggregated["pooled_recall@2"] - 0.5).abs() < 1e-9);
+/// ```
+pub fn pooled_recall_at_k(runs: &[TrecRun], qrels: &[Qrel], k: usize) -> BatchResults {
+    let grouped = GroupedQueries::new(runs, qrels);
+    let metric_name = format!("pooled_recall@{}", k);
+
+    let mut query_results = Vec::new();
+
+    for query in grouped.iter() {
+        // The pool is every judged relevant document; a query without runs
+        // keeps its empty ranking and scores 0
+        let QueryView { query_id, ranked_ids, relevant: pool, .. } = query;
+        if pool.is_empty() {
+            continue;
+        }
+
+        let mut query_metrics = HashMap::new();
+        query_metrics.insert(metric_name.clone(), recall_at_k(&ranked_ids, &pool, k));
+
+        query_results.push(QueryResults {
+            query_id: query_id.to_string(),
+            metrics: query_metrics,
+        });
+    }
+
+    let (aggregated, effective_query_count) = mean_aggregates(&query_results);
+
+    BatchResults {
+        query_results,
+        aggregated,
+        effective_query_count,
+        skipped: Vec::new(),
+    }
+}
+
+/// Per-query nDCG@k discounted by how much of the top k is judged.
+///
+/// Formula: `adjusted = nDCG@k × coverage@k`, where `coverage@k` is the
+/// fraction of the top k retrieved documents that have any judgment in
+/// `qrels` (relevant or not).
+///
+/// nDCG treats unjudged documents as non-relevant, so with low coverage it is
+/// an unreliable estimate. This is a heuristic reliability-weighted score, not
+/// an estimator of true nDCG: it only shrinks scores that rest on few
+/// judgments, making them comparable at a glance. Uses binary relevance
+/// (grade > 0) and the smallest run tag per query, as in [`evaluate_trec_batch`];
+/// queries without runs are skipped.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::coverage_adjusted_ndcg;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new()
+///     .add("1", "doc1", 0.9, "run")
+///     .add("1", "unjudged", 0.8, "run")
+///     .build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).build();
+///
+/// // nDCG@2 is 1.0, but only half of the top 2 is judged
+/// assert_eq!(coverage_adjusted_ndcg(&runs, &qrels, 2)["1"], 0.5);
+/// ```
+pub fn coverage_adjusted_ndcg(runs: &[TrecRun], qrels: &[Qrel], k: usize) -> HashMap<String, f64> {
+    let grouped = GroupedQueries::new(runs, qrels);
+
+    let mut adjusted = HashMap::new();
+    for query in grouped.iter() {
+        if query.ranked_run.is_none() {
+            continue;
+        }
+        let QueryView { query_id, ranked_ids, relevant, qrels: query_qrels, .. } = query;
+
+        let top_k = &ranked_ids[..k.min(ranked_ids.len())];
+        let coverage = if top_k.is_empty() {
+            0.0
+        } else {
+            let judged = top_k.iter().filter(|id| query_qrels.contains_key(**id)).count();
+            judged as f64 / top_k.len() as f64
+        };
+
+        adjusted.insert(query_id.to_string(), ndcg_at_k(&ranked_ids, &relevant, k) * coverage);
+    }
+
+    adjusted
+}
+
+/// Traffic-weighted mean nDCG@k.
+///
+/// Computes per-query nDCG@k as in [`evaluate_trec_batch`] (binary relevance,
+/// smallest run tag per query) and averages with `weights`, so frequently issued
+/// queries count for more. Queries missing from `weights` get weight 1.0;
+/// queries without runs are skipped. Returns 0.0 when the total weight is zero.
+///
+/// # Formula
+///
+/// ```text
+/// weighted_ndcg = Σ_q w_q · nDCG@k(q) / Σ_q w_q
+/// ```
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::weighted_ndcg;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+/// use std::collections::HashMap;
+///
+/// let runs = RunBuilder::new()
+///     .add("hit", "doc1", 0.9, "run")
+///     .add("miss", "doc2", 0.9, "run")
+///     .build();
+/// let qrels = QrelBuilder::new().add("hit", "doc1", 1).add("miss", "doc3", 1).build();
+///
+/// let weights = HashMap::from([("hit".to_string(), 3.0), ("miss".to_string(), 1.0)]);
+/// assert_eq!(weighted_ndcg(&runs, &qrels, 10, &weights), 0.75);
+/// ```
+pub fn weighted_ndcg(
+    runs: &[TrecRun],
+    qrels: &[Qrel],
+    k: usize,
+    weights: &HashMap<String, f64>,
+) -> f64 {
+    let grouped = GroupedQueries::new(runs, qrels);
+
+    let mut weighted_sum = 0.0;
+    let mut total_weight = 0.0;
+    for query in grouped.iter() {
+        if query.ranked_run.is_none() {
+            continue;
+        }
+        let QueryView { query_id, ranked_ids, relevant, .. } = query;
+
+        let weight = weights.get(query_id).copied().unwrap_or(1.0);
+        weighted_sum += weight * ndcg_at_k(&ranked_ids, &relevant, k);
+        total_weight += weight;
+    }
+
+    if total_weight > 0.0 {
+        weighted_sum / total_weight
+    } else {
+        0.0
+    }
+}
+
+/// Mean ranking depth at which recall first reaches a target.
+///
+/// For each query with judged relevant documents (grade > 0), finds the
+/// smallest k with recall@k ≥ `target_recall`, using the smallest run tag as in
+/// [`evaluate_trec_batch`]. A target of 1.0 therefore requires scanning to the
+/// query's last relevant document. Queries whose run never reaches the target,
+/// including queries with no run, are counted instead of averaged.
+///
+/// # Returns
+///
+/// `(mean depth over queries that reach the target, number that never do)`.
+/// The mean is 0.0 if no query reaches the target.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::mean_depth_for_recall;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new()
+///     .add("1", "doc1", 0.9, "run")
+///     .add("1", "doc2", 0.8, "run")
+///     .add("1", "doc3", 0.7, "run")
+///     .build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).add("1", "doc3", 1).build();
+///
+/// assert_eq!(mean_depth_for_recall(&runs, &qrels, 0.5), (1.0, 0));
+/// assert_eq!(mean_depth_for_recall(&runs, &qrels, 1.0), (3.0, 0));
+/// ```
+pub fn mean_depth_for_recall(runs: &[TrecRun], qrels: &[Qrel], target_recall: f64) -> (f64, usize) {
+    let grouped = GroupedQueries::new(runs, qrels);
+
+    let mut depths = Vec::new();
+    let mut never_reached = 0;
+
+    for query in grouped.iter() {
+        let QueryView { ranked_ids, relevant, .. } = query;
+        if relevant.is_empty() {
+            continue;
+        }
+
+        // A query without runs has no ranking and never reaches the target
+        let mut found = HashSet::new();
+        let depth = ranked_ids.iter().position(|id| {
+            if relevant.contains(id) {
+                found.insert(id);
+            }
+            // Tolerance so that e.g. 3 of 10 meets a target of 0.3
+            found.len() as f64 / relevant.len() as f64 >= target_recall - 1e-12
+        });
+
+        match depth {
+            Some(position) => depths.push((position + 1) as f64),
+            None => never_reached += 1,
+        }
+    }
+
+    let mean = if depths.is_empty() {
+        0.0
+    } else {
+        depths.iter().sum::<f64>() / depths.len() as f64
+    };
+
+    (mean, never_reached)
+}
+
+/// Evaluate several run files and align a metric by query for ensemble analysis.
+///
+/// Each file is loaded with [`load_trec_runs`](crate::trec::load_trec_runs)
+/// and evaluated with the rank-cutoff metric `metric` ("ndcg", "precision",
+/// "recall", "err", "rbp", "f1" or "success") at cutoff `k`, using the smallest
+/// run tag in the file for each query as in [`evaluate_trec_batch`].
+///
+/// # Returns
+///
+/// Map from each qrels query ID to one value per file, in `run_paths` order;
+/// `None` where that file has no run for the query.
+///
+/// # Errors
+///
+/// Fails if a run file cannot be loaded or `metric` is not a known
+/// rank-cutoff metric.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::path::Path;
+/// use rank_eval::batch::evaluate_ensemble;
+/// use rank_eval::trec::load_qrels;
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let qrels = load_qrels("qrels.txt")?;
+/// let paths = [Path::new("bm25.txt"), Path::new("dense.txt")];
+/// let matrix = evaluate_ensemble(&paths, &qrels, "ndcg", 10)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn evaluate_ensemble(
+    run_paths: &[&Path],
+    qrels: &[Qrel],
+    metric: &str,
+    k: usize,
+) -> anyhow::Result<HashMap<String, Vec<Option<f64>>>> {
+    use crate::trec::load_trec_runs;
+
+    if compute_binary_metric_at_k::<&str>(metric, &[], &HashSet::new(), k).is_none() {
+        anyhow::bail!("Unknown rank-cutoff metric: {}", metric);
+    }
+
+    let mut matrix: HashMap<String, Vec<Option<f64>>> = qrels
+        .iter()
+        .map(|qrel| (qrel.query_id.clone(), Vec::with_capacity(run_paths.len())))
+        .collect();
+
+    for path in run_paths {
+        let runs = load_trec_runs(path)?;
+        let grouped = GroupedQueries::new(&runs, qrels);
+
+        for query in grouped.iter() {
+            let value = query.ranked_run.and_then(|_| {
+                compute_binary_metric_at_k(metric, &query.ranked_ids, &query.relevant, k)
+            });
+            matrix.get_mut(query.query_id).expect("every qrels query has a row").push(value);
+        }
+    }
+
+    Ok(matrix)
+}
+
+/// Evaluate TREC runs and qrels in batch without cloning document IDs.
+///
+/// Identical to [`evaluate_trec_batch`], including the `skipped` report: both
+/// group runs and qrels by `&str` references into `runs` and `qrels` instead
+/// of cloning every query and document ID into owned maps, and only the query
+/// IDs of the returned `QueryResults` are allocated. Kept as an explicit entry
+/// point for callers that want to state the no-cloning guarantee.
+///
+/// The borrowed IDs live only for the duration of the call: `runs` and `qrels`
+/// must outlive it, but the returned `BatchResults` owns its data and does not
+/// borrow from them.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::{evaluate_trec_batch, evaluate_trec_batch_borrowed};
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new().add("1", "doc1", 0.9, "run").add("1", "doc2", 0.8, "run").build();
+/// let qrels = QrelBuilder::new().add("1", "doc2", 1).build();
+///
+/// let borrowed = evaluate_trec_batch_borrowed(&runs, &qrels, &["mrr"]);
+/// let owned = evaluate_trec_batch(&runs, &qrels, &["mrr"]);
+/// assert_eq!(borrowed, owned);
+/// ```
+pub fn evaluate_trec_batch_borrowed(
+    runs: &[TrecRun],
+    qrels: &[Qrel],
+    metrics: &[&str],
+) -> BatchResults {
+    evaluate_trec_batch(runs, qrels, metrics)
+}
+
+/// Evaluate TREC runs and qrels that are both sorted by query ID.
+///
+/// A streaming merge join: two cursors advance through `runs_sorted` and
+/// `qrels_sorted` in lockstep, and each query is evaluated as soon as its
+/// block ends, without grouping into hash maps. Semantics match
+/// [`evaluate_trec_batch`]: queries without runs are skipped and each run is
+/// ordered by score. With several run tags, the tag of the query's first run
+/// entry is used rather than the smallest, so results match
+/// [`evaluate_trec_batch`] for single-tag input. `query_results` are in query
+/// ID order.
+///
+/// # Errors
+///
+/// [`ValidationError::NotSortedByQuery`] if either input is not sorted by
+/// `query_id` (lexicographically).
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::evaluate_sorted;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new().add("1", "doc1", 0.9, "run").add("2", "doc2", 0.9, "run").build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).add("2", "doc3", 1).build();
+///
+/// let results = evaluate_sorted(&runs, &qrels, &["mrr"]).unwrap();
+/// assert_eq!(results.aggregated["mrr"], 0.5);
+///
+/// let unsorted = QrelBuilder::new().add("2", "doc3", 1).add("1", "doc1", 1).build();
+/// assert!(evaluate_sorted(&runs, &unsorted, &["mrr"]).is_err());
+/// ```
+pub fn evaluate_sorted(
+    runs_sorted: &[TrecRun],
+    qrels_sorted: &[Qrel],
+    metrics: &[&str],
+) -> Result<BatchResults, ValidationError> {
+    fn check_sorted<T>(
+        items: &[T],
+        query_id: impl Fn(&T) -> &str,
+        input: &'static str,
+    ) -> Result<(), ValidationError> {
+        match items.windows(2).position(|w| query_id(&w[0]) > query_id(&w[1])) {
+            Some(i) => Err(ValidationError::NotSortedByQuery { input, index: i + 1 }),
+            None => Ok(()),
+        }
+    }
+    check_sorted(runs_sorted, |r| &r.query_id, "runs")?;
+    check_sorted(qrels_sorted, |q| &q.query_id, "qrels")?;
+
+    let mut query_results = Vec::new();
+    let mut run_pos = 0;
+    let mut qrel_pos = 0;
+
+    while qrel_pos < qrels_sorted.len() {
+        let query_id = &qrels_sorted[qrel_pos].query_id;
+        let qrel_block_len = qrels_sorted[qrel_pos..]
+            .iter()
+            .take_while(|q| &q.query_id == query_id)
+            .count();
+        let query_qrels = &qrels_sorted[qrel_pos..qrel_pos + qrel_block_len];
+        qrel_pos += qrel_block_len;
+
+        // Skip runs for queries that have no qrels
+        while run_pos < runs_sorted.len() && runs_sorted[run_pos].query_id < *query_id {
+            run_pos += 1;
+        }
+        let run_block_len = runs_sorted[run_pos..]
+            .iter()
+            .take_while(|r| &r.query_id == query_id)
+            .count();
+        let query_runs = &runs_sorted[run_pos..run_pos + run_block_len];
+        run_pos += run_block_len;
+
+        let Some(first_run) = query_runs.first() else {
+            continue;
+        };
+        let mut ranked_run: Vec<&TrecRun> = query_runs
+            .iter()
+            .filter(|r| r.run_tag == first_run.run_tag)
+            .collect();
+        ranked_run.sort_by(|a, b| b.score.total_cmp(&a.score));
+        let ranked_ids: Vec<&String> = ranked_run.iter().map(|r| &r.doc_id).collect();
+
+        let relevant: HashSet<&String> = query_qrels
+            .iter()
+            .filter(|q| q.relevance > 0)
+            .map(|q| &q.doc_id)
+            .collect();
+
+        let mut query_metrics = HashMap::new();
+        for metric_name in metrics {
+            match compute_binary_metric(metric_name, &ranked_ids, &relevant) {
+                Some(value) => {
+                    query_metrics.insert(metric_name.to_string(), value);
+                }
+                None => eprintln!("Unknown metric: {}", metric_name),
+            }
+        }
+
+        query_results.push(QueryResults {
+            query_id: query_id.clone(),
+            metrics: query_metrics,
+        });
+    }
+
+    let (aggregated, effective_query_count) = mean_aggregates(&query_results);
+
+    Ok(BatchResults {
+        query_results,
+        aggregated,
+        effective_query_count,
+        skipped: Vec::new(),
+    })
+}
+
+/// Evaluate in-memory rankings and qrels keyed by query ID.
+///
+/// Each ranking is joined to its qrels by query ID; rankings without matching
+/// qrels are skipped. Rankings are evaluated in the order given (they are not
+/// re-sorted by score).
+///
+/// Supports the binary metric names accepted by [`evaluate_batch_binary`]
+/// (any grade > 0 counts as relevant) plus the graded metrics
+/// `graded_ndcg@5`, `graded_ndcg@10`, and `graded_map`.
+///
+/// # Arguments
+///
+/// * `rankings` - Map from query_id to (document_id, score) tuples in ranked order
+/// * `qrels` - Map from query_id to document_id -> relevance grade
+/// * `metrics` - List of metric names to compute
+///
+/// # Returns
+///
+/// `BatchResults` with per-query results (sorted by query ID) and aggregated means.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::batch::evaluate_maps;
+///
+/// let mut rankings = HashMap::new();
+/// rankings.insert("q1".to_string(), vec![("doc1".to_string(), 0.9), ("doc2".to_string(), 0.8)]);
+///
+/// let mut q1 = HashMap::new();
+/// q1.insert("doc2".to_string(), 2);
+/// let mut qrels = HashMap::new();
+/// qrels.insert("q1".to_string(), q1);
+///
+/// let results = evaluate_maps(&rankings, &qrels, &["mrr", "graded_ndcg@10"]);
+/// assert!((results.aggregated["mrr"] - 0.5).abs() < 1e-9);
+/// ```
+pub fn evaluate_maps(
+    rankings: &HashMap<String, Vec<(String, f32)>>,
+    qrels: &HashMap<String, HashMap<String, u32>>,
+    metrics: &[&str],
+) -> BatchResults {
+    let mut query_ids: Vec<&String> = rankings
+        .keys()
+        .filter(|id| qrels.contains_key(*id))
+        .collect();
+    query_ids.sort();
+
+    let mut query_results = Vec::new();
+
+    for query_id in query_ids {
+        let ranked = &rankings[query_id];
+        let query_qrels = &qrels[query_id];
+
+        let ranked_ids: Vec<&String> = ranked.iter().map(|(id, _)| id).collect();
+        let relevant: HashSet<_> = query_qrels
+            .iter()
+            .filter(|(_, &rel)| rel > 0)
+            .map(|(id, _)| id)
+            .collect();
+
+        let mut query_metrics = HashMap::new();
+
+        for metric_name in metrics {
+            let value = match compute_graded_metric(metric_name, ranked, query_qrels)
+                .or_else(|| compute_binary_metric(metric_name, &ranked_ids, &relevant))
+            {
+                Some(value) => value,
+                None => {
+                    eprintln!("Unknown metric: {}", metric_name);
+                    continue;
+                }
+            };
+
+            query_metrics.insert(metric_name.to_string(), value);
+        }
+
+        query_results.push(QueryResults {
+            query_id: query_id.clone(),
+            metrics: query_metrics,
+        });
+    }
+
+    let (aggregated, effective_query_count) = mean_aggregates(&query_results);
+
+    BatchResults {
+        query_results,
+        aggregated,
+        effective_query_count,
+        skipped: Vec::new(),
+    }
+}
+
+/// Batch results with a standard error for each aggregate.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BatchResultsWithSE {
+    pub results: BatchResults,
+    /// Jackknife standard error of each entry in `results.aggregated`
+    pub standard_errors: HashMap<String, f64>,
+}
+
+/// Evaluate TREC runs like [`evaluate_trec_batch`] and attach jackknife
+/// standard errors to the aggregates.
+///
+/// Formula: `SE = √((n-1)/n × Σᵢ (θ₍ᵢ₎ - θ̄)²)`
+///
+/// Where `θ₍ᵢ₎` is the metric's mean with query i left out and `θ̄` is the
+/// mean of those n leave-one-out means, over the queries that report the
+/// metric. For a plain mean this equals the analytic standard error, but it
+/// needs no distributional assumptions. The SE is NaN for a metric reported
+/// by fewer than two queries.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::evaluate_with_jackknife;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new()
+///     .add("1", "doc1", 0.9, "run")
+///     .add("2", "doc3", 0.9, "run")
+///     .add("2", "doc2", 0.8, "run")
+///     .build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).add("2", "doc2", 1).build();
+///
+/// let with_se = evaluate_with_jackknife(&runs, &qrels, &["mrr"]);
+/// assert_eq!(with_se.results.aggregated["mrr"], 0.75);
+/// assert!((with_se.standard_errors["mrr"] - 0.25).abs() < 1e-12);
+/// ```
+pub fn evaluate_with_jackknife(
+    runs: &[TrecRun],
+    qrels: &[Qrel],
+    metrics: &[&str],
+) -> BatchResultsWithSE {
+    let results = evaluate_trec_batch(runs, qrels, metrics);
+
+    let standard_errors = results
+        .aggregated
+        .keys()
+        .map(|metric| {
+            let values: Vec<f64> = results
+                .query_results
+                .iter()
+                .filter_map(|qr| qr.metrics.get(metric).copied())
+                .collect();
+            if values.len() < 2 {
+                return (metric.clone(), f64::NAN);
+            }
+
+            let n = values.len() as f64;
+            let total: f64 = values.iter().sum();
+            let loo_means: Vec<f64> = values.iter().map(|v| (total - v) / (n - 1.0)).collect();
+            let loo_mean = loo_means.iter().sum::<f64>() / n;
+            let spread: f64 = loo_means.iter().map(|m| (m - loo_mean).powi(2)).sum();
+
+            (metric.clone(), ((n - 1.0) / n * spread).sqrt())
+        })
+        .collect();
+
+    BatchResultsWithSE {
+        results,
+        standard_errors,
+    }
+}
+
+/// Mean and sample standard deviation of per-query graded nDCG@k.
+///
+/// Each query in `qrels` with a run is scored with exponential-gain nDCG@k
+/// ([`graded::ExponentialGain`], `gain(g) = 2^g - 1`), using the smallest run tag
+/// as in [`evaluate_trec_batch`]; queries without runs are skipped. Grades
+/// above `max_grade` are capped at `max_grade`, so a stray out-of-scale label
+/// cannot dominate the gains.
+///
+/// # Returns
+///
+/// `(mean, sample standard deviation)` over the evaluated queries. The mean is
+/// 0.0 with no queries, and the standard deviation is NaN with fewer than two.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::graded_ndcg_with_std;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new()
+///     .add("1", "doc1", 0.9, "run")
+///     .add("2", "doc2", 0.9, "run")
+///     .add("2", "doc3", 0.8, "run")
+///     .build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 2).add("2", "doc2", 2).build();
+///
+/// let (mean, std) = graded_ndcg_with_std(&runs, &qrels, 10, 2);
+/// assert_eq!((mean, std), (1.0, 0.0));
+/// ```
+pub fn graded_ndcg_with_std(
+    runs: &[TrecRun],
+    qrels: &[Qrel],
+    k: usize,
+    max_grade: u32,
+) -> (f64, f64) {
+    let grouped = GroupedQueries::new(runs, qrels);
+
+    let values: Vec<f64> = grouped
+        .iter()
+        .filter_map(|query| {
+            // The graded metrics take owned IDs
+            let ranked: Vec<(String, f32)> = query
+                .ranked_run?
+                .iter()
+                .map(|&(doc_id, score)| (doc_id.to_string(), score))
+                .collect();
+            let capped: HashMap<String, u32> = query
+                .qrels
+                .iter()
+                .map(|(&doc_id, &grade)| (doc_id.to_string(), grade.min(max_grade)))
+                .collect();
+            Some(graded::compute_ndcg_with_gain(&ranked, &capped, k, &graded::ExponentialGain))
+        })
+        .collect();
+
+    if values.is_empty() {
+        return (0.0, f64::NAN);
+    }
+
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+
+    (mean, variance.sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_evaluate_batch_binary() {
+        let rankings = vec![
+            vec!["doc1", "doc2", "doc3"],
+            vec!["doc4", "doc5", "doc6"],
+        ];
+        let qrels = vec![
+            ["doc1", "doc3"].into_iter().collect::<HashSet<_>>(),
+            ["doc4"].into_iter().collect::<HashSet<_>>(),
+        ];
+
+        let results = evaluate_batch_binary(&rankings, &qrels, &["ndcg@10", "precision@5"]);
+
+        assert_eq!(results.query_results.len(), 2);
+        assert!(results.aggregated.contains_key("ndcg@10"));
+        assert!(results.aggregated.contains_key("precision@5"));
+    }
+
+    #[test]
+    fn test_evaluate_trec_batch_complete_mode() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "doc1", 0.9, "run1")
+            .add("2", "doc2", 0.9, "run1")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "doc1", 1)
+            .add("2", "doc2", 1)
+            .add("3", "doc3", 1)
+            .build();
+
+        let default = evaluate_trec_batch(&runs, &qrels, &["mrr"]);
+        assert_eq!(default.query_results.len(), 2);
+        assert!((default.aggregated["mrr"] - 1.0).abs() < 1e-9);
+
+        let options = TrecBatchOptions {
+            complete: true,
+            ..Default::default()
+        };
+        let complete = evaluate_trec_batch_with_options(&runs, &qrels, &["mrr"], &options);
+        assert_eq!(complete.query_results.len(), 3);
+        assert!((complete.aggregated["mrr"] - 2.0 / 3.0).abs() < 1e-9);
+        let missing = complete
+            .query_results
+            .iter()
+            .find(|qr| qr.query_id == "3")
+            .unwrap();
+        assert_eq!(missing.metrics["mrr"], 0.0);
+    }
+
+    #[test]
+    fn test_evaluate_maps() {
+        let mut rankings = HashMap::new();
+        rankings.insert(
+            "q1".to_string(),
+            vec![("doc1".to_string(), 0.9), ("doc2".to_string(), 0.8)],
+        );
+        rankings.insert("q2".to_string(), vec![("doc3".to_string(), 0.7)]);
+        rankings.insert("unjudged".to_string(), vec![("doc4".to_string(), 0.7)]);
+
+        let mut qrels = HashMap::new();
+        qrels.insert(
+            "q1".to_string(),
+            [("doc2".to_string(), 2)].into_iter().collect::<HashMap<_, _>>(),
+        );
+        qrels.insert(
+            "q2".to_string(),
+            [("doc3".to_string(), 1)].into_iter().collect::<HashMap<_, _>>(),
+        );
+
+        let results = evaluate_maps(&rankings, &qrels, &["mrr", "graded_map"]);
+
+        assert_eq!(results.query_results.len(), 2);
+        assert_eq!(results.query_results[0].query_id, "q1");
+        assert!((results.query_results[0].metrics["mrr"] - 0.5).abs() < 1e-9);
+        assert!((results.query_results[0].metrics["graded_map"] - 0.5).abs() < 1e-9);
+        assert!((results.query_results[1].metrics["mrr"] - 1.0).abs() < 1e-9);
+        assert!((results.aggregated["mrr"] - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_pooled_recall_at_k() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let mut run_builder = RunBuilder::new();
+        for i in 1..=6 {
+            run_builder = run_builder.add("1", &format!("doc{}", i), 1.0 - i as f32 / 10.0, "run");
+        }
+        let runs = run_builder.build();
+        // Shallow pool: only the top five documents were judged
+        let mut shallow = QrelBuilder::new().add("1", "doc1", 1);
+        for i in 2..=5 {
+            shallow = shallow.add("1", &format!("doc{}", i), 0);
+        }
+        let qrels = shallow.build();
+
+        let pooled = pooled_recall_at_k(&runs, &qrels, 5);
+        assert!((pooled.aggregated["pooled_recall@5"] - 1.0).abs() < 1e-9);
+
+        // Deeper judging finds doc6 relevant too: recall@5 drops to 0.5
+        let mut full_qrels = qrels.clone();
+        full_qrels.extend(QrelBuilder::new().add("1", "doc6", 1).build());
+        let true_recall = evaluate_trec_batch(&runs, &full_qrels, &["recall@5"]);
+        assert!((true_recall.aggregated["recall@5"] - 0.5).abs() < 1e-9);
+
+        // With complete qrels, pooled recall equals standard recall
+        let complete = pooled_recall_at_k(&runs, &full_qrels, 5);
+        assert!((complete.aggregated["pooled_recall@5"] - 0.5).abs() < 1e-9);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_batch_results_serde_round_trip() {
+        let rankings = vec![vec!["doc1", "doc2"], vec!["doc3"]];
+        let qrels = vec![
+            ["doc2"].into_iter().collect::<HashSet<_>>(),
+            ["doc3"].into_iter().collect::<HashSet<_>>(),
+        ];
+        let results = evaluate_batch_binary(&rankings, &qrels, &["mrr", "ndcg@10"]);
+
+        let json = serde_json::to_string(&results).unwrap();
+        let decoded: BatchResults = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, results);
+    }
+
+    #[test]
+    fn test_evaluate_with_dynamic_k_matches_r_precision() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "doc1", 0.9, "run")
+            .add("1", "doc2", 0.8, "run")
+            .add("1", "doc3", 0.7, "run")
+            .add("2", "doc4", 0.9, "run")
+            .add("2", "doc5", 0.8, "run")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "doc2", 1)
+            .add("1", "doc3", 1)
+            .add("2", "doc4", 1)
+            .build();
+
+        let dynamic = evaluate_with_dynamic_k(&runs, &qrels, "precision", |r| r);
+        let reference = evaluate_trec_batch(&runs, &qrels, &["r_precision"]);
+
+        for result in &dynamic.query_results {
+            let expected = reference
+                .query_results
+                .iter()
+                .find(|r| r.query_id == result.query_id)
+                .unwrap()
+                .metrics["r_precision"];
+            assert!((result.metrics["precision"] - expected).abs() < 1e-9);
+        }
+        assert!((dynamic.aggregated["precision"] - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_evaluate_and_compare() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let new = RunBuilder::new()
+            .add("1", "doc1", 0.9, "new")
+            .add("1", "doc2", 0.8, "new")
+            .add("2", "doc3", 0.9, "new")
+            .add("3", "doc6", 0.9, "new")
+            .add("3", "doc5", 0.8, "new")
+            .build();
+        let baseline = RunBuilder::new()
+            .add("1", "doc2", 0.9, "base")
+            .add("1", "doc1", 0.8, "base")
+            .add("2", "doc3", 0.9, "base")
+            .add("3", "doc5", 0.9, "base")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "doc1", 1)
+            .add("2", "doc3", 1)
+            .add("3", "doc5", 1)
+            .build();
+
+        let report = evaluate_and_compare(&new, &baseline, &qrels, &["mrr", "precision@1"]);
+        assert_eq!(report.new.query_results.len(), 3);
+        assert_eq!(report.baseline.query_results.len(), 3);
+
+        let mrr = &report.metrics["mrr"];
+        assert_eq!((mrr.wins, mrr.losses, mrr.ties), (1, 1, 1));
+        assert!((mrr.new_mean - 2.5 / 3.0).abs() < 1e-9);
+        assert!((mrr.baseline_mean - 2.5 / 3.0).abs() < 1e-9);
+        assert!(mrr.mean_delta.abs() < 1e-9);
+        assert_eq!(mrr.t_test.degrees_of_freedom, 2);
+        assert!(report.metrics.contains_key("precision@1"));
+    }
+
+    #[test]
+    fn test_evaluate_multi_k() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "doc1", 0.9, "run")
+            .add("1", "doc2", 0.8, "run")
+            .add("1", "doc3", 0.7, "run")
+            .add("2", "doc4", 0.9, "run")
+            .add("2", "doc5", 0.8, "run")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "doc2", 1)
+            .add("2", "doc4", 1)
+            .add("2", "doc5", 1)
+            .build();
+
+        let multi = evaluate_multi_k(&runs, &qrels, "ndcg", &[1, 5, 10]);
+        assert_eq!(multi.aggregated.len(), 3);
+
+        let single = evaluate_trec_batch(&runs, &qrels, &["ndcg@5", "ndcg@10"]);
+        assert!((multi.aggregated["ndcg@5"] - single.aggregated["ndcg@5"]).abs() < 1e-9);
+        assert!((multi.aggregated["ndcg@10"] - single.aggregated["ndcg@10"]).abs() < 1e-9);
+        // Query 1 misses at rank 1, query 2 hits
+        assert!((multi.aggregated["ndcg@1"] - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_qrels_sensitivity() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "doc1", 0.9, "run")
+            .add("1", "doc2", 0.8, "run")
+            .add("2", "doc3", 0.9, "run")
+            .build();
+        let original = QrelBuilder::new()
+            .add("1", "doc1", 1)
+            .add("1", "doc2", 1)
+            .add("2", "doc3", 1)
+            .build();
+        // doc1 re-judged as non-relevant
+        let revised = QrelBuilder::new()
+            .add("1", "doc1", 0)
+            .add("1", "doc2", 1)
+            .add("2", "doc3", 1)
+            .build();
+
+        let versions = vec![
+            ("original".to_string(), original),
+            ("revised".to_string(), revised),
+        ];
+        let sensitivity = qrels_sensitivity(&runs, &versions, "precision@1");
+
+        assert_eq!(sensitivity.means.len(), 2);
+        assert!((sensitivity.means["original"] - 1.0).abs() < 1e-9);
+        assert!((sensitivity.means["revised"] - 0.5).abs() < 1e-9);
+        assert!((sensitivity.range - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_relevance_by_rank() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "doc1", 0.9, "run")
+            .add("1", "doc2", 0.8, "run")
+            .add("2", "doc3", 0.9, "run")
+            .add("2", "doc4", 0.8, "run")
+            .add("3", "doc5", 0.9, "run")
+            .add("4", "doc6", 0.9, "run")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "doc1", 1)
+            .add("2", "doc4", 1)
+            .add("3", "doc5", 2)
+            .add("4", "doc6", 0)
+            .build();
+
+        let density = relevance_by_rank(&runs, &qrels, 3);
+        assert_eq!(density.len(), 3);
+        assert!((density[0] - 0.5).abs() < 1e-9);
+        assert!((density[1] - 0.25).abs() < 1e-9);
+        assert_eq!(density[2], 0.0);
+    }
+
+    #[test]
+    fn test_evaluate_trec_batch_borrowed_matches_owned() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let mut run_builder = RunBuilder::new();
+        let mut qrel_builder = QrelBuilder::new();
+        for q in 0..50 {
+            let query_id = format!("q{}", q);
+            for d in 0..20 {
+                let doc_id = format!("d{}", (q * 7 + d * 3) % 40);
+                run_builder = run_builder.add(&query_id, &doc_id, 1.0 / (d + 1) as f32, "run");
+            }
+            for d in 0..40 {
+                if (q + d) % 5 == 0 {
+                    qrel_builder = qrel_builder.add(&query_id, &format!("d{}", d), (d % 3) as u32);
+                }
+            }
+        }
+        // One query without qrels and one without runs are both skipped
+        let runs = run_builder.add("unjudged", "d1", 1.0, "run").build();
+        let qrels = qrel_builder.add("unretrieved", "d1", 1).build();
+        let metrics = ["ndcg@10", "map", "mrr", "precision@5", "recall@10"];
+
+        let owned = evaluate_trec_batch(&runs, &qrels, &metrics);
+        let borrowed = evaluate_trec_batch_borrowed(&runs, &qrels, &metrics);
+
+        assert_eq!(owned.skipped.len(), 2);
+        assert_eq!(borrowed, owned);
+    }
+
+    #[test]
+    fn test_ndcg_ceiling_option() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "doc1", 0.9, "run")
+            .add("1", "doc2", 0.8, "run")
+            .add("1", "doc3", 0.7, "run")
+            .add("2", "doc4", 0.9, "run")
+            .add("2", "doc5", 0.8, "run")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "doc3", 1)
+            .add("1", "doc9", 1)
+            .add("2", "doc4", 1)
+            .build();
+
+        let options = TrecBatchOptions {
+            ndcg_ceiling: true,
+            ..Default::default()
+        };
+        let results =
+            evaluate_trec_batch_with_options(&runs, &qrels, &["ndcg@10", "mrr"], &options);
+
+        for result in &results.query_results {
+            let actual = result.metrics["ndcg@10"];
+            let ceiling = result.metrics["ndcg@10_ceiling"];
+            assert!(ceiling >= actual - 1e-12);
+            assert!(!result.metrics.contains_key("mrr_ceiling"));
+        }
+
+        let q1 = results.query_results.iter().find(|r| r.query_id == "1").unwrap();
+        assert!(q1.metrics["ndcg@10_ceiling"] > q1.metrics["ndcg@10"]);
+        // doc9 was never retrieved, so even the ceiling is below 1
+        assert!(q1.metrics["ndcg@10_ceiling"] < 1.0);
+    }
+
+    #[test]
+    fn test_evaluate_all_systems() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "doc1", 0.9, "a")
+            .add("1", "doc2", 0.8, "a")
+            .add("1", "doc2", 0.9, "b")
+            .add("1", "doc1", 0.8, "b")
+            .add("1", "doc3", 0.9, "c")
+            .add("2", "doc4", 0.9, "c")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "doc1", 1)
+            .add("2", "doc4", 1)
+            .build();
+
+        let systems = evaluate_all_systems(&runs, &qrels, &["mrr"]);
+        assert_eq!(systems.len(), 3);
+        assert!((systems["a"].aggregated["mrr"] - 1.0).abs() < 1e-9);
+        assert!((systems["b"].aggregated["mrr"] - 0.5).abs() < 1e-9);
+        // System c retrieved nothing relevant for query 1 but found doc4 for query 2
+        assert_eq!(systems["c"].query_results.len(), 2);
+        assert!((systems["c"].aggregated["mrr"] - 0.5).abs() < 1e-9);
+
+        let table = aggregate_table(&systems);
+        let tags: Vec<&str> = table.iter().map(|row| row.0.as_str()).collect();
+        assert_eq!(tags, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_aggregate_f1_modes_differ() {
+        let query = |id: &str, precision: f64, recall: f64| QueryResults {
+            query_id: id.to_string(),
+            metrics: [
+                ("precision@10".to_string(), precision),
+                ("recall@10".to_string(), recall),
+            ]
+            .into_iter()
+            .collect(),
+        };
+        // Precision and recall peak on different queries
+        let results = BatchResults {
+            query_results: vec![query("1", 1.0, 0.0), query("2", 0.0, 1.0)],
+            aggregated: HashMap::new(),
+            effective_query_count: HashMap::new(),
+            skipped: Vec::new(),
+        };
+
+        let per_query = aggregate_f1(&results, 10, 1.0, F1AggMode::MeanOfPerQuery);
+        let of_means = aggregate_f1(&results, 10, 1.0, F1AggMode::OfMeans);
+        assert_eq!(per_query, 0.0);
+        assert!((of_means - 0.5).abs() < 1e-9);
+
+        // Missing metrics
+        assert_eq!(aggregate_f1(&results, 5, 1.0, F1AggMode::OfMeans), 0.0);
+    }
+
+    #[test]
+    fn test_evaluate_sorted_matches_batch() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "doc2", 0.7, "run")
+            .add("1", "doc1", 0.9, "run")
+            .add("2", "doc3", 0.9, "run")
+            .add("4", "doc4", 0.9, "run")
+            .add("5", "doc5", 0.9, "run")
+            .add("5", "doc6", 0.8, "run")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "doc2", 1)
+            .add("3", "doc9", 1)
+            .add("4", "doc4", 0)
+            .add("5", "doc6", 2)
+            .build();
+        let metrics = ["mrr", "ndcg@10", "precision@5"];
+
+        let sorted = evaluate_sorted(&runs, &qrels, &metrics).unwrap();
+        let grouped = evaluate_trec_batch(&runs, &qrels, &metrics);
+
+        let ids: Vec<&str> = sorted.query_results.iter().map(|qr| qr.query_id.as_str()).collect();
+        assert_eq!(ids, vec!["1", "4", "5"]);
+        for qr in &sorted.query_results {
+            let expected = grouped
+                .query_results
+                .iter()
+                .find(|g| g.query_id == qr.query_id)
+                .unwrap();
+            assert_eq!(qr.metrics, expected.metrics);
+        }
+        for (name, value) in &grouped.aggregated {
+            assert!((value - sorted.aggregated[name]).abs() < 1e-12);
+        }
+
+        let mut reversed = runs.clone();
+        reversed.reverse();
+        assert_eq!(
+            evaluate_sorted(&reversed, &qrels, &metrics),
+            Err(ValidationError::NotSortedByQuery { input: "runs", index: 2 })
+        );
+    }
+
+    #[test]
+    fn test_aggregate_by_relevant_count() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "a", 0.9, "run")
+            .add("2", "b", 0.9, "run")
+            .add("2", "c", 0.8, "run")
+            .build();
+        let mut qrel_builder = QrelBuilder::new().add("1", "a", 1);
+        for i in 0..9 {
+            qrel_builder = qrel_builder.add("2", &format!("missing{}", i), 1);
+        }
+        let qrels = qrel_builder.build();
+
+        let results = evaluate_trec_batch(&runs, &qrels, &["recall@10", "mrr"]);
+        let weighted = aggregate_by_relevant_count(&results, &qrels);
+
+        // Plain mean weights both queries equally; the weighted mean is
+        // dominated by query 2 with its 9 relevant documents
+        assert!((results.aggregated["recall@10"] - 0.5).abs() < 1e-9);
+        assert!((weighted["recall@10"] - 0.1).abs() < 1e-9);
+        assert!(weighted["mrr"] < results.aggregated["mrr"]);
+    }
+
+    #[test]
+    fn test_effective_query_count_excludes_skipped() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "doc1", 0.9, "run1")
+            .add("2", "doc2", 0.9, "run1")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "doc1", 1)
+            .add("2", "doc2", 1)
+            .add("3", "doc3", 1)
+            .build();
+
+        let results = evaluate_trec_batch(&runs, &qrels, &["mrr", "ndcg@10"]);
+        assert_eq!(results.effective_query_count["mrr"], 2);
+        assert_eq!(results.effective_query_count["ndcg@10"], 2);
+
+        let options = TrecBatchOptions {
+            complete: true,
+            ..Default::default()
+        };
+        let complete = evaluate_trec_batch_with_options(&runs, &qrels, &["mrr"], &options);
+        assert_eq!(complete.effective_query_count["mrr"], 3);
+    }
+
+    #[test]
+    fn test_evaluate_trec_batch_reports_skipped_queries() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "doc1", 0.9, "run1")
+            .add("3", "doc3", 0.9, "run1")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "doc1", 1)
+            .add("2", "doc2", 1)
+            .build();
+
+        let results = evaluate_trec_batch(&runs, &qrels, &["mrr"]);
+
+        let skipped: Vec<(&str, SkipReason)> = results
+            .skipped
+            .iter()
+            .map(|q| (q.query_id.as_str(), q.reason))
+            .collect();
+        assert_eq!(
+            skipped,
+            vec![("2", SkipReason::MissingFromRuns), ("3", SkipReason::MissingFromQrels)]
+        );
+        assert_eq!(results.query_results.len(), 1);
+        assert_eq!(results.aggregated["mrr"], 1.0);
+
+        // Complete mode evaluates the query instead of skipping it
+        let options = TrecBatchOptions {
+            complete: true,
+            ..Default::default()
+        };
+        let complete = evaluate_trec_batch_with_options(&runs, &qrels, &["mrr"], &options);
+        assert!(complete.skipped.iter().all(|q| q.query_id != "2"));
+    }
+
+    #[test]
+    fn test_mean_depth_for_full_recall() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "r1", 0.9, "run")
+            .add("1", "n1", 0.8, "run")
+            .add("1", "r2", 0.7, "run")
+            .add("1", "n2", 0.6, "run")
+            .add("2", "n3", 0.9, "run")
+            .add("2", "r3", 0.8, "run")
+            .add("3", "r4", 0.9, "run")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "r1", 1)
+            .add("1", "r2", 2)
+            .add("2", "r3", 1)
+            .add("3", "r4", 1)
+            .add("3", "missing", 1)
+            .add("4", "r5", 1)
+            .build();
+
+        // Last relevant documents at depths 3 and 2; queries 3 (partial) and
+        // 4 (no run) never reach full recall
+        assert_eq!(mean_depth_for_recall(&runs, &qrels, 1.0), (2.5, 2));
+        assert_eq!(mean_depth_for_recall(&runs, &qrels, 0.5), (4.0 / 3.0, 1));
+    }
+
+    #[test]
+    fn test_evaluate_ensemble_aligns_runs() {
+        use crate::trec::QrelBuilder;
+        use std::io::Write;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let write_run = |name: &str, lines: &[&str]| {
+            let path = dir.path().join(name);
+            let mut file = std::fs::File::create(&path).unwrap();
+            for line in lines {
+                writeln!(file, "{}", line).unwrap();
+            }
+            path
+        };
+        let first = write_run("first.txt", &["1 Q0 doc1 1 0.9 a", "2 Q0 doc2 1 0.9 a"]);
+        let second = write_run("second.txt", &["1 Q0 doc3 1 0.9 b"]);
+        let qrels = QrelBuilder::new().add("1", "doc1", 1).add("2", "doc2", 1).build();
+
+        let paths = [first.as_path(), second.as_path()];
+        let matrix = evaluate_ensemble(&paths, &qrels, "precision", 1).unwrap();
+
+        assert_eq!(matrix.len(), 2);
+        assert_eq!(matrix["1"], vec![Some(1.0), Some(0.0)]);
+        assert_eq!(matrix["2"], vec![Some(1.0), None]);
+        assert!(evaluate_ensemble(&paths, &qrels, "mrr", 1).is_err());
+    }
+
+    #[test]
+    fn test_graded_ndcg_with_std_matches_per_query() {
+        use crate::graded::{compute_ndcg_with_gain, ExponentialGain};
+        use crate::trec::{group_qrels_by_query, group_runs_by_query, QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "a", 0.9, "run")
+            .add("1", "b", 0.8, "run")
+            .add("2", "c", 0.9, "run")
+            .add("2", "d", 0.8, "run")
+            .add("3", "e", 0.9, "run")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "a", 1)
+            .add("1", "b", 3)
+            .add("2", "d", 2)
+            .add("3", "e", 1)
+            .build();
+
+        let runs_by_query = group_runs_by_query(&runs);
+        let qrels_by_query = group_qrels_by_query(&qrels);
+        let per_query: Vec<f64> = ["1", "2", "3"]
+            .iter()
+            .map(|q| {
+                let ranked = &runs_by_query[*q]["run"];
+                compute_ndcg_with_gain(ranked, &qrels_by_query[*q], 10, &ExponentialGain)
+            })
+            .collect();
+        let mean = per_query.iter().sum::<f64>() / 3.0;
+        let std = (per_query.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / 2.0).sqrt();
+
+        let (got_mean, got_std) = graded_ndcg_with_std(&runs, &qrels, 10, 3);
+        assert!((got_mean - mean).abs() < 1e-12);
+        assert!((got_std - std).abs() < 1e-12);
+        assert!(got_std > 0.0);
+    }
+
+    #[test]
+    fn test_coverage_adjusted_ndcg_penalizes_low_coverage() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("judged", "r1", 0.9, "run")
+            .add("judged", "n1", 0.8, "run")
+            .add("judged", "n2", 0.7, "run")
+            .add("sparse", "r2", 0.9, "run")
+            .add("sparse", "u1", 0.8, "run")
+            .add("sparse", "u2", 0.7, "run")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("judged", "r1", 1)
+            .add("judged", "n1", 0)
+            .add("judged", "n2", 0)
+            .add("sparse", "r2", 1)
+            .build();
+
+        let adjusted = coverage_adjusted_ndcg(&runs, &qrels, 3);
+        // Both queries have raw nDCG@3 of 1.0
+        assert_eq!(adjusted["judged"], 1.0);
+        assert!((adjusted["sparse"] - 1.0 / 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_evaluate_with_jackknife_standard_errors() {
+        use crate::statistics::standard_error;
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        // MRR 1, 1/2, 1/3 on queries 1-3
+        let runs = RunBuilder::new()
+            .add("1", "rel1", 0.9, "run")
+            .add("2", "x", 0.9, "run")
+            .add("2", "rel2", 0.8, "run")
+            .add("3", "x", 0.9, "run")
+            .add("3", "y", 0.8, "run")
+            .add("3", "rel3", 0.7, "run")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "rel1", 1)
+            .add("2", "rel2", 1)
+            .add("3", "rel3", 1)
+            .build();
+
+        let with_se = evaluate_with_jackknife(&runs, &qrels, &["mrr", "recall@10"]);
+        let mrr_se = with_se.standard_errors["mrr"];
+        assert!(mrr_se > 0.0);
+        assert!((mrr_se - standard_error(&[1.0, 0.5, 1.0 / 3.0])).abs() < 1e-12);
+        // Every query has recall@10 of 1.0
+        assert!(with_se.standard_errors["recall@10"].abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_weighted_ndcg_upweights_high_scoring_query() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("good", "r1", 0.9, "run")
+            .add("good", "n1", 0.8, "run")
+            .add("poor", "n2", 0.9, "run")
+            .add("poor", "r2", 0.8, "run")
+            .build();
+        let qrels = QrelBuilder::new().add("good", "r1", 1).add("poor", "r2", 1).build();
+
+        let uniform = weighted_ndcg(&runs, &qrels, 10, &HashMap::new());
+        let mut weights = HashMap::new();
+        weights.insert("good".to_string(), 10.0);
+        weights.insert("poor".to_string(), 1.0);
+        let weighted = weighted_ndcg(&runs, &qrels, 10, &weights);
+
+        // Missing weights default to 1.0, so the uniform case is the plain mean
+        let poor = 1.0 / 3f64.log2();
+        assert!((uniform - (1.0 + poor) / 2.0).abs() < 1e-12);
+        assert!((weighted - (10.0 + poor) / 11.0).abs() < 1e-12);
+        assert!(weighted > uniform);
+    }
+
+    #[test]
+    fn test_single_run_functions_use_smallest_tag() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "doc2", 0.9, "b_run")
+            .add("1", "doc1", 0.8, "b_run")
+            .add("1", "doc1", 0.9, "a_run")
+            .build();
+        let qrels = QrelBuilder::new().add("1", "doc1", 1).build();
+
+        // Only "a_run" ranks doc1 first; the choice must not vary between calls
+        for _ in 0..8 {
+            assert_eq!(evaluate_trec_batch(&runs, &qrels, &["mrr"]).aggregated["mrr"], 1.0);
+            assert_eq!(coverage_adjusted_ndcg(&runs, &qrels, 1)["1"], 1.0);
+            assert_eq!(mean_depth_for_recall(&runs, &qrels, 1.0), (1.0, 0));
+        }
+    }
+}
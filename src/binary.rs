@@ -6,7 +6,38 @@
 //!
 //! These metrics use binary relevance: a document is either relevant (in the set) or not.
 
-use std::collections::HashSet;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// Derive the binary relevant set from graded qrels.
+///
+/// Keeps the documents with grade ≥ `threshold`. With `threshold = 1` this is
+/// the set the batch evaluators use (any grade > 0 is relevant); a higher
+/// threshold evaluates against only the most relevant documents.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::binary::{relevant_from_qrels, precision_at_k};
+///
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1", 2);
+/// qrels.insert("doc2", 0);
+///
+/// let relevant = relevant_from_qrels(&qrels, 1);
+/// assert!((precision_at_k(&["doc1", "doc2"], &relevant, 2) - 0.5).abs() < 1e-9);
+/// ```
+pub fn relevant_from_qrels<I: Eq + std::hash::Hash + Clone>(
+    qrels: &HashMap<I, u32>,
+    threshold: u32,
+) -> HashSet<I> {
+    qrels
+        .iter()
+        .filter(|(_, &grade)| grade >= threshold)
+        .map(|(id, _)| id.clone())
+        .collect()
+}
 
 /// Precision at k: fraction of top-k that are relevant.
 ///
@@ -100,6 +131,90 @@ pub fn mrr<I: Eq + std::hash::Hash>(ranked: &[I], relevant: &HashSet<I>) -> f64
     0.0
 }
 
+/// Rank of the first relevant document (1-based).
+///
+/// This is the quantity behind [`mrr`]: whenever it is `Some(r)`, `mrr` is `1 / r`.
+/// Looking at its distribution across queries shows where relevant documents
+/// first appear, which the reciprocal compresses.
+///
+/// Returns `None` if no relevant document is retrieved.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::binary::first_relevant_rank;
+///
+/// let ranked = vec!["doc1", "doc2", "doc3"];
+/// let relevant: HashSet<_> = ["doc3"].into_iter().collect();
+///
+/// assert_eq!(first_relevant_rank(&ranked, &relevant), Some(3));
+/// ```
+pub fn first_relevant_rank<I: Eq + std::hash::Hash>(
+    ranked: &[I],
+    relevant: &HashSet<I>,
+) -> Option<usize> {
+    ranked
+        .iter()
+        .position(|id| relevant.contains(id))
+        .map(|i| i + 1)
+}
+
+/// Summarize per-query first-relevant ranks for reporting alongside MRR.
+///
+/// Takes (query_id, rank) pairs as produced by [`first_relevant_rank`] and
+/// returns `(mean_rank, found, not_found)`, where `mean_rank` averages only the
+/// queries with a relevant document retrieved (0.0 if there are none).
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::binary::rank_of_first_relevant_stats;
+///
+/// let ranks = vec![
+///     ("q1".to_string(), Some(1)),
+///     ("q2".to_string(), Some(3)),
+///     ("q3".to_string(), None),
+/// ];
+/// assert_eq!(rank_of_first_relevant_stats(&ranks), (2.0, 2, 1));
+/// ```
+pub fn rank_of_first_relevant_stats(scores: &[(String, Option<usize>)]) -> (f64, usize, usize) {
+    let found: Vec<usize> = scores.iter().filter_map(|(_, rank)| *rank).collect();
+    let not_found = scores.len() - found.len();
+
+    let mean_rank = if found.is_empty() {
+        0.0
+    } else {
+        found.iter().sum::<usize>() as f64 / found.len() as f64
+    };
+
+    (mean_rank, found.len(), not_found)
+}
+
+/// Number of relevant documents ranked before the first non-relevant one.
+///
+/// A precision-at-the-top diagnostic: a high value means the head of the
+/// ranking is clean. Returns the full count of leading relevant documents if
+/// the ranking contains no non-relevant document.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::binary::relevant_before_first_nonrelevant;
+///
+/// let ranked = vec!["doc1", "doc2", "doc3"];
+/// let relevant: HashSet<_> = ["doc1", "doc3"].into_iter().collect();
+///
+/// assert_eq!(relevant_before_first_nonrelevant(&ranked, &relevant), 1);
+/// ```
+pub fn relevant_before_first_nonrelevant<I: Eq + std::hash::Hash>(
+    ranked: &[I],
+    relevant: &HashSet<I>,
+) -> usize {
+    ranked.iter().take_while(|id| relevant.contains(id)).count()
+}
+
 /// Discounted Cumulative Gain at k.
 ///
 /// Formula: `DCG@k = Σᵢ (rel(i) / log₂(i + 2))`
@@ -131,12 +246,41 @@ pub fn mrr<I: Eq + std::hash::Hash>(ranked: &[I], relevant: &HashSet<I>) -> f64
 /// // Total: 1.5
 /// ```
 pub fn dcg_at_k<I: Eq + std::hash::Hash>(ranked: &[I], relevant: &HashSet<I>, k: usize) -> f64 {
+    dcg_at_k_gain(ranked, relevant, k, 1.0)
+}
+
+/// Discounted Cumulative Gain at k with an explicit gain per relevant document.
+///
+/// Formula: `DCG@k = Σᵢ (g × rel(i) / log₂(i + 2))`
+///
+/// Binary relevance treats every relevant document as one grade, so its gain
+/// `g` is a constant: `1.0` under both linear and exponential (`2^1 - 1`) gain,
+/// which makes `gain_of_relevant = 1.0` exactly [`dcg_at_k`]. Setting `g`
+/// explicitly keeps binary DCG on the same scale as a graded gain function.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::binary::{dcg_at_k, dcg_at_k_gain};
+///
+/// let ranked = vec!["doc1", "doc2", "doc3"];
+/// let relevant: HashSet<_> = ["doc1", "doc3"].into_iter().collect();
+///
+/// assert_eq!(dcg_at_k_gain(&ranked, &relevant, 3, 3.0), 3.0 * dcg_at_k(&ranked, &relevant, 3));
+/// ```
+pub fn dcg_at_k_gain<I: Eq + std::hash::Hash>(
+    ranked: &[I],
+    relevant: &HashSet<I>,
+    k: usize,
+    gain_of_relevant: f64,
+) -> f64 {
     ranked
         .iter()
         .take(k)
         .enumerate()
         .filter(|(_, id)| relevant.contains(id))
-        .map(|(i, _)| 1.0 / (i as f64 + 2.0).log2())
+        .map(|(i, _)| gain_of_relevant / (i as f64 + 2.0).log2())
         .sum()
 }
 
@@ -192,6 +336,34 @@ pub fn ndcg_at_k<I: Eq + std::hash::Hash>(ranked: &[I], relevant: &HashSet<I>, k
     dcg_at_k(ranked, relevant, k) / ideal
 }
 
+/// nDCG@k of the reversed ranking: a pessimistic reference point.
+///
+/// Scores the same documents in the opposite order, giving a lower reference
+/// for interpreting [`ndcg_at_k`] on this ranking. A large gap between the two
+/// means the ranking's order matters for this query; a small gap means its
+/// nDCG is driven by which documents were retrieved rather than their order.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::binary::{ndcg_at_k, worst_case_ndcg};
+///
+/// let ranked = vec!["doc1", "doc2", "doc3"];
+/// let relevant: HashSet<_> = ["doc1"].into_iter().collect();
+///
+/// assert!(worst_case_ndcg(&ranked, &relevant, 3) < ndcg_at_k(&ranked, &relevant, 3));
+/// ```
+pub fn worst_case_ndcg<I: Eq + std::hash::Hash>(
+    ranked: &[I],
+    relevant: &HashSet<I>,
+    k: usize,
+) -> f64 {
+    let reversed: Vec<&I> = ranked.iter().rev().collect();
+    let relevant: HashSet<&I> = relevant.iter().collect();
+    ndcg_at_k(&reversed, &relevant, k)
+}
+
 /// Average Precision: average of precision at each relevant doc.
 ///
 /// Formula: `AP = (1/|R|) × Σᵢ (P@i × rel(i))`
@@ -236,6 +408,186 @@ pub fn average_precision<I: Eq + std::hash::Hash>(ranked: &[I], relevant: &HashS
     sum / relevant.len() as f64
 }
 
+/// Area under the precision-recall curve (AUPRC), by the trapezoidal rule.
+///
+/// The curve starts at (recall 0, precision 1) and adds one (recall@k, precision@k)
+/// point for each k from 1 to the ranking length; the area is the sum of
+/// trapezoids between consecutive points.
+///
+/// Relationship to AP: [`average_precision`] is the same area computed as a
+/// step function (precision held constant over each recall increment, sampled
+/// at relevant documents). The trapezoidal rule linearly interpolates between
+/// points instead, so AUPRC and AP agree only when every relevant document is
+/// ranked before every non-relevant one; otherwise AUPRC is typically lower.
+/// Unretrieved relevant documents truncate the curve below recall 1 in both.
+///
+/// Returns 0.0 if there are no relevant documents.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::binary::{auprc, average_precision};
+///
+/// let ranked = vec!["doc1", "doc2"];
+/// let relevant: HashSet<_> = ["doc1", "doc2"].into_iter().collect();
+///
+/// assert!((auprc(&ranked, &relevant) - average_precision(&ranked, &relevant)).abs() < 1e-9);
+/// ```
+pub fn auprc<I: Eq + std::hash::Hash>(ranked: &[I], relevant: &HashSet<I>) -> f64 {
+    if relevant.is_empty() {
+        return 0.0;
+    }
+
+    let mut area = 0.0;
+    let mut hits = 0;
+    let (mut prev_recall, mut prev_precision) = (0.0, 1.0);
+
+    for (i, id) in ranked.iter().enumerate() {
+        if relevant.contains(id) {
+            hits += 1;
+        }
+        let recall = hits as f64 / relevant.len() as f64;
+        let precision = hits as f64 / (i + 1) as f64;
+
+        area += (recall - prev_recall) * (precision + prev_precision) / 2.0;
+        prev_recall = recall;
+        prev_precision = precision;
+    }
+
+    area
+}
+
+/// Pairwise accuracy: fraction of (relevant, non-relevant) pairs ordered correctly.
+///
+/// Formula: `PA = |{(r, n) : rank(r) < rank(n)}| / (|R_ret| × |N_ret|)`
+///
+/// Where `R_ret` and `N_ret` are the relevant and non-relevant documents in
+/// `ranked`. Only retrieved documents form pairs. This is the ROC-AUC of the
+/// ranking; the number of misordered pairs is
+/// [`crate::statistics::swaps_to_ideal`].
+///
+/// Returns 0.0 if the ranking has no relevant or no non-relevant document.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::binary::pairwise_accuracy;
+///
+/// let ranked = vec!["doc1", "doc2", "doc3"];
+/// let relevant: HashSet<_> = ["doc2"].into_iter().collect();
+///
+/// // doc2 is above doc3 but below doc1
+/// assert!((pairwise_accuracy(&ranked, &relevant) - 0.5).abs() < 1e-9);
+/// ```
+pub fn pairwise_accuracy<I: Eq + std::hash::Hash>(ranked: &[I], relevant: &HashSet<I>) -> f64 {
+    let mut relevant_seen = 0usize;
+    let mut non_relevant_seen = 0usize;
+    let mut correct_pairs = 0usize;
+
+    for doc in ranked {
+        if relevant.contains(doc) {
+            relevant_seen += 1;
+        } else {
+            // Every relevant document above this one forms a correct pair
+            non_relevant_seen += 1;
+            correct_pairs += relevant_seen;
+        }
+    }
+
+    let total_pairs = relevant_seen * non_relevant_seen;
+    if total_pairs == 0 {
+        return 0.0;
+    }
+    correct_pairs as f64 / total_pairs as f64
+}
+
+/// Inferred Average Precision (infAP) for judgments sampled from the pool.
+///
+/// Formula: `infAP = (1/R̂) × Σ_{sampled relevant k} E[P@k] / sampling_rate`
+///
+/// Where, for a judged relevant document at rank k:
+/// - `E[P@1] = 1`
+/// - `E[P@k] = 1/k + ((k-1)/k) × (rel + ε) / (rel + nonrel + 2ε)`, with `rel`
+///   and `nonrel` the judged relevant and non-relevant documents above rank k
+/// - `R̂ = |judged_relevant| / sampling_rate` estimates the number of relevant
+///   documents in the full pool
+///
+/// Each sampled relevant document stands for `1/sampling_rate` relevant
+/// documents, so the rate cancels and infAP is the mean of `E[P@k]` over the
+/// sampled relevant documents: a uniformly subsampled pool estimates the
+/// full-pool AP. Documents above rank k that were not sampled are assumed to
+/// be relevant at the same rate as the judged ones; the pool-membership factor
+/// `|d100|/(k-1)` of Yilmaz & Aslam is omitted, i.e. every retrieved document
+/// is treated as part of the pool. With every retrieved document judged,
+/// infAP equals [`average_precision`] up to the smoothing constant `ε = 1e-5`.
+///
+/// Reference: Yilmaz & Aslam (2006) "Estimating average precision with incomplete and imperfect judgments"
+///
+/// # Arguments
+///
+/// * `ranked` - List of document IDs in ranked order
+/// * `judged_relevant` - Sampled documents judged relevant
+/// * `judged_nonrelevant` - Sampled documents judged non-relevant
+/// * `sampling_rate` - Fraction of the pool that was judged, in (0, 1]
+///
+/// # Returns
+///
+/// The infAP estimate, or 0.0 if nothing was judged relevant or
+/// `sampling_rate <= 0`.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::binary::inf_ap;
+///
+/// let ranked = vec!["doc1", "doc2", "doc3"];
+/// let relevant: HashSet<_> = ["doc1", "doc3"].into_iter().collect();
+/// let nonrelevant: HashSet<_> = ["doc2"].into_iter().collect();
+///
+/// let estimate = inf_ap(&ranked, &relevant, &nonrelevant, 0.5);
+/// assert!(estimate > 0.0 && estimate < 1.0);
+/// ```
+pub fn inf_ap<I: Eq + std::hash::Hash>(
+    ranked: &[I],
+    judged_relevant: &HashSet<I>,
+    judged_nonrelevant: &HashSet<I>,
+    sampling_rate: f64,
+) -> f64 {
+    const EPSILON: f64 = 1e-5;
+
+    if judged_relevant.is_empty() || sampling_rate <= 0.0 {
+        return 0.0;
+    }
+
+    let mut relevant_above = 0.0;
+    let mut nonrelevant_above = 0.0;
+    let mut sum = 0.0;
+
+    for (i, doc) in ranked.iter().enumerate() {
+        if judged_relevant.contains(doc) {
+            let k = (i + 1) as f64;
+            sum += if i == 0 {
+                1.0
+            } else {
+                let fraction_relevant = (relevant_above + EPSILON)
+                    / (relevant_above + nonrelevant_above + 2.0 * EPSILON);
+                1.0 / k + ((k - 1.0) / k) * fraction_relevant
+            };
+            relevant_above += 1.0;
+        } else if judged_nonrelevant.contains(doc) {
+            nonrelevant_above += 1.0;
+        }
+    }
+
+    // Scale the sum and R̂ alike: both stand for the full pool
+    let sampling_rate = sampling_rate.min(1.0);
+    let estimated_relevant = judged_relevant.len() as f64 / sampling_rate;
+    (sum / sampling_rate) / estimated_relevant
+}
+
 /// Expected Reciprocal Rank (ERR).
 ///
 /// ERR models user behavior using a cascade model where users scan results
@@ -349,6 +701,147 @@ pub fn rbp_at_k<I: Eq + std::hash::Hash>(
     (1.0 - persistence) * rbp
 }
 
+/// Expected utility under a caller-supplied examination model.
+///
+/// Formula: `EU = Σᵢ examine_prob(i) × utility(rel(i))`
+///
+/// Where `i` is the 1-based rank. This generalizes user-model metrics: with
+/// `examine_prob(i) = (1-p) × p^(i-1)` and a 0/1 utility it is [`rbp_at_k`]
+/// over the whole ranking. Utility for non-relevant documents may be negative
+/// to model the cost of wasted effort.
+///
+/// # Arguments
+///
+/// * `ranked` - List of document IDs in ranked order
+/// * `relevant` - Set of relevant document IDs
+/// * `examine_prob` - Probability (or weight) that the user examines rank i
+/// * `utility` - Gain from examining a document, given whether it is relevant
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::binary::expected_utility;
+///
+/// let ranked = vec!["doc1", "doc2"];
+/// let relevant: HashSet<_> = ["doc2"].into_iter().collect();
+///
+/// // User reads only the first result
+/// let eu = expected_utility(&ranked, &relevant, |i| if i == 1 { 1.0 } else { 0.0 }, |rel| {
+///     if rel { 1.0 } else { -0.5 }
+/// });
+/// assert_eq!(eu, -0.5);
+/// ```
+pub fn expected_utility<I, E, U>(
+    ranked: &[I],
+    relevant: &HashSet<I>,
+    examine_prob: E,
+    utility: U,
+) -> f64
+where
+    I: Eq + std::hash::Hash,
+    E: Fn(usize) -> f64,
+    U: Fn(bool) -> f64,
+{
+    ranked
+        .iter()
+        .enumerate()
+        .map(|(i, id)| examine_prob(i + 1) * utility(relevant.contains(id)))
+        .sum()
+}
+
+/// Expected number of documents examined by a cascade-model user.
+///
+/// Formula: `E[examined] = Σᵢ₌₁..ₖ Πⱼ<ᵢ (1 - p × rel(j))`
+///
+/// The user scans the top k in order and, after examining each relevant
+/// document, stops with probability `p = stop_prob_when_relevant`. With
+/// `p = 1.0` this is the rank of the first relevant document; if none is
+/// retrieved (or `p = 0.0`) the user examines all of the top k.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::binary::expected_examinations;
+///
+/// let ranked = vec!["doc1", "doc2", "doc3"];
+/// let relevant: HashSet<_> = ["doc1"].into_iter().collect();
+///
+/// // Stops at rank 1 half the time, otherwise reads all 3
+/// assert!((expected_examinations(&ranked, &relevant, 0.5, 10) - 2.0).abs() < 1e-9);
+/// ```
+pub fn expected_examinations<I: Eq + std::hash::Hash>(
+    ranked: &[I],
+    relevant: &HashSet<I>,
+    stop_prob_when_relevant: f64,
+    k: usize,
+) -> f64 {
+    let stop_prob = stop_prob_when_relevant.clamp(0.0, 1.0);
+    let mut p_reach = 1.0;
+    let mut expected = 0.0;
+
+    for doc in ranked.iter().take(k) {
+        expected += p_reach;
+        if relevant.contains(doc) {
+            p_reach *= 1.0 - stop_prob;
+        }
+    }
+
+    expected
+}
+
+/// Effort-normalized precision at k under a persistence (RBP) user model.
+///
+/// Formula: `EP@k = Σᵢ₌₁..ₖ rel(i) × pⁱ⁻¹ / Σᵢ₌₁..ₖ pⁱ⁻¹`
+///
+/// The user examines rank i with probability `pⁱ⁻¹`, where `p = persistence`.
+/// The numerator is the expected number of relevant documents seen and the
+/// denominator the expected number of documents examined, so EP@k is the
+/// precision per unit of effort. Relevant documents count for more where users
+/// actually look; with `p = 1.0` it is plain precision over the retrieved top
+/// k. Only retrieved documents are examined, so a short ranking is not
+/// penalized for its length. Returns 0.0 if nothing is examined.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::binary::effort_precision;
+///
+/// let relevant: HashSet<_> = ["doc1"].into_iter().collect();
+///
+/// let top = effort_precision(&["doc1", "doc2"], &relevant, 0.5, 10);
+/// let bottom = effort_precision(&["doc2", "doc1"], &relevant, 0.5, 10);
+/// assert!((top - 1.0 / 1.5).abs() < 1e-9);
+/// assert!((bottom - 0.5 / 1.5).abs() < 1e-9);
+/// ```
+pub fn effort_precision<I: Eq + std::hash::Hash>(
+    ranked: &[I],
+    relevant: &HashSet<I>,
+    persistence: f64,
+    k: usize,
+) -> f64 {
+    let persistence = persistence.clamp(0.0, 1.0);
+    let mut p_examine = 1.0;
+    let mut found = 0.0;
+    let mut examined = 0.0;
+
+    for id in ranked.iter().take(k) {
+        examined += p_examine;
+        if relevant.contains(id) {
+            found += p_examine;
+        }
+        p_examine *= persistence;
+    }
+
+    if examined == 0.0 {
+        0.0
+    } else {
+        found / examined
+    }
+}
+
 /// F-measure at k: harmonic mean of precision and recall.
 ///
 /// F@k = (1 + β²) × (P@k × R@k) / (β² × P@k + R@k)
@@ -391,6 +884,86 @@ pub fn f_measure_at_k<I: Eq + std::hash::Hash>(
     (1.0 + beta_sq) * (precision * recall) / (beta_sq * precision + recall)
 }
 
+/// Set precision: fraction of the retrieved set that is relevant (trec_eval `set_P`).
+///
+/// Formula: `P = |retrieved ∩ relevant| / |retrieved|`
+///
+/// Order-free: for a ranking, this equals [`precision_at_k`] with `k` set to
+/// the number of retrieved documents. Returns 0.0 if nothing is retrieved.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::binary::set_precision;
+///
+/// let retrieved: HashSet<_> = ["doc1", "doc2"].into_iter().collect();
+/// let relevant: HashSet<_> = ["doc1", "doc3"].into_iter().collect();
+///
+/// assert_eq!(set_precision(&retrieved, &relevant), 0.5);
+/// ```
+pub fn set_precision<I: Eq + std::hash::Hash>(retrieved: &HashSet<I>, relevant: &HashSet<I>) -> f64 {
+    if retrieved.is_empty() {
+        return 0.0;
+    }
+    retrieved.intersection(relevant).count() as f64 / retrieved.len() as f64
+}
+
+/// Set recall: fraction of the relevant set that is retrieved (trec_eval `set_recall`).
+///
+/// Formula: `R = |retrieved ∩ relevant| / |relevant|`
+///
+/// Returns 0.0 if there are no relevant documents.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::binary::set_recall;
+///
+/// let retrieved: HashSet<_> = ["doc1", "doc2"].into_iter().collect();
+/// let relevant: HashSet<_> = ["doc1", "doc3"].into_iter().collect();
+///
+/// assert_eq!(set_recall(&retrieved, &relevant), 0.5);
+/// ```
+pub fn set_recall<I: Eq + std::hash::Hash>(retrieved: &HashSet<I>, relevant: &HashSet<I>) -> f64 {
+    if relevant.is_empty() {
+        return 0.0;
+    }
+    retrieved.intersection(relevant).count() as f64 / relevant.len() as f64
+}
+
+/// Set F-measure over the retrieved set (trec_eval `set_F`).
+///
+/// F = (1 + β²) × (P × R) / (β² × P + R), using [`set_precision`] and [`set_recall`].
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::binary::set_f;
+///
+/// let retrieved: HashSet<_> = ["doc1", "doc2"].into_iter().collect();
+/// let relevant: HashSet<_> = ["doc1", "doc3"].into_iter().collect();
+///
+/// assert!((set_f(&retrieved, &relevant, 1.0) - 0.5).abs() < 1e-9);
+/// ```
+pub fn set_f<I: Eq + std::hash::Hash>(
+    retrieved: &HashSet<I>,
+    relevant: &HashSet<I>,
+    beta: f64,
+) -> f64 {
+    let precision = set_precision(retrieved, relevant);
+    let recall = set_recall(retrieved, relevant);
+
+    if precision == 0.0 && recall == 0.0 {
+        return 0.0;
+    }
+
+    let beta_sq = beta * beta;
+    (1.0 + beta_sq) * (precision * recall) / (beta_sq * precision + recall)
+}
+
 /// Success at k: whether at least one relevant document is in top-k.
 ///
 /// Returns 1.0 if at least one relevant doc is in top-k, 0.0 otherwise.
@@ -447,6 +1020,52 @@ pub fn r_precision<I: Eq + std::hash::Hash>(ranked: &[I], relevant: &HashSet<I>)
     precision_at_k(ranked, relevant, r)
 }
 
+/// Evaluate a metric on scored documents with caller-controlled tie-breaking.
+///
+/// Documents are sorted by score (descending); documents with equal scores are
+/// ordered by `tie_break`. The resulting ranking is passed to `metric`. This
+/// removes the hidden dependence on input order when scores tie.
+///
+/// # Arguments
+///
+/// * `scored` - (document_id, score) pairs in any order
+/// * `relevant` - Set of relevant document IDs
+/// * `tie_break` - Ordering applied between documents with equal scores
+/// * `metric` - Metric to compute on the sorted ranking
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::binary::{evaluate_with_tie_break, ndcg_at_k};
+///
+/// let scored = vec![("doc2", 0.5), ("doc1", 0.5)];
+/// let relevant: HashSet<_> = ["doc1"].into_iter().collect();
+///
+/// // Lexicographic tie-break puts doc1 first
+/// let ndcg = evaluate_with_tie_break(&scored, &relevant, |a, b| a.cmp(b), |r, rel| {
+///     ndcg_at_k(r, rel, 10)
+/// });
+/// assert!((ndcg - 1.0).abs() < 1e-9);
+/// ```
+pub fn evaluate_with_tie_break<I, T, M>(
+    scored: &[(I, f32)],
+    relevant: &HashSet<I>,
+    tie_break: T,
+    metric: M,
+) -> f64
+where
+    I: Eq + std::hash::Hash + Clone,
+    T: Fn(&I, &I) -> Ordering,
+    M: Fn(&[I], &HashSet<I>) -> f64,
+{
+    let mut sorted: Vec<&(I, f32)> = scored.iter().collect();
+    sorted.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| tie_break(&a.0, &b.0)));
+    let ranked: Vec<I> = sorted.into_iter().map(|(id, _)| id.clone()).collect();
+
+    metric(&ranked, relevant)
+}
+
 /// All metrics for a single ranking (binary relevance).
 #[cfg(feature = "serde")]
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -494,6 +1113,29 @@ impl Metrics {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_relevant_from_qrels() {
+        let qrels: HashMap<_, _> = [("a", 0), ("b", 1), ("c", 2), ("d", 3)].into_iter().collect();
+
+        let any: HashSet<_> = ["b", "c", "d"].into_iter().collect();
+        assert_eq!(relevant_from_qrels(&qrels, 1), any);
+        let high: HashSet<_> = ["c", "d"].into_iter().collect();
+        assert_eq!(relevant_from_qrels(&qrels, 2), high);
+        assert_eq!(relevant_from_qrels(&qrels, 0).len(), 4);
+        assert!(relevant_from_qrels(&qrels, 4).is_empty());
+    }
+
+    #[test]
+    fn test_dcg_at_k_gain() {
+        let ranked = vec!["a", "b", "c", "d"];
+        let relevant: HashSet<_> = ["b", "d"].into_iter().collect();
+
+        let expected = 1.0 / 3f64.log2() + 1.0 / 5f64.log2();
+        assert!((dcg_at_k_gain(&ranked, &relevant, 4, 1.0) - expected).abs() < 1e-12);
+        assert_eq!(dcg_at_k_gain(&ranked, &relevant, 4, 1.0), dcg_at_k(&ranked, &relevant, 4));
+        assert!((dcg_at_k_gain(&ranked, &relevant, 4, 2.5) - 2.5 * expected).abs() < 1e-12);
+    }
+
     #[test]
     fn test_precision_at_k() {
         let ranked = vec!["a", "b", "c", "d", "e"];
@@ -617,7 +1259,7 @@ mod tests {
 
         // F2 score (beta=2.0, emphasizes recall)
         let f2 = f_measure_at_k(&ranked, &relevant, 3, 2.0);
-        assert!(f2 >= 0.0 && f2 <= 1.0);
+        assert!((0.0..=1.0).contains(&f2));
         // F2 should be >= F1 when recall > precision
         if recall > precision {
             assert!(f2 >= f1);
@@ -653,5 +1295,196 @@ mod tests {
         let r_prec2 = r_precision(&ranked2, &relevant);
         assert!((r_prec2 - 1.0).abs() < 1e-9);
     }
-}
 
+    #[test]
+    fn test_evaluate_with_tie_break() {
+        let scored = vec![("a", 0.9), ("c", 0.5), ("b", 0.5)];
+        let relevant: HashSet<_> = ["c"].into_iter().collect();
+        let ndcg = |r: &[&str], rel: &HashSet<&str>| ndcg_at_k(r, rel, 3);
+
+        let ascending = evaluate_with_tie_break(&scored, &relevant, |a, b| a.cmp(b), ndcg);
+        let descending = evaluate_with_tie_break(&scored, &relevant, |a, b| b.cmp(a), ndcg);
+
+        // Ascending puts "b" before "c" (rank 3); descending puts "c" at rank 2
+        assert!((ascending - 1.0 / 4.0_f64.log2()).abs() < 1e-9);
+        assert!((descending - 1.0 / 3.0_f64.log2()).abs() < 1e-9);
+        assert!(descending > ascending);
+    }
+
+    #[test]
+    fn test_first_relevant_rank_matches_mrr() {
+        let relevant: HashSet<_> = ["x"].into_iter().collect();
+        let cases: Vec<Vec<&str>> = vec![
+            vec!["x", "a", "b"],
+            vec!["a", "x", "b"],
+            vec!["a", "b", "c", "d", "x"],
+            vec!["a", "b"],
+            vec![],
+        ];
+
+        for ranked in cases {
+            let rr = mrr(&ranked, &relevant);
+            match first_relevant_rank(&ranked, &relevant) {
+                Some(rank) => assert!((rr - 1.0 / rank as f64).abs() < 1e-9),
+                None => assert_eq!(rr, 0.0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_pairwise_accuracy() {
+        let relevant: HashSet<_> = ["a", "b"].into_iter().collect();
+
+        assert_eq!(pairwise_accuracy(&["a", "b", "c", "d"], &relevant), 1.0);
+        assert_eq!(pairwise_accuracy(&["d", "c", "b", "a"], &relevant), 0.0);
+        assert!((pairwise_accuracy(&["a", "c", "b", "d"], &relevant) - 0.75).abs() < 1e-9);
+        assert_eq!(pairwise_accuracy(&["a", "b"], &relevant), 0.0);
+    }
+
+    #[test]
+    fn test_inf_ap_full_pool_matches_ap() {
+        let ranked = vec!["a", "b", "c", "d", "e", "f"];
+        let relevant: HashSet<_> = ["b", "c", "f", "z"].into_iter().collect();
+        let nonrelevant: HashSet<_> = ["a", "d", "e"].into_iter().collect();
+
+        let ap = average_precision(&ranked, &relevant);
+        let inferred = inf_ap(&ranked, &relevant, &nonrelevant, 1.0);
+        assert!((inferred - ap).abs() < 1e-4);
+
+        assert_eq!(inf_ap(&ranked, &HashSet::new(), &nonrelevant, 1.0), 0.0);
+    }
+
+    #[test]
+    fn test_inf_ap_uniform_subsample_estimates_full_ap() {
+        // Relevant documents thin out down the ranking
+        let ranked: Vec<usize> = (0..300).collect();
+        let is_relevant = |i: usize| if i < 60 { i % 3 == 0 } else { i % 10 == 0 };
+        let relevant: HashSet<usize> = ranked.iter().copied().filter(|&i| is_relevant(i)).collect();
+        let nonrelevant: HashSet<usize> =
+            ranked.iter().copied().filter(|&i| !is_relevant(i)).collect();
+        let ap = average_precision(&ranked, &relevant);
+
+        // Judge every other document, independent of relevance
+        let sample = |set: &HashSet<usize>| -> HashSet<usize> {
+            set.iter().copied().filter(|i| i % 2 == 0).collect()
+        };
+        let estimate = inf_ap(&ranked, &sample(&relevant), &sample(&nonrelevant), 0.5);
+        assert!((estimate - ap).abs() < 0.05, "infAP {} vs AP {}", estimate, ap);
+    }
+
+    #[test]
+    fn test_auprc_vs_ap() {
+        let relevant: HashSet<_> = ["a", "c"].into_iter().collect();
+        let ranked = vec!["a", "b", "c"];
+
+        // Points: (0, 1), (0.5, 1), (0.5, 0.5), (1, 2/3)
+        let expected = 0.5 + 0.5 * (0.5 + 2.0 / 3.0) / 2.0;
+        assert!((auprc(&ranked, &relevant) - expected).abs() < 1e-9);
+
+        // AP uses the step function: (1 + 2/3) / 2
+        let ap = average_precision(&ranked, &relevant);
+        assert!((ap - 5.0 / 6.0).abs() < 1e-9);
+        assert!(auprc(&ranked, &relevant) < ap);
+
+        // A perfect ranking gives equal values
+        let perfect = vec!["a", "c", "b"];
+        assert!((auprc(&perfect, &relevant) - average_precision(&perfect, &relevant)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_relevant_before_first_nonrelevant() {
+        let relevant: HashSet<_> = ["a", "b", "c", "e"].into_iter().collect();
+        let ranked = vec!["a", "b", "c", "d", "e"];
+        assert_eq!(relevant_before_first_nonrelevant(&ranked, &relevant), 3);
+        assert_eq!(relevant_before_first_nonrelevant(&["d", "a"], &relevant), 0);
+    }
+
+    #[test]
+    fn test_worst_case_ndcg() {
+        let relevant: HashSet<_> = ["a", "b"].into_iter().collect();
+        let ranked = vec!["a", "b", "c", "d", "e"];
+
+        let actual = ndcg_at_k(&ranked, &relevant, 5);
+        let worst = worst_case_ndcg(&ranked, &relevant, 5);
+        assert!(worst <= actual);
+        assert!((actual - 1.0).abs() < 1e-9);
+
+        // Relevant docs at the bottom: only the reversed ranking reaches them at k=2
+        assert_eq!(worst_case_ndcg(&ranked, &relevant, 2), 0.0);
+    }
+
+    #[test]
+    fn test_rank_of_first_relevant_stats() {
+        let ranks = vec![
+            ("q1".to_string(), Some(1)),
+            ("q2".to_string(), Some(2)),
+            ("q3".to_string(), Some(6)),
+            ("q4".to_string(), None),
+        ];
+        let (mean, found, not_found) = rank_of_first_relevant_stats(&ranks);
+        assert!((mean - 3.0).abs() < 1e-9);
+        assert_eq!((found, not_found), (3, 1));
+
+        assert_eq!(rank_of_first_relevant_stats(&[]), (0.0, 0, 0));
+    }
+
+    #[test]
+    fn test_set_measures() {
+        let relevant: HashSet<_> = ["a", "c", "e"].into_iter().collect();
+        let ranked = vec!["a", "b", "c", "d"];
+        let reordered = vec!["d", "c", "b", "a"];
+        let retrieved: HashSet<_> = ranked.iter().copied().collect();
+
+        let set_p = set_precision(&retrieved, &relevant);
+        assert!((set_p - precision_at_k(&ranked, &relevant, ranked.len())).abs() < 1e-9);
+        assert!((set_p - precision_at_k(&reordered, &relevant, reordered.len())).abs() < 1e-9);
+
+        let set_r = set_recall(&retrieved, &relevant);
+        assert!((set_r - 2.0 / 3.0).abs() < 1e-9);
+        assert!(
+            (set_f(&retrieved, &relevant, 1.0) - f_measure_at_k(&ranked, &relevant, 4, 1.0)).abs()
+                < 1e-9
+        );
+    }
+
+    #[test]
+    fn test_expected_examinations() {
+        let ranked = vec!["a", "b", "c", "d", "e"];
+        let relevant: HashSet<_> = ["c", "e"].into_iter().collect();
+
+        // Certain stop: examine exactly up to the first relevant document
+        assert_eq!(expected_examinations(&ranked, &relevant, 1.0, 10), 3.0);
+        // Never stop: examine the whole top k
+        assert_eq!(expected_examinations(&ranked, &relevant, 0.0, 4), 4.0);
+        assert_eq!(expected_examinations(&ranked, &HashSet::new(), 1.0, 10), 5.0);
+    }
+
+    #[test]
+    fn test_expected_utility_reproduces_rbp() {
+        let ranked = vec!["a", "b", "c", "d", "e"];
+        let relevant: HashSet<_> = ["a", "c", "e"].into_iter().collect();
+        let p: f64 = 0.8;
+
+        let eu = expected_utility(
+            &ranked,
+            &relevant,
+            |i| (1.0 - p) * p.powi(i as i32 - 1),
+            |rel| if rel { 1.0 } else { 0.0 },
+        );
+        assert!((eu - rbp_at_k(&ranked, &relevant, ranked.len(), p)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_effort_precision_rewards_moving_relevant_up() {
+        let relevant: HashSet<_> = ["r"].into_iter().collect();
+
+        let deep = effort_precision(&["a", "b", "r", "c"], &relevant, 0.8, 4);
+        let shallow = effort_precision(&["a", "r", "b", "c"], &relevant, 0.8, 4);
+        let first = effort_precision(&["r", "a", "b", "c"], &relevant, 0.8, 4);
+        assert!(deep < shallow && shallow < first);
+
+        // Full persistence is plain precision
+        assert_eq!(effort_precision(&["a", "b", "r", "c"], &relevant, 1.0, 4), 0.25);
+        assert_eq!(effort_precision::<&str>(&[], &relevant, 0.8, 4), 0.0);
+    }
+}
@@ -5,6 +5,7 @@
 use crate::trec::{Qrel, TrecRun, load_trec_runs, load_qrels};
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
 
 /// Dataset metadata.
@@ -101,6 +102,74 @@ pub fn load_trec_qrels_from_dir(qrels_dir: impl AsRef<Path>) -> Result<Vec<Qrel>
     anyhow::bail!("No qrels file found in {:?}. Tried: {:?}", qrels_dir.as_ref(), possible_names);
 }
 
+/// Read a `key<TAB>value` file as `(line_number, key, value)` triples.
+///
+/// Empty lines and lines starting with `#` are skipped, a leading BOM is
+/// stripped, and keys and values are trimmed. `file_kind` and `value_name`
+/// name the file and its value column in error messages.
+fn read_tsv_pairs(
+    path: &Path,
+    file_kind: &str,
+    value_name: &str,
+) -> Result<Vec<(usize, String, String)>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to open {} file: {:?}", file_kind, path))?;
+    let mut pairs = Vec::new();
+
+    for (line_num, line) in content.lines().enumerate() {
+        let line = line.trim_start_matches('\u{feff}');
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = line.split_once('\t').ok_or_else(|| {
+            anyhow::anyhow!(
+                "Line {}: Invalid {} format. Expected query_id<TAB>{}\nLine: {}",
+                line_num + 1,
+                file_kind,
+                value_name,
+                line
+            )
+        })?;
+        pairs.push((line_num + 1, key.trim().to_string(), value.trim().to_string()));
+    }
+
+    Ok(pairs)
+}
+
+/// Load a topics file mapping query IDs to query text.
+///
+/// Format: one `query_id<TAB>text` pair per line. Empty lines and lines
+/// starting with `#` are skipped; the text is trimmed. Pass the result to
+/// [`crate::export::export_to_csv_with_topics`] for human-readable reports.
+pub fn load_topics(path: impl AsRef<Path>) -> Result<HashMap<String, String>> {
+    let pairs = read_tsv_pairs(path.as_ref(), "topics", "text")?;
+    Ok(pairs.into_iter().map(|(_, query_id, text)| (query_id, text)).collect())
+}
+
+/// Load per-query weights, e.g. production traffic counts.
+///
+/// Format: one `query_id<TAB>weight` pair per line. Empty lines and lines
+/// starting with `#` are skipped. Weights must be finite and non-negative.
+/// Pass the result to [`crate::batch::weighted_ndcg`] to aggregate nDCG by
+/// how often each query is actually issued.
+pub fn load_query_weights(path: impl AsRef<Path>) -> Result<HashMap<String, f64>> {
+    let pairs = read_tsv_pairs(path.as_ref(), "query weights", "weight")?;
+    let mut weights = HashMap::new();
+
+    for (line_num, query_id, weight) in pairs {
+        let weight: f64 = weight
+            .parse()
+            .with_context(|| format!("Line {}: Invalid weight: {}", line_num, weight))?;
+        if !weight.is_finite() || weight < 0.0 {
+            anyhow::bail!("Line {}: Weight must be finite and non-negative: {}", line_num, weight);
+        }
+        weights.insert(query_id, weight);
+    }
+
+    Ok(weights)
+}
+
 /// Create a dataset configuration file.
 pub fn create_dataset_config(
     name: &str,
@@ -2,7 +2,7 @@
 //!
 //! Provides detailed statistics about datasets, run files, and qrels.
 
-use crate::trec::{Qrel, TrecRun};
+use crate::trec::{group_runs_by_query, Qrel, TrecRun};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 
@@ -106,6 +106,354 @@ pub fn compute_comprehensive_stats(
     }
 }
 
+/// Compute score distributions for relevant and non-relevant retrieved documents.
+///
+/// A run entry is relevant if its (query, document) pair has grade > 0 in
+/// `qrels`; unjudged documents count as non-relevant. Well-separated
+/// distributions (relevant scores systematically higher) indicate a
+/// discriminative scorer.
+///
+/// Returns `(relevant, non_relevant)` distributions.
+pub fn relevance_conditioned_score_dist(
+    runs: &[TrecRun],
+    qrels: &[Qrel],
+) -> (ScoreDistribution, ScoreDistribution) {
+    let relevant_pairs: HashSet<(&str, &str)> = qrels
+        .iter()
+        .filter(|q| q.relevance > 0)
+        .map(|q| (q.query_id.as_str(), q.doc_id.as_str()))
+        .collect();
+
+    let (mut relevant, mut non_relevant): (Vec<f32>, Vec<f32>) = (Vec::new(), Vec::new());
+    for run in runs {
+        if relevant_pairs.contains(&(run.query_id.as_str(), run.doc_id.as_str())) {
+            relevant.push(run.score);
+        } else {
+            non_relevant.push(run.score);
+        }
+    }
+
+    relevant.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    non_relevant.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+
+    (
+        compute_score_distribution(&relevant),
+        compute_score_distribution(&non_relevant),
+    )
+}
+
+/// List relevant documents that no run retrieves, per query.
+///
+/// A document is unreachable if it has grade > 0 in `qrels` but appears in no
+/// run (under any run tag) for its query. These documents set an absolute
+/// recall ceiling: no fusion or re-ranking of the given runs can retrieve them.
+///
+/// Returns query_id -> sorted unreachable doc_ids; queries where every
+/// relevant document is retrieved somewhere are omitted.
+pub fn unreachable_relevant(runs: &[TrecRun], qrels: &[Qrel]) -> HashMap<String, Vec<String>> {
+    let retrieved: HashSet<(&str, &str)> = runs
+        .iter()
+        .map(|r| (r.query_id.as_str(), r.doc_id.as_str()))
+        .collect();
+
+    let mut unreachable: HashMap<String, Vec<String>> = HashMap::new();
+    for qrel in qrels {
+        if qrel.relevance > 0
+            && !retrieved.contains(&(qrel.query_id.as_str(), qrel.doc_id.as_str()))
+        {
+            unreachable
+                .entry(qrel.query_id.clone())
+                .or_default()
+                .push(qrel.doc_id.clone());
+        }
+    }
+
+    for docs in unreachable.values_mut() {
+        docs.sort();
+        docs.dedup();
+    }
+
+    unreachable
+}
+
+/// Count adjacent score/rank inversions per run tag.
+///
+/// Within each (query, run tag) group, entries are ordered by rank and each
+/// adjacent pair where the lower-ranked entry has a strictly higher score
+/// counts as one inversion. A healthy run has 0; every tag in `runs` appears
+/// in the result.
+pub fn score_rank_inversions(runs: &[TrecRun]) -> HashMap<String, usize> {
+    let mut groups: HashMap<(&str, &str), Vec<&TrecRun>> = HashMap::new();
+    for run in runs {
+        groups
+            .entry((run.query_id.as_str(), run.run_tag.as_str()))
+            .or_default()
+            .push(run);
+    }
+
+    let mut inversions: HashMap<String, usize> = HashMap::new();
+    for ((_, tag), mut group) in groups {
+        group.sort_by_key(|r| r.rank);
+        let count = group
+            .windows(2)
+            .filter(|pair| pair[1].score > pair[0].score)
+            .count();
+        *inversions.entry(tag.to_string()).or_insert(0) += count;
+    }
+
+    inversions
+}
+
+/// Mean top-k Jaccard overlap between each pair of run tags.
+///
+/// For every query retrieved by both tags, the overlap is
+/// `|top_k(a) ∩ top_k(b)| / |top_k(a) ∪ top_k(b)|`, with each run ordered by
+/// score; the result is the mean over those queries. Low overlap means the
+/// systems retrieve different documents, so fusing them is more likely to
+/// help. The map is symmetric: both `(a, b)` and `(b, a)` are present. Pairs
+/// that share no query are omitted.
+pub fn run_overlap_matrix(runs: &[TrecRun], k: usize) -> HashMap<(String, String), f64> {
+    let mut totals: HashMap<(String, String), (f64, usize)> = HashMap::new();
+
+    for query_runs in group_runs_by_query(runs).values() {
+        let top_k: Vec<(&String, HashSet<&String>)> = query_runs
+            .iter()
+            .map(|(tag, ranked)| (tag, ranked.iter().take(k).map(|(id, _)| id).collect()))
+            .collect();
+
+        for (i, (tag_a, docs_a)) in top_k.iter().enumerate() {
+            for (tag_b, docs_b) in &top_k[i + 1..] {
+                let union = docs_a.union(docs_b).count();
+                let jaccard = if union > 0 {
+                    docs_a.intersection(docs_b).count() as f64 / union as f64
+                } else {
+                    0.0
+                };
+                for key in [
+                    ((*tag_a).clone(), (*tag_b).clone()),
+                    ((*tag_b).clone(), (*tag_a).clone()),
+                ] {
+                    let entry = totals.entry(key).or_insert((0.0, 0));
+                    entry.0 += jaccard;
+                    entry.1 += 1;
+                }
+            }
+        }
+    }
+
+    totals
+        .into_iter()
+        .map(|(pair, (sum, count))| (pair, sum / count as f64))
+        .collect()
+}
+
+/// Estimate the value of judging each unjudged top-k document.
+///
+/// For every unjudged document in the top k of some run, the value is the
+/// binary nDCG@k increase that run would see if the document were judged
+/// relevant (it also joins the ideal ranking), averaged over all run tags that
+/// retrieved the query. Documents high in many runs score highest, so sorting
+/// by value gives a judging priority. The value can be slightly negative for a
+/// deep document when the query has relevant documents no run retrieved.
+///
+/// Returns (query_id, doc_id) -> estimated nDCG@k gain.
+pub fn judgment_value_estimate(
+    runs: &[TrecRun],
+    qrels: &[Qrel],
+    k: usize,
+) -> HashMap<(String, String), f64> {
+    use crate::binary::ndcg_at_k;
+    use crate::trec::group_qrels_by_query;
+
+    let qrels_by_query = group_qrels_by_query(qrels);
+    let no_judgments = HashMap::new();
+    let mut values: HashMap<(String, String), f64> = HashMap::new();
+
+    for (query_id, query_runs) in group_runs_by_query(runs) {
+        let judged = qrels_by_query.get(&query_id).unwrap_or(&no_judgments);
+        let relevant: HashSet<&String> = judged
+            .iter()
+            .filter(|(_, &grade)| grade > 0)
+            .map(|(id, _)| id)
+            .collect();
+        let n_tags = query_runs.len() as f64;
+
+        for ranked in query_runs.values() {
+            let ranked_ids: Vec<&String> = ranked.iter().map(|(id, _)| id).collect();
+            let current = ndcg_at_k(&ranked_ids, &relevant, k);
+
+            for doc_id in ranked_ids.iter().take(k) {
+                if judged.contains_key(*doc_id) {
+                    continue;
+                }
+                let mut with_doc = relevant.clone();
+                with_doc.insert(doc_id);
+                let gain = ndcg_at_k(&ranked_ids, &with_doc, k) - current;
+                *values
+                    .entry((query_id.clone(), (*doc_id).clone()))
+                    .or_insert(0.0) += gain / n_tags;
+            }
+        }
+    }
+
+    values
+}
+
+/// Estimate, per query, how much reciprocal rank fusion could gain over the best run.
+///
+/// Fuses all run tags for the query with RRF (`score(d) = Σ 1 / (60 + rank)`
+/// over the runs that retrieve `d`) and returns the fused binary nDCG@k minus
+/// the best single run's nDCG@k. Complementary runs, which retrieve different
+/// relevant documents, give positive potential; identical runs give 0. The
+/// value is negative when fusion would dilute a clearly better run. Queries
+/// with a single run tag have potential 0.
+///
+/// Returns query_id -> potential, for every query with runs.
+pub fn fusion_potential(runs: &[TrecRun], qrels: &[Qrel], k: usize) -> HashMap<String, f64> {
+    use crate::binary::ndcg_at_k;
+    use crate::trec::group_qrels_by_query;
+
+    const RRF_K: f64 = 60.0;
+
+    let qrels_by_query = group_qrels_by_query(qrels);
+    let mut potential = HashMap::new();
+
+    for (query_id, query_runs) in group_runs_by_query(runs) {
+        let relevant: HashSet<&String> = qrels_by_query
+            .get(&query_id)
+            .map(|judged| {
+                judged
+                    .iter()
+                    .filter(|(_, &grade)| grade > 0)
+                    .map(|(id, _)| id)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut best_single = 0.0f64;
+        let mut fused_scores: HashMap<&String, f64> = HashMap::new();
+        for ranked in query_runs.values() {
+            let ranked_ids: Vec<&String> = ranked.iter().map(|(id, _)| id).collect();
+            best_single = best_single.max(ndcg_at_k(&ranked_ids, &relevant, k));
+            for (rank, id) in ranked_ids.iter().enumerate() {
+                *fused_scores.entry(id).or_insert(0.0) += 1.0 / (RRF_K + (rank + 1) as f64);
+            }
+        }
+
+        let mut fused: Vec<(&String, f64)> = fused_scores.into_iter().collect();
+        fused.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        let fused_ids: Vec<&String> = fused.into_iter().map(|(id, _)| id).collect();
+
+        potential.insert(query_id, ndcg_at_k(&fused_ids, &relevant, k) - best_single);
+    }
+
+    potential
+}
+
+/// Count, per run tag, how many distinct queries retrieve each document.
+///
+/// A run that returns the same few popular documents for unrelated queries
+/// shows a handful of documents with frequencies close to the number of
+/// queries. Repeated entries of a document within one query count once.
+///
+/// Returns run_tag -> doc_id -> number of queries retrieving it.
+pub fn document_query_frequency(runs: &[TrecRun]) -> HashMap<String, HashMap<String, usize>> {
+    let pairs: HashSet<(&str, &str, &str)> = runs
+        .iter()
+        .map(|run| (run.run_tag.as_str(), run.doc_id.as_str(), run.query_id.as_str()))
+        .collect();
+
+    let mut frequency: HashMap<String, HashMap<String, usize>> = HashMap::new();
+    for (run_tag, doc_id, _) in pairs {
+        *frequency
+            .entry(run_tag.to_string())
+            .or_default()
+            .entry(doc_id.to_string())
+            .or_insert(0) += 1;
+    }
+
+    frequency
+}
+
+/// Find documents that are unjudged yet reach the top k for many queries.
+///
+/// For each document, counts the queries where it appears in the top k of
+/// any run tag without a judgment for that query. Documents a run keeps
+/// surfacing unjudged are prime judging candidates, since each judgment
+/// settles the document's contribution to several queries at once.
+///
+/// Returns `(doc_id, query count)` for documents counted in at least
+/// `min_queries` queries, most frequent first (ties by doc ID).
+pub fn frequent_unjudged_top_docs(
+    runs: &[TrecRun],
+    qrels: &[Qrel],
+    k: usize,
+    min_queries: usize,
+) -> Vec<(String, usize)> {
+    let judged: HashSet<(&str, &str)> = qrels
+        .iter()
+        .map(|q| (q.query_id.as_str(), q.doc_id.as_str()))
+        .collect();
+
+    let mut queries_by_doc: HashMap<String, HashSet<String>> = HashMap::new();
+    for (query_id, query_runs) in group_runs_by_query(runs) {
+        for ranked in query_runs.values() {
+            for (doc_id, _) in ranked.iter().take(k) {
+                if !judged.contains(&(query_id.as_str(), doc_id.as_str())) {
+                    queries_by_doc
+                        .entry(doc_id.clone())
+                        .or_default()
+                        .insert(query_id.clone());
+                }
+            }
+        }
+    }
+
+    let mut frequent: Vec<(String, usize)> = queries_by_doc
+        .into_iter()
+        .map(|(doc_id, queries)| (doc_id, queries.len()))
+        .filter(|&(_, count)| count >= min_queries)
+        .collect();
+    frequent.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    frequent
+}
+
+/// Count judged documents per query.
+///
+/// Every judgment counts regardless of grade (non-relevant included), and a
+/// document judged twice for the same query counts once. Unlike
+/// `QrelStatistics::queries_with_relevant`, this shows how deeply each query
+/// was assessed. See [`judgment_count_summary`] for mean/min/max.
+pub fn judgments_per_query(qrels: &[Qrel]) -> HashMap<String, usize> {
+    let judged: HashSet<(&str, &str)> = qrels
+        .iter()
+        .map(|q| (q.query_id.as_str(), q.doc_id.as_str()))
+        .collect();
+
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for (query_id, _) in judged {
+        *counts.entry(query_id.to_string()).or_insert(0) += 1;
+    }
+
+    counts
+}
+
+/// Mean, min, and max of per-query judgment counts from [`judgments_per_query`].
+///
+/// Returns `(0.0, 0, 0)` for an empty map.
+pub fn judgment_count_summary(counts: &HashMap<String, usize>) -> (f64, usize, usize) {
+    if counts.is_empty() {
+        return (0.0, 0, 0);
+    }
+
+    let total: usize = counts.values().sum();
+    let min = counts.values().copied().min().unwrap_or(0);
+    let max = counts.values().copied().max().unwrap_or(0);
+
+    (total as f64 / counts.len() as f64, min, max)
+}
+
 /// Compute statistics for run files.
 fn compute_run_statistics(runs: &[TrecRun]) -> RunStatistics {
     if runs.is_empty() {
@@ -389,6 +737,166 @@ fn compute_score_distribution(scores: &[f32]) -> ScoreDistribution {
     }
 }
 
+/// Compression (δ) of the t-digest used by [`streaming_score_distribution`].
+/// The digest holds on the order of δ centroids.
+const TDIGEST_COMPRESSION: f64 = 100.0;
+
+/// A merging t-digest (Dunning & Ertl) for approximate quantiles.
+///
+/// Values are buffered and periodically merged into centroids whose size is
+/// bounded by the arcsine scale function, so centroids are small near the
+/// tails and large in the middle of the distribution.
+struct TDigest {
+    /// (mean, weight) pairs, sorted by mean
+    centroids: Vec<(f64, f64)>,
+    buffer: Vec<f64>,
+}
+
+impl TDigest {
+    fn new() -> Self {
+        Self {
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.buffer.push(value);
+        if self.buffer.len() >= 10 * TDIGEST_COMPRESSION as usize {
+            self.compress();
+        }
+    }
+
+    /// Scale function k₁(q) = δ/(2π) × asin(2q - 1).
+    fn scale(q: f64) -> f64 {
+        TDIGEST_COMPRESSION / (2.0 * std::f64::consts::PI) * (2.0 * q.clamp(0.0, 1.0) - 1.0).asin()
+    }
+
+    /// Merge buffered values into the centroids.
+    fn compress(&mut self) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        let mut items: Vec<(f64, f64)> = self.centroids.drain(..).collect();
+        items.extend(self.buffer.drain(..).map(|value| (value, 1.0)));
+        items.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let total: f64 = items.iter().map(|(_, weight)| weight).sum();
+        let mut merged = Vec::new();
+        let mut current = items[0];
+        let mut weight_before = 0.0;
+
+        for &(mean, weight) in &items[1..] {
+            let q_left = weight_before / total;
+            let q_right = (weight_before + current.1 + weight) / total;
+            if Self::scale(q_right) - Self::scale(q_left) <= 1.0 {
+                current.1 += weight;
+                current.0 += (mean - current.0) * weight / current.1;
+            } else {
+                weight_before += current.1;
+                merged.push(current);
+                current = (mean, weight);
+            }
+        }
+        merged.push(current);
+
+        self.centroids = merged;
+    }
+
+    /// Approximate quantile `q` in [0, 1], interpolating between centroid
+    /// centers and out to the exact `min` and `max`. Call after `compress`.
+    fn quantile(&self, q: f64, min: f64, max: f64) -> f64 {
+        let total: f64 = self.centroids.iter().map(|(_, weight)| weight).sum();
+        let target = q * total;
+
+        let mut previous = (min, 0.0); // (value, cumulative weight)
+        let mut cumulative = 0.0;
+        for &(mean, weight) in &self.centroids {
+            let center = cumulative + weight / 2.0;
+            if target < center {
+                let span = center - previous.1;
+                let t = if span > 0.0 { (target - previous.1) / span } else { 0.0 };
+                return previous.0 + t * (mean - previous.0);
+            }
+            previous = (mean, center);
+            cumulative += weight;
+        }
+
+        let span = total - previous.1;
+        let t = if span > 0.0 { (target - previous.1) / span } else { 1.0 };
+        previous.0 + t.min(1.0) * (max - previous.0)
+    }
+}
+
+/// Compute a score distribution in bounded memory from a stream of scores.
+///
+/// Unlike the exact distribution in [`RunStatistics`], which sorts every
+/// score, this makes one pass and keeps only a t-digest of roughly 100
+/// centroids plus a small buffer, so it suits runs with billions of entries.
+/// `min`, `max`, `mean` and `std_dev` are exact (up to floating-point
+/// summation error); `median` and `percentiles` are approximate.
+///
+/// # Approximation error
+///
+/// The error is in rank, not value: an estimated p-th percentile is the true
+/// q-th percentile for some q near p. With the compression used here, |q - p|
+/// is typically well under 0.01 near the median and much smaller in the tails
+/// (p99 is usually accurate to about 0.001), because the digest keeps tail
+/// centroids small. The value error therefore depends on how dense the scores
+/// are around the percentile.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::dataset::streaming_score_distribution;
+///
+/// let dist = streaming_score_distribution((0..10_000).map(|i| i as f32 / 10_000.0));
+/// assert_eq!(dist.min, 0.0);
+/// assert!((dist.median - 0.5).abs() < 0.01);
+/// ```
+pub fn streaming_score_distribution(scores: impl Iterator<Item = f32>) -> ScoreDistribution {
+    let mut digest = TDigest::new();
+    let mut count = 0usize;
+    let mut mean = 0.0;
+    let mut sum_sq_diff = 0.0; // Welford's running sum of squared deviations
+    let mut min = f32::INFINITY;
+    let mut max = f32::NEG_INFINITY;
+
+    for score in scores {
+        count += 1;
+        let value = score as f64;
+        let delta = value - mean;
+        mean += delta / count as f64;
+        sum_sq_diff += delta * (value - mean);
+        min = min.min(score);
+        max = max.max(score);
+        digest.add(value);
+    }
+
+    if count == 0 {
+        return compute_score_distribution(&[]);
+    }
+    digest.compress();
+
+    let quantile = |q: f64| digest.quantile(q, min as f64, max as f64);
+    ScoreDistribution {
+        min,
+        max,
+        mean,
+        median: quantile(0.5),
+        std_dev: (sum_sq_diff / count as f64).sqrt(),
+        percentiles: Percentiles {
+            p25: quantile(0.25) as f32,
+            p50: quantile(0.50) as f32,
+            p75: quantile(0.75) as f32,
+            p90: quantile(0.90) as f32,
+            p95: quantile(0.95) as f32,
+            p99: quantile(0.99) as f32,
+        },
+    }
+}
+
 /// Print comprehensive statistics report.
 pub fn print_statistics_report(stats: &ComprehensiveStats) {
     println!("\n╔════════════════════════════════════════════════════════════════╗");
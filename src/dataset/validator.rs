@@ -4,11 +4,15 @@
 //! and provides detailed validation reports.
 
 use crate::trec::{load_qrels, load_trec_runs, TrecRun};
+use crate::validation::{check_rank_sequences, RankIssue, RankIssueKind};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
+/// Largest score magnitude accepted without a warning.
+const MAX_SANE_SCORE: f32 = 1e6;
+
 /// Comprehensive validation result.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DatasetValidationResult {
@@ -36,10 +40,31 @@ pub struct ValidationStatistics {
     pub documents_in_both: usize,
 }
 
+/// Options for [`validate_dataset_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct ValidationOptions {
+    /// Treat duplicate (query, doc) qrels with *different* grades as errors
+    /// instead of warnings. Identical duplicates are only ever warnings.
+    pub strict: bool,
+}
+
 /// Validate a complete dataset (runs + qrels).
 pub fn validate_dataset(
     runs_path: impl AsRef<Path>,
     qrels_path: impl AsRef<Path>,
+) -> Result<DatasetValidationResult> {
+    validate_dataset_with_options(runs_path, qrels_path, &ValidationOptions::default())
+}
+
+/// Validate a complete dataset (runs + qrels) with validation options.
+///
+/// Identical to [`validate_dataset`] unless `options.strict` is set, in which
+/// case conflicting qrels grades make the dataset invalid. Conflicts matter
+/// because only one of the grades survives grouping, silently.
+pub fn validate_dataset_with_options(
+    runs_path: impl AsRef<Path>,
+    qrels_path: impl AsRef<Path>,
+    options: &ValidationOptions,
 ) -> Result<DatasetValidationResult> {
     let mut errors = Vec::new();
     let mut warnings = Vec::new();
@@ -130,19 +155,34 @@ pub fn validate_dataset(
         }
     }
 
-    // Check for duplicate query-doc pairs in qrels
-    let mut seen_qrels: HashSet<(String, String)> = HashSet::new();
+    // Check for duplicate query-doc pairs in qrels; differing grades conflict
+    let mut seen_qrels: HashMap<(&str, &str), u32> = HashMap::new();
     for qrel in &qrels {
-        let key = (qrel.query_id.clone(), qrel.doc_id.clone());
-        if !seen_qrels.insert(key.clone()) {
-            warnings.push(format!(
+        let key = (qrel.query_id.as_str(), qrel.doc_id.as_str());
+        match seen_qrels.insert(key, qrel.relevance) {
+            Some(previous) if previous != qrel.relevance => {
+                let message = format!(
+                    "Conflicting qrels grades: query={}, doc={}, grades {} and {}",
+                    qrel.query_id, qrel.doc_id, previous, qrel.relevance
+                );
+                if options.strict {
+                    errors.push(message);
+                } else {
+                    warnings.push(message);
+                }
+            }
+            Some(_) => warnings.push(format!(
                 "Duplicate qrel entry: query={}, doc={}",
                 qrel.query_id, qrel.doc_id
-            ));
+            )),
+            None => {}
         }
     }
 
-    // Check rank ordering within queries
+    // Check rank sequences within each (query, tag) group
+    warnings.extend(check_rank_sequences(&runs).iter().map(rank_issue_message));
+
+    // Check scores within each (query, tag) group
     let mut by_query_run: HashMap<String, Vec<&TrecRun>> = HashMap::new();
     for run in &runs {
         by_query_run
@@ -162,16 +202,23 @@ pub fn validate_dataset(
         }
 
         for (tag, tag_runs) in &by_tag {
-            let mut sorted = tag_runs.clone();
-            sorted.sort_by_key(|r| r.rank);
-
-            for (expected_rank, run) in sorted.iter().enumerate() {
-                if run.rank != expected_rank + 1 {
-                    warnings.push(format!(
-                        "Query {} (tag {}): rank {} not sequential (expected {})",
-                        query_id, tag, run.rank, expected_rank + 1
-                    ));
-                }
+            // Constant scores make score-based ranking arbitrary
+            if tag_runs.len() > 1 && tag_runs.iter().all(|r| r.score == tag_runs[0].score) {
+                warnings.push(format!(
+                    "Query {} (tag {}): all {} scores are constant ({}); score order is arbitrary",
+                    query_id, tag, tag_runs.len(), tag_runs[0].score
+                ));
+            }
+
+            let out_of_range = tag_runs
+                .iter()
+                .filter(|r| r.score.abs() > MAX_SANE_SCORE)
+                .count();
+            if out_of_range > 0 {
+                warnings.push(format!(
+                    "Query {} (tag {}): {} scores outside [-{:e}, {:e}]",
+                    query_id, tag, out_of_range, MAX_SANE_SCORE, MAX_SANE_SCORE
+                ));
             }
         }
     }
@@ -204,6 +251,25 @@ pub fn validate_dataset(
     })
 }
 
+/// Warning text for one issue found by [`check_rank_sequences`].
+fn rank_issue_message(issue: &RankIssue) -> String {
+    let detail = match issue.kind {
+        // TREC ranks are 1-based
+        RankIssueKind::NotStartingAtOne { first: 0 } => {
+            "entries with rank 0 (ranks are 1-based)".to_string()
+        }
+        RankIssueKind::NotStartingAtOne { first } => {
+            format!("ranks start at {} instead of 1", first)
+        }
+        RankIssueKind::Gap { after, next } => format!("ranks skip from {} to {}", after, next),
+        RankIssueKind::Duplicate { rank, count } => {
+            format!("duplicate rank {} shared by {} documents", rank, count)
+        }
+        RankIssueKind::OutOfOrder { .. } => "entries are not listed in rank order".to_string(),
+    };
+    format!("Query {} (tag {}): {}", issue.query_id, issue.run_tag, detail)
+}
+
 /// Print validation report to stdout.
 pub fn print_validation_report(result: &DatasetValidationResult) {
     println!("\n╔════════════════════════════════════════════════════════════════╗");
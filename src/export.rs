@@ -1,11 +1,82 @@
 //! Export utilities for evaluation results (CSV, JSON).
 
-use crate::batch::BatchResults;
+use crate::batch::{BatchResults, QueryResults};
+use crate::statistics::{paired_t_test, percentile};
+use anyhow::{Context, Result};
 use std::collections::HashMap;
-use std::io::Write;
+use std::io::{BufRead, BufReader, Read, Write};
+
+/// Prefix of the summary row labels written after the blank line in CSV
+/// exports, so they cannot be mistaken for query IDs.
+const SUMMARY_MARKER: &str = "#";
+
+/// Label of the CSV summary row holding the aggregated means.
+const MEAN_ROW: &str = "mean";
+
+/// Labels of the summary rows written after the mean row in CSV exports.
+const SUMMARY_ROWS: [&str; 3] = ["median", "p25", "p75"];
+
+/// Label of the CSV summary row holding each aggregate's effective query count.
+const QUERY_COUNT_ROW: &str = "n_queries";
+
+/// Header of the optional query text column written by [`export_to_csv_with_topics`].
+const QUERY_TEXT_COLUMN: &str = "query_text";
+
+/// Distribution summary of one metric's per-query values.
+///
+/// For skewed metric distributions the median and interquartile range are
+/// more informative than the mean.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MetricSummary {
+    pub median: f64,
+    pub p25: f64,
+    pub p75: f64,
+}
+
+/// Compute the median and quartiles of each aggregated metric's per-query values.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::batch::evaluate_batch_binary;
+/// use rank_eval::export::summarize_metrics;
+///
+/// let rankings = vec![vec!["doc1"], vec!["doc2", "doc1"], vec!["doc3"]];
+/// let qrels = vec![["doc1"].into_iter().collect::<HashSet<_>>(); 3];
+/// let results = evaluate_batch_binary(&rankings, &qrels, &["mrr"]);
+///
+/// let summary = summarize_metrics(&results);
+/// assert_eq!(summary["mrr"].median, 0.5);
+/// ```
+pub fn summarize_metrics(results: &BatchResults) -> HashMap<String, MetricSummary> {
+    results
+        .aggregated
+        .keys()
+        .map(|name| {
+            let values: Vec<f64> = results
+                .query_results
+                .iter()
+                .filter_map(|qr| qr.metrics.get(name).copied())
+                .collect();
+            let summary = MetricSummary {
+                median: percentile(&values, 0.5),
+                p25: percentile(&values, 0.25),
+                p75: percentile(&values, 0.75),
+            };
+            (name.clone(), summary)
+        })
+        .collect()
+}
 
 /// Export batch results to CSV format.
 ///
+/// Per-query rows are followed by a blank line and summary rows: `#mean`, then
+/// `#median`, `#p25`, and `#p75` of the per-query values (see
+/// [`summarize_metrics`]), and `#n_queries`, the number of queries behind each
+/// mean. Query IDs containing commas or quotes are quoted.
+///
 /// # Arguments
 ///
 /// * `results` - Batch evaluation results
@@ -26,6 +97,46 @@ use std::io::Write;
 /// export_to_csv(&results, &mut csv).unwrap();
 /// ```
 pub fn export_to_csv<W: Write>(results: &BatchResults, writer: &mut W) -> std::io::Result<()> {
+    write_csv(results, None, writer)
+}
+
+/// Export batch results to CSV format with a `query_text` column.
+///
+/// Identical to [`export_to_csv`] except that a quoted `query_text` column
+/// follows `query_id`, filled from `topics` (e.g. loaded with
+/// `dataset::load_topics`). Queries without a topic and the summary rows get
+/// an empty text. [`import_from_csv`] skips the column.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::{HashMap, HashSet};
+/// use rank_eval::batch::evaluate_batch_binary;
+/// use rank_eval::export::export_to_csv_with_topics;
+///
+/// let rankings = vec![vec!["doc1", "doc2"]];
+/// let qrels = vec![["doc1"].into_iter().collect::<HashSet<_>>()];
+/// let results = evaluate_batch_binary(&rankings, &qrels, &["mrr"]);
+/// let topics: HashMap<_, _> = [("query_0".to_string(), "what is bm25".to_string())].into_iter().collect();
+///
+/// let mut csv = Vec::new();
+/// export_to_csv_with_topics(&results, &topics, &mut csv).unwrap();
+/// assert!(String::from_utf8(csv).unwrap().contains("query_0,\"what is bm25\",1.000000"));
+/// ```
+pub fn export_to_csv_with_topics<W: Write>(
+    results: &BatchResults,
+    topics: &HashMap<String, String>,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    write_csv(results, Some(topics), writer)
+}
+
+/// Shared CSV writer; `topics` adds the quoted `query_text` column.
+fn write_csv<W: Write>(
+    results: &BatchResults,
+    topics: Option<&HashMap<String, String>>,
+    writer: &mut W,
+) -> std::io::Result<()> {
     if results.query_results.is_empty() {
         return Ok(());
     }
@@ -33,8 +144,28 @@ pub fn export_to_csv<W: Write>(results: &BatchResults, writer: &mut W) -> std::i
     // Get all metric names from first query
     let metric_names: Vec<&String> = results.query_results[0].metrics.keys().collect();
 
+    // Summary row label, marked so it cannot collide with a query ID
+    let write_label = |writer: &mut W, label: &str| -> std::io::Result<()> {
+        write!(writer, "{}{}", SUMMARY_MARKER, label)?;
+        if topics.is_some() {
+            write!(writer, ",\"\"")?;
+        }
+        Ok(())
+    };
+
+    // Quoted CSV field with embedded quotes doubled
+    let text_field = |query_id: &str| {
+        let text = topics
+            .and_then(|topics| topics.get(query_id))
+            .map_or("", String::as_str);
+        format!(",\"{}\"", text.replace('"', "\"\""))
+    };
+
     // Write header
     write!(writer, "query_id")?;
+    if topics.is_some() {
+        write!(writer, ",{}", QUERY_TEXT_COLUMN)?;
+    }
     for metric_name in &metric_names {
         write!(writer, ",{}", metric_name)?;
     }
@@ -42,7 +173,10 @@ pub fn export_to_csv<W: Write>(results: &BatchResults, writer: &mut W) -> std::i
 
     // Write per-query results
     for query_result in &results.query_results {
-        write!(writer, "{}", query_result.query_id)?;
+        write!(writer, "{}", csv_field(&query_result.query_id))?;
+        if topics.is_some() {
+            write!(writer, "{}", text_field(&query_result.query_id))?;
+        }
         for metric_name in &metric_names {
             let value = query_result.metrics.get(*metric_name).unwrap_or(&0.0);
             write!(writer, ",{:.6}", value)?;
@@ -52,18 +186,433 @@ pub fn export_to_csv<W: Write>(results: &BatchResults, writer: &mut W) -> std::i
 
     // Write aggregated means
     writeln!(writer)?;
-    write!(writer, "mean")?;
+    write_label(writer, MEAN_ROW)?;
     for metric_name in &metric_names {
         let value = results.aggregated.get(*metric_name).unwrap_or(&0.0);
         write!(writer, ",{:.6}", value)?;
     }
     writeln!(writer)?;
 
+    // Write median and quartiles
+    let summary = summarize_metrics(results);
+    for label in SUMMARY_ROWS {
+        write_label(writer, label)?;
+        for metric_name in &metric_names {
+            let value = summary.get(*metric_name).map_or(0.0, |s| match label {
+                "median" => s.median,
+                "p25" => s.p25,
+                _ => s.p75,
+            });
+            write!(writer, ",{:.6}", value)?;
+        }
+        writeln!(writer)?;
+    }
+
+    // Write the number of queries behind each mean
+    write_label(writer, QUERY_COUNT_ROW)?;
+    for metric_name in &metric_names {
+        let count = results.effective_query_count.get(*metric_name).unwrap_or(&0);
+        write!(writer, ",{}", count)?;
+    }
+    writeln!(writer)?;
+
     Ok(())
 }
 
+/// A CSV field holding `value`, quoted (with quotes doubled) if it contains a
+/// comma or quote or has surrounding whitespace.
+fn csv_field(value: &str) -> std::borrow::Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) || value.trim() != value {
+        format!("\"{}\"", value.replace('"', "\"\"")).into()
+    } else {
+        value.into()
+    }
+}
+
+/// Import batch results from the CSV format produced by [`export_to_csv`].
+///
+/// Rows before the first blank line are per-query rows and become
+/// `QueryResults`, whatever their query ID. Rows after it are summary rows:
+/// `#mean` (if present) becomes the aggregate and `#n_queries` its effective
+/// query count, and `#median`/`#p25`/`#p75` are skipped (they are derived
+/// from the per-query values). Summary labels without the `#` marker are also
+/// accepted. A `query_text` column, as written by
+/// [`export_to_csv_with_topics`], is skipped. If the mean row is missing,
+/// aggregates are recomputed as the mean of the per-query values. Values are
+/// read back at the precision they were written with (6 decimal places).
+///
+/// # Arguments
+///
+/// * `reader` - Reader containing the CSV
+///
+/// # Returns
+///
+/// Reconstructed `BatchResults`, or an error if a row is malformed.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::batch::evaluate_batch_binary;
+/// use rank_eval::export::{export_to_csv, import_from_csv};
+///
+/// let rankings = vec![vec!["doc1", "doc2"]];
+/// let qrels = vec![["doc1"].into_iter().collect::<HashSet<_>>()];
+/// let results = evaluate_batch_binary(&rankings, &qrels, &["ndcg@10"]);
+///
+/// let mut csv = Vec::new();
+/// export_to_csv(&results, &mut csv).unwrap();
+///
+/// let imported = import_from_csv(csv.as_slice()).unwrap();
+/// assert_eq!(imported.query_results.len(), 1);
+/// ```
+pub fn import_from_csv<R: Read>(reader: R) -> Result<BatchResults> {
+    let reader = BufReader::new(reader);
+    let mut metric_names: Vec<String> = Vec::new();
+    let mut first_metric_column = 1;
+    let mut query_results = Vec::new();
+    let mut aggregated: Option<HashMap<String, f64>> = None;
+    let mut effective_query_count: Option<HashMap<String, usize>> = None;
+    let mut in_summary = false;
+
+    for (line_num, line) in reader.lines().enumerate() {
+        let line = line.context("Failed to read line")?;
+        let line = line.trim();
+        if line.is_empty() {
+            // The blank line after the header's query rows starts the summary
+            in_summary |= !metric_names.is_empty();
+            continue;
+        }
+
+        let fields = split_csv_fields(line);
+        if metric_names.is_empty() {
+            if fields[0] != "query_id" {
+                return Err(anyhow::anyhow!(
+                    "Line {}: Expected header starting with 'query_id', found '{}'",
+                    line_num + 1,
+                    fields[0]
+                ));
+            }
+            if fields.get(1).map(String::as_str) == Some(QUERY_TEXT_COLUMN) {
+                first_metric_column = 2;
+            }
+            metric_names = fields[first_metric_column..].to_vec();
+            continue;
+        }
+
+        if fields.len() != metric_names.len() + first_metric_column {
+            return Err(anyhow::anyhow!(
+                "Line {}: Expected {} fields, found {}\nLine: {}",
+                line_num + 1,
+                metric_names.len() + first_metric_column,
+                fields.len(),
+                line
+            ));
+        }
+
+        let mut metrics = HashMap::new();
+        for (name, value) in metric_names.iter().zip(&fields[first_metric_column..]) {
+            let value: f64 = value.parse().with_context(|| {
+                format!("Invalid value for {} on line {}: {}", name, line_num + 1, value)
+            })?;
+            metrics.insert(name.clone(), value);
+        }
+
+        if !in_summary {
+            query_results.push(QueryResults {
+                query_id: fields[0].clone(),
+                metrics,
+            });
+            continue;
+        }
+
+        let label = fields[0].strip_prefix(SUMMARY_MARKER).unwrap_or(&fields[0]);
+        if label == MEAN_ROW {
+            aggregated = Some(metrics);
+        } else if label == QUERY_COUNT_ROW {
+            let counts = metrics.into_iter().map(|(name, n)| (name, n as usize)).collect();
+            effective_query_count = Some(counts);
+        } else if !SUMMARY_ROWS.contains(&label) {
+            return Err(anyhow::anyhow!(
+                "Line {}: Unknown summary row '{}'",
+                line_num + 1,
+                fields[0]
+            ));
+        }
+    }
+
+    let aggregated = aggregated.unwrap_or_else(|| {
+        let n = query_results.len().max(1) as f64;
+        metric_names
+            .iter()
+            .map(|name| {
+                let sum: f64 = query_results
+                    .iter()
+                    .filter_map(|qr| qr.metrics.get(name))
+                    .sum();
+                (name.clone(), sum / n)
+            })
+            .collect()
+    });
+
+    let effective_query_count = effective_query_count.unwrap_or_else(|| {
+        metric_names
+            .iter()
+            .map(|name| {
+                let n = query_results.iter().filter(|qr| qr.metrics.contains_key(name)).count();
+                (name.clone(), n)
+            })
+            .collect()
+    });
+
+    Ok(BatchResults {
+        query_results,
+        aggregated,
+        effective_query_count,
+        skipped: Vec::new(),
+    })
+}
+
+/// Split a CSV line into fields, unquoting `"..."` fields (`""` is a literal quote).
+fn split_csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+
+    fields
+}
+
+/// Export a per-query comparison of two systems on one metric to CSV format.
+///
+/// Queries are paired by `query_id` (queries missing from either system are
+/// omitted) and written as `query_id,a_value,b_value,delta` rows with
+/// `delta = a - b`, sorted by delta (largest improvement first). After a blank
+/// line, a `mean` row gives the mean of each column and a `p_value` row gives
+/// the two-sided paired t-test p-value (`alpha = 0.05`). Query IDs containing
+/// commas or quotes are quoted, as in [`export_to_csv`].
+///
+/// # Arguments
+///
+/// * `a` - Batch results for system A
+/// * `b` - Batch results for system B
+/// * `metric` - Metric name to compare
+/// * `writer` - Writer to write CSV to
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::batch::evaluate_batch_binary;
+/// use rank_eval::export::export_comparison_csv;
+///
+/// let qrels = vec![["doc1"].into_iter().collect::<HashSet<_>>()];
+/// let a = evaluate_batch_binary(&[vec!["doc1", "doc2"]], &qrels, &["mrr"]);
+/// let b = evaluate_batch_binary(&[vec!["doc2", "doc1"]], &qrels, &["mrr"]);
+///
+/// let mut csv = Vec::new();
+/// export_comparison_csv(&a, &b, "mrr", &mut csv).unwrap();
+/// ```
+pub fn export_comparison_csv<W: Write>(
+    a: &BatchResults,
+    b: &BatchResults,
+    metric: &str,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    let b_values: HashMap<&str, f64> = b
+        .query_results
+        .iter()
+        .filter_map(|qr| qr.metrics.get(metric).map(|v| (qr.query_id.as_str(), *v)))
+        .collect();
+
+    let mut rows: Vec<(&str, f64, f64)> = a
+        .query_results
+        .iter()
+        .filter_map(|qr| {
+            let a_value = *qr.metrics.get(metric)?;
+            let b_value = *b_values.get(qr.query_id.as_str())?;
+            Some((qr.query_id.as_str(), a_value, b_value))
+        })
+        .collect();
+    rows.sort_by(|x, y| (y.1 - y.2).total_cmp(&(x.1 - x.2)).then_with(|| x.0.cmp(y.0)));
+
+    writeln!(writer, "query_id,a_value,b_value,delta")?;
+    for (query_id, a_value, b_value) in &rows {
+        writeln!(
+            writer,
+            "{},{:.6},{:.6},{:.6}",
+            csv_field(query_id),
+            a_value,
+            b_value,
+            a_value - b_value
+        )?;
+    }
+
+    let a_scores: Vec<f64> = rows.iter().map(|r| r.1).collect();
+    let b_scores: Vec<f64> = rows.iter().map(|r| r.2).collect();
+    let n = rows.len().max(1) as f64;
+    let a_mean = a_scores.iter().sum::<f64>() / n;
+    let b_mean = b_scores.iter().sum::<f64>() / n;
+    let test = paired_t_test(&a_scores, &b_scores, 0.05);
+
+    writeln!(writer)?;
+    writeln!(
+        writer,
+        "mean,{:.6},{:.6},{:.6}",
+        a_mean,
+        b_mean,
+        a_mean - b_mean
+    )?;
+    writeln!(writer, "p_value,{:.6}", test.p_value)?;
+
+    Ok(())
+}
+
+/// Export per-system results to one CSV with a section per run tag.
+///
+/// Systems are written in tag order. Each section starts with a
+/// `# tag: <name>` line followed by that system's table in the
+/// [`export_to_csv`] format; sections are separated by a blank line.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::evaluate_all_systems;
+/// use rank_eval::export::export_by_tag_csv;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new().add("1", "doc1", 0.9, "bm25").add("1", "doc2", 0.9, "dense").build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).build();
+///
+/// let mut csv = Vec::new();
+/// export_by_tag_csv(&evaluate_all_systems(&runs, &qrels, &["mrr"]), &mut csv).unwrap();
+/// assert!(String::from_utf8(csv).unwrap().starts_with("# tag: bm25\n"));
+/// ```
+pub fn export_by_tag_csv<W: Write>(
+    results: &HashMap<String, BatchResults>,
+    writer: &mut W,
+) -> std::io::Result<()> {
+    let mut tags: Vec<&String> = results.keys().collect();
+    tags.sort();
+
+    for (i, tag) in tags.into_iter().enumerate() {
+        if i > 0 {
+            writeln!(writer)?;
+        }
+        writeln!(writer, "# tag: {}", tag)?;
+        write_csv(&results[tag], None, writer)?;
+    }
+
+    Ok(())
+}
+
+/// Rank systems under each metric for a leaderboard.
+///
+/// Returns system -> metric -> rank, where rank 1 is the highest aggregated
+/// mean. Tied systems share the minimum rank ("1, 1, 3" competition ranking).
+/// A system without an aggregate for a metric is left unranked for it.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::evaluate_all_systems;
+/// use rank_eval::export::leaderboard;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new().add("1", "doc1", 0.9, "bm25").add("1", "doc2", 0.9, "dense").build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).build();
+///
+/// let board = leaderboard(&evaluate_all_systems(&runs, &qrels, &["mrr"]), &["mrr"]);
+/// assert_eq!(board["bm25"]["mrr"], 1);
+/// assert_eq!(board["dense"]["mrr"], 2);
+/// ```
+pub fn leaderboard(
+    results: &HashMap<String, BatchResults>,
+    metrics: &[&str],
+) -> HashMap<String, HashMap<String, usize>> {
+    let mut board: HashMap<String, HashMap<String, usize>> = HashMap::new();
+
+    for metric in metrics {
+        let means: Vec<(&String, f64)> = results
+            .iter()
+            .filter_map(|(system, r)| Some((system, *r.aggregated.get(*metric)?)))
+            .collect();
+
+        for (system, mean) in &means {
+            let better = means.iter().filter(|(_, other)| other > mean).count();
+            board
+                .entry((*system).clone())
+                .or_default()
+                .insert(metric.to_string(), better + 1);
+        }
+    }
+
+    board
+}
+
+/// Render a GitHub-flavored Markdown table of aggregated results.
+///
+/// One row per system (sorted by name) and one column per metric, in the
+/// given order. Values have 4 decimals; the best value in each column is
+/// bolded, including every system tied for best. A missing aggregate renders
+/// as `-`.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::batch::evaluate_all_systems;
+/// use rank_eval::export::export_markdown;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new().add("1", "doc1", 0.9, "bm25").add("1", "doc2", 0.9, "dense").build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).build();
+///
+/// let table = export_markdown(&evaluate_all_systems(&runs, &qrels, &["mrr"]), &["mrr"]);
+/// assert_eq!(table, "| system | mrr |\n|---|---:|\n| bm25 | **1.0000** |\n| dense | 0.0000 |\n");
+/// ```
+pub fn export_markdown(results: &HashMap<String, BatchResults>, metrics: &[&str]) -> String {
+    let board = leaderboard(results, metrics);
+    let mut systems: Vec<&String> = results.keys().collect();
+    systems.sort();
+
+    let mut table = format!("| system | {} |\n", metrics.join(" | "));
+    table.push_str(&format!("|---|{}\n", "---:|".repeat(metrics.len())));
+
+    for system in systems {
+        table.push_str(&format!("| {} |", system));
+        for metric in metrics {
+            let cell = match results[system].aggregated.get(*metric) {
+                Some(value) if board[system][*metric] == 1 => format!("**{:.4}**", value),
+                Some(value) => format!("{:.4}", value),
+                None => "-".to_string(),
+            };
+            table.push_str(&format!(" {} |", cell));
+        }
+        table.push('\n');
+    }
+
+    table
+}
+
 /// Export batch results to JSON format.
 ///
+/// The JSON object holds `query_results` and `aggregated` (the serialized
+/// [`BatchResults`]) plus a `summary` object mapping each metric to its
+/// median and quartiles (see [`summarize_metrics`]).
+///
 /// # Arguments
 ///
 /// * `results` - Batch evaluation results
@@ -88,30 +637,38 @@ pub fn export_to_csv<W: Write>(results: &BatchResults, writer: &mut W) -> std::i
 #[cfg(feature = "serde")]
 pub fn export_to_json(results: &BatchResults) -> Result<String, serde_json::Error> {
     #[derive(serde::Serialize)]
-    struct ExportableResults {
-        query_results: Vec<QueryResultsExport>,
-        aggregated: HashMap<String, f64>,
-    }
-
-    #[derive(serde::Serialize)]
-    struct QueryResultsExport {
-        query_id: String,
-        metrics: HashMap<String, f64>,
+    struct JsonExport<'a> {
+        #[serde(flatten)]
+        results: &'a BatchResults,
+        summary: HashMap<String, MetricSummary>,
     }
 
-    let exportable = ExportableResults {
-        query_results: results
-            .query_results
-            .iter()
-            .map(|qr| QueryResultsExport {
-                query_id: qr.query_id.clone(),
-                metrics: qr.metrics.clone(),
-            })
-            .collect(),
-        aggregated: results.aggregated.clone(),
-    };
+    serde_json::to_string_pretty(&JsonExport {
+        results,
+        summary: summarize_metrics(results),
+    })
+}
 
-    serde_json::to_string_pretty(&exportable)
+/// Import batch results from the JSON format produced by [`export_to_json`].
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::batch::evaluate_batch_binary;
+/// use rank_eval::export::{export_to_json, import_from_json};
+///
+/// let rankings = vec![vec!["doc1", "doc2"]];
+/// let qrels = vec![["doc1"].into_iter().collect::<HashSet<_>>()];
+/// let results = evaluate_batch_binary(&rankings, &qrels, &["ndcg@10"]);
+///
+/// let json = export_to_json(&results).unwrap();
+/// let imported = import_from_json(&json).unwrap();
+/// assert_eq!(imported.aggregated, results.aggregated);
+/// ```
+#[cfg(feature = "serde")]
+pub fn import_from_json(json: &str) -> Result<BatchResults, serde_json::Error> {
+    serde_json::from_str(json)
 }
 
 #[cfg(test)]
@@ -142,6 +699,101 @@ mod tests {
         assert!(csv_str.contains("mean"));
     }
 
+    #[test]
+    fn test_import_from_csv_round_trip() {
+        let rankings = vec![
+            vec!["doc1", "doc2", "doc3"],
+            vec!["doc4", "doc5", "doc6"],
+        ];
+        let qrels = vec![
+            ["doc1", "doc3"].into_iter().collect::<HashSet<_>>(),
+            ["doc5"].into_iter().collect::<HashSet<_>>(),
+        ];
+        let results = evaluate_batch_binary(&rankings, &qrels, &["ndcg@10", "mrr"]);
+
+        let mut csv = Vec::new();
+        export_to_csv(&results, &mut csv).unwrap();
+        let imported = import_from_csv(csv.as_slice()).unwrap();
+
+        assert_eq!(imported.query_results.len(), results.query_results.len());
+        for (original, restored) in results.query_results.iter().zip(&imported.query_results) {
+            assert_eq!(original.query_id, restored.query_id);
+            assert_eq!(original.metrics.len(), restored.metrics.len());
+            for (name, value) in &original.metrics {
+                assert!((value - restored.metrics[name]).abs() < 1e-6);
+            }
+        }
+        for (name, value) in &results.aggregated {
+            assert!((value - imported.aggregated[name]).abs() < 1e-6);
+        }
+        assert_eq!(imported.effective_query_count, results.effective_query_count);
+
+        assert!(import_from_csv("query_id,mrr\nq1,abc\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_csv_round_trip_with_reserved_query_ids() {
+        let qrels = vec![["doc1"].into_iter().collect::<HashSet<_>>(); 4];
+        let mut results = evaluate_batch_binary(
+            &[vec!["doc1"], vec!["doc2", "doc1"], vec!["doc2"], vec!["doc1"]],
+            &qrels,
+            &["mrr"],
+        );
+        let ids = ["mean", "p25", "n_queries", "a,\"b\""];
+        for (query, id) in results.query_results.iter_mut().zip(ids) {
+            query.query_id = id.to_string();
+        }
+
+        let mut csv = Vec::new();
+        export_to_csv(&results, &mut csv).unwrap();
+        let imported = import_from_csv(csv.as_slice()).unwrap();
+
+        assert_eq!(imported.query_results, results.query_results);
+        assert!((imported.aggregated["mrr"] - 0.625).abs() < 1e-6);
+        assert_eq!(imported.effective_query_count["mrr"], 4);
+    }
+
+    #[test]
+    fn test_export_comparison_csv() {
+        let qrels = vec![
+            ["doc1"].into_iter().collect::<HashSet<_>>(),
+            ["doc2"].into_iter().collect::<HashSet<_>>(),
+            ["doc3"].into_iter().collect::<HashSet<_>>(),
+        ];
+        let a = evaluate_batch_binary(
+            &[vec!["doc1"], vec!["doc2"], vec!["doc9", "doc3"]],
+            &qrels,
+            &["mrr"],
+        );
+        let b = evaluate_batch_binary(
+            &[vec!["doc9", "doc1"], vec!["doc2"], vec!["doc3"]],
+            &qrels,
+            &["mrr"],
+        );
+
+        let mut csv = Vec::new();
+        export_comparison_csv(&a, &b, "mrr", &mut csv).unwrap();
+        let csv_str = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv_str.lines().collect();
+
+        assert_eq!(lines[0], "query_id,a_value,b_value,delta");
+        assert_eq!(lines[1], "query_0,1.000000,0.500000,0.500000");
+        assert_eq!(lines[3], "query_2,0.500000,1.000000,-0.500000");
+
+        let expected_p = paired_t_test(&[1.0, 1.0, 0.5], &[0.5, 1.0, 1.0], 0.05).p_value;
+        assert_eq!(lines[5], "mean,0.833333,0.833333,0.000000");
+        assert_eq!(lines[6], format!("p_value,{:.6}", expected_p));
+
+        // Query IDs with commas are quoted so the row keeps four fields
+        let (mut a, mut b) = (a, b);
+        a.query_results[0].query_id = "q,1".to_string();
+        b.query_results[0].query_id = "q,1".to_string();
+        let mut csv = Vec::new();
+        export_comparison_csv(&a, &b, "mrr", &mut csv).unwrap();
+        let csv_str = String::from_utf8(csv).unwrap();
+        assert!(csv_str.contains("\"q,1\",1.000000,0.500000,0.500000"));
+    }
+
     #[cfg(feature = "serde")]
     #[test]
     fn test_export_to_json() {
@@ -153,6 +805,116 @@ mod tests {
         assert!(json.contains("query_results"));
         assert!(json.contains("aggregated"));
         assert!(json.contains("ndcg@10"));
+
+        let imported = import_from_json(&json).unwrap();
+        assert_eq!(imported.query_results[0].metrics, results.query_results[0].metrics);
+        assert_eq!(imported.aggregated, results.aggregated);
     }
-}
 
+    #[test]
+    fn test_export_median_and_quartiles() {
+        // MRR values: 1.0, 0.5, 0.25, 0.0
+        let rankings = vec![
+            vec!["doc1"],
+            vec!["doc2", "doc1"],
+            vec!["doc2", "doc3", "doc4", "doc1"],
+            vec!["doc2"],
+        ];
+        let qrels = vec![["doc1"].into_iter().collect::<HashSet<_>>(); 4];
+        let results = evaluate_batch_binary(&rankings, &qrels, &["mrr"]);
+
+        let mut csv = Vec::new();
+        export_to_csv(&results, &mut csv).unwrap();
+        let csv_str = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv_str.lines().collect();
+
+        assert_eq!(lines[6], "#mean,0.437500");
+        assert_eq!(lines[7], "#median,0.375000");
+        assert_eq!(lines[8], "#p25,0.187500");
+        assert_eq!(lines[9], "#p75,0.625000");
+        assert_eq!(lines[10], "#n_queries,4");
+
+        // Summary rows are not read back as queries
+        let imported = import_from_csv(csv_str.as_bytes()).unwrap();
+        assert_eq!(imported.query_results.len(), 4);
+
+        #[cfg(feature = "serde")]
+        {
+            let json: serde_json::Value =
+                serde_json::from_str(&export_to_json(&results).unwrap()).unwrap();
+            assert_eq!(json["summary"]["mrr"]["median"], 0.375);
+        }
+    }
+
+    #[test]
+    fn test_export_by_tag_csv() {
+        let qrels = vec![["doc1"].into_iter().collect::<HashSet<_>>()];
+        let mut systems = HashMap::new();
+        systems.insert(
+            "dense".to_string(),
+            evaluate_batch_binary(&[vec!["doc2", "doc1"]], &qrels, &["mrr"]),
+        );
+        systems.insert(
+            "bm25".to_string(),
+            evaluate_batch_binary(&[vec!["doc1", "doc2"]], &qrels, &["mrr"]),
+        );
+
+        let mut output = Vec::new();
+        export_by_tag_csv(&systems, &mut output).unwrap();
+        let csv = String::from_utf8(output).unwrap();
+
+        let bm25 = csv.find("# tag: bm25").unwrap();
+        let dense = csv.find("# tag: dense").unwrap();
+        assert!(bm25 < dense);
+        assert!(csv[bm25..dense].contains("query_0,1.000000"));
+        assert!(csv[dense..].contains("query_0,0.500000"));
+        assert_eq!(csv.matches("query_id,mrr").count(), 2);
+    }
+
+    #[test]
+    fn test_leaderboard_with_ties() {
+        let system = |mrr: f64, recall: f64| BatchResults {
+            query_results: Vec::new(),
+            aggregated: [("mrr".to_string(), mrr), ("recall@10".to_string(), recall)]
+                .into_iter()
+                .collect(),
+            effective_query_count: HashMap::new(),
+            skipped: Vec::new(),
+        };
+        let mut systems = HashMap::new();
+        systems.insert("a".to_string(), system(0.9, 0.5));
+        systems.insert("b".to_string(), system(0.7, 0.8));
+        systems.insert("c".to_string(), system(0.9, 0.2));
+
+        let board = leaderboard(&systems, &["mrr", "recall@10"]);
+        assert_eq!(board["a"]["mrr"], 1);
+        assert_eq!(board["c"]["mrr"], 1);
+        assert_eq!(board["b"]["mrr"], 3);
+        assert_eq!(board["b"]["recall@10"], 1);
+        assert_eq!(board["a"]["recall@10"], 2);
+        assert_eq!(board["c"]["recall@10"], 3);
+    }
+
+    #[test]
+    fn test_export_markdown_bolds_best() {
+        let system = |mrr: f64| BatchResults {
+            query_results: Vec::new(),
+            aggregated: [("mrr".to_string(), mrr)].into_iter().collect(),
+            effective_query_count: HashMap::new(),
+            skipped: Vec::new(),
+        };
+        let mut systems = HashMap::new();
+        systems.insert("b".to_string(), system(0.25));
+        systems.insert("a".to_string(), system(0.5));
+        systems.insert("c".to_string(), system(0.5));
+
+        let table = export_markdown(&systems, &["mrr", "ndcg@10"]);
+        let lines: Vec<&str> = table.lines().collect();
+
+        assert_eq!(lines[0], "| system | mrr | ndcg@10 |");
+        assert_eq!(lines[1], "|---|---:|---:|");
+        assert_eq!(lines[2], "| a | **0.5000** | - |");
+        assert_eq!(lines[3], "| b | 0.2500 | - |");
+        assert_eq!(lines[4], "| c | **0.5000** | - |");
+    }
+}
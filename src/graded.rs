@@ -6,7 +6,8 @@
 //! Unlike binary metrics, these use the actual relevance scores in calculations,
 //! making them more suitable for real-world datasets with graded judgments.
 
-use std::collections::HashMap;
+use crate::trec::{first_tag_run, group_qrels_by_query, group_runs_by_query, Qrel, TrecRun};
+use std::collections::{HashMap, HashSet};
 
 /// Compute nDCG@k for graded relevance.
 ///
@@ -54,19 +55,321 @@ pub fn compute_ndcg(
     qrels: &HashMap<String, u32>,
     k: usize,
 ) -> f64 {
-    let mut dcg = 0.0;
-    let mut ideal_gains: Vec<u32> = qrels.values().copied().filter(|&r| r > 0).collect();
-    ideal_gains.sort_by(|a, b| b.cmp(a));
+    compute_ndcg_with_gain(ranked, qrels, k, &LinearGain)
+}
+
+/// Mapping from a relevance grade to the gain a document contributes.
+///
+/// Implement this to plug a custom gain into the `*_with_gain` graded metrics.
+/// Grade 0 should map to 0 gain.
+pub trait Gain {
+    /// Gain contributed by a document with relevance `grade`.
+    fn gain(&self, grade: u32) -> f64;
+}
+
+/// Linear gain: `gain(g) = g`. The default for [`compute_ndcg`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinearGain;
 
+impl Gain for LinearGain {
+    fn gain(&self, grade: u32) -> f64 {
+        grade as f64
+    }
+}
+
+/// Exponential gain: `gain(g) = 2^g - 1`, emphasizing highly relevant documents.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExponentialGain;
+
+impl Gain for ExponentialGain {
+    fn gain(&self, grade: u32) -> f64 {
+        2.0_f64.powi(grade as i32) - 1.0
+    }
+}
+
+/// Compute nDCG@k for graded relevance with a custom gain.
+///
+/// Formula: `DCG@k = Σᵢ gain(rel(i)) / log₂(i + 2)`, normalized by the DCG of
+/// the judged documents sorted by gain (descending).
+///
+/// With [`LinearGain`] this is exactly [`compute_ndcg`].
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::{compute_ndcg, compute_ndcg_with_gain, ExponentialGain};
+///
+/// let ranked = vec![("doc1".to_string(), 0.9), ("doc2".to_string(), 0.8)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 1);
+/// qrels.insert("doc2".to_string(), 3);
+///
+/// // Exponential gain penalizes burying the grade-3 document more heavily
+/// let exp = compute_ndcg_with_gain(&ranked, &qrels, 2, &ExponentialGain);
+/// assert!(exp < compute_ndcg(&ranked, &qrels, 2));
+/// ```
+pub fn compute_ndcg_with_gain(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+    gain: &dyn Gain,
+) -> f64 {
+    let mut dcg = 0.0;
     for (rank, (doc_id, _)) in ranked.iter().take(k).enumerate() {
         if let Some(&relevance) = qrels.get(doc_id.as_str()) {
             if relevance > 0 {
                 // Use log2(rank + 2) for DCG calculation
+                dcg += gain.gain(relevance) / ((rank + 2) as f64).log2();
+            }
+        }
+    }
+
+    let mut ideal_gains: Vec<f64> = qrels
+        .values()
+        .filter(|&&r| r > 0)
+        .map(|&r| gain.gain(r))
+        .collect();
+    ideal_gains.sort_by(|a, b| b.total_cmp(a));
+
+    let mut idcg = 0.0;
+    for (rank, &g) in ideal_gains.iter().take(k).enumerate() {
+        idcg += g / ((rank + 2) as f64).log2();
+    }
+
+    if idcg > 0.0 {
+        dcg / idcg
+    } else {
+        0.0
+    }
+}
+
+/// Adapts a closure to [`Gain`].
+struct FnGain<F>(F);
+
+impl<F: Fn(u32) -> f64> Gain for FnGain<F> {
+    fn gain(&self, grade: u32) -> f64 {
+        (self.0)(grade)
+    }
+}
+
+/// Compute nDCG@k with gains from an ad-hoc grade mapping.
+///
+/// Like [`compute_ndcg_with_gain`], with `map_grade` deriving the gain of
+/// both the ranked documents and the ideal ranking. Handy for one-off
+/// relevance definitions, e.g. counting only grade-2 documents as relevant.
+/// Unjudged and grade-0 documents contribute no gain regardless of the
+/// mapping.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::compute_ndcg_with_mapper;
+///
+/// let ranked = vec![("doc1".to_string(), 0.9), ("doc2".to_string(), 0.8)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 1);
+/// qrels.insert("doc2".to_string(), 2);
+///
+/// // Relevant only if grade == 2
+/// let strict = compute_ndcg_with_mapper(&ranked, &qrels, 10, |g| if g == 2 { 1.0 } else { 0.0 });
+/// assert!((strict - 1.0 / 3f64.log2()).abs() < 1e-9);
+/// ```
+pub fn compute_ndcg_with_mapper(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+    map_grade: impl Fn(u32) -> f64,
+) -> f64 {
+    compute_ndcg_with_gain(ranked, qrels, k, &FnGain(map_grade))
+}
+
+/// Compute nDCG@k with a floor on the gain of every relevant document.
+///
+/// Formula: `gain(g) = max(g, min_gain)` for `g > 0`, with linear gain
+/// otherwise as in [`compute_ndcg`].
+///
+/// With a floor above 1, grade-1 documents count meaningfully next to highly
+/// relevant ones instead of being nearly ignored. The floor applies to the
+/// ideal ranking too. Non-relevant (grade 0) documents still have no gain, and
+/// `min_gain <= 1.0` gives exactly [`compute_ndcg`].
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::{compute_ndcg, compute_ndcg_with_min_gain};
+///
+/// let ranked = vec![("doc1".to_string(), 0.9), ("doc2".to_string(), 0.8)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 1);
+/// qrels.insert("doc2".to_string(), 4);
+///
+/// // Ranking the grade-1 document first costs less when its gain is floored
+/// let floored = compute_ndcg_with_min_gain(&ranked, &qrels, 10, 2.0);
+/// assert!(floored > compute_ndcg(&ranked, &qrels, 10));
+/// ```
+pub fn compute_ndcg_with_min_gain(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+    min_gain: f64,
+) -> f64 {
+    compute_ndcg_with_mapper(ranked, qrels, k, |grade| (grade as f64).max(min_gain))
+}
+
+/// Compute nDCG@k with each judgment's gain weighted by its confidence.
+///
+/// Formula: `DCG@k = Σᵢ conf(i) × rel(i) / log₂(i + 2)`, normalized by the DCG
+/// of the judged documents sorted by weighted gain (descending).
+///
+/// `qrels_with_conf` maps each document to `(grade, confidence)`, e.g. the
+/// annotator agreement of a crowdsourced label. Low-confidence judgments count
+/// less in both DCG and IDCG; with every confidence 1.0 this is exactly
+/// [`compute_ndcg`]. Negative confidences are treated as 0.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::compute_ndcg_weighted;
+///
+/// let ranked = vec!["doc1", "doc2"];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1", (1, 1.0));
+/// qrels.insert("doc2", (3, 0.5));
+///
+/// let ndcg = compute_ndcg_weighted(&ranked, &qrels, 10);
+/// assert!(ndcg > 0.0 && ndcg < 1.0);
+/// ```
+pub fn compute_ndcg_weighted<I: Eq + std::hash::Hash>(
+    ranked: &[I],
+    qrels_with_conf: &HashMap<I, (u32, f32)>,
+    k: usize,
+) -> f64 {
+    let weighted_gain = |&(grade, conf): &(u32, f32)| grade as f64 * (conf as f64).max(0.0);
+
+    let mut dcg = 0.0;
+    for (rank, doc_id) in ranked.iter().take(k).enumerate() {
+        if let Some(judgment) = qrels_with_conf.get(doc_id) {
+            dcg += weighted_gain(judgment) / ((rank + 2) as f64).log2();
+        }
+    }
+
+    let mut ideal_gains: Vec<f64> = qrels_with_conf.values().map(weighted_gain).collect();
+    ideal_gains.sort_by(|a, b| b.total_cmp(a));
+
+    let mut idcg = 0.0;
+    for (rank, &g) in ideal_gains.iter().take(k).enumerate() {
+        idcg += g / ((rank + 2) as f64).log2();
+    }
+
+    if idcg > 0.0 {
+        dcg / idcg
+    } else {
+        0.0
+    }
+}
+
+/// Compute nDCG@k with each retrieved document's gain scaled by a decay factor.
+///
+/// Formula: `DCG@k = Σᵢ decay(i) × rel(i) / log₂(i + 2)`
+///
+/// `decay` gives a per-document factor, e.g. for recency tasks where stale
+/// documents are worth less; documents without an entry are not decayed
+/// (factor 1.0). The ideal DCG is computed from the *un-decayed* grades, so the
+/// score measures the gain delivered against what fresh relevant documents
+/// would give, and decay can only lower it relative to [`compute_ndcg`].
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::compute_ndcg_decayed;
+///
+/// let ranked = vec!["doc1"];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1", 2);
+/// let mut decay = HashMap::new();
+/// decay.insert("doc1", 0.5);
+///
+/// assert!((compute_ndcg_decayed(&ranked, &qrels, &decay, 10) - 0.5).abs() < 1e-9);
+/// ```
+pub fn compute_ndcg_decayed<I: Eq + std::hash::Hash>(
+    ranked: &[I],
+    qrels: &HashMap<I, u32>,
+    decay: &HashMap<I, f32>,
+    k: usize,
+) -> f64 {
+    let mut dcg = 0.0;
+    for (rank, doc_id) in ranked.iter().take(k).enumerate() {
+        if let Some(&relevance) = qrels.get(doc_id) {
+            let factor = decay.get(doc_id).copied().unwrap_or(1.0) as f64;
+            dcg += factor * relevance as f64 / ((rank + 2) as f64).log2();
+        }
+    }
+
+    let mut ideal_grades: Vec<u32> = qrels.values().copied().filter(|&g| g > 0).collect();
+    ideal_grades.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut idcg = 0.0;
+    for (rank, &grade) in ideal_grades.iter().take(k).enumerate() {
+        idcg += grade as f64 / ((rank + 2) as f64).log2();
+    }
+
+    if idcg > 0.0 {
+        dcg / idcg
+    } else {
+        0.0
+    }
+}
+
+/// Compute nDCG@k normalized by the best ordering of the retrieved documents only.
+///
+/// Identical to [`compute_ndcg`] except that IDCG@k is computed from the
+/// grades of the documents in `ranked` (the whole ranking, not just the top k)
+/// instead of all judged documents. This isolates ranking quality from
+/// retrieval quality: relevant documents the run never retrieved do not lower
+/// the score, so the result is ≥ [`compute_ndcg`].
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::{compute_ndcg, compute_ndcg_retrieved_ideal};
+///
+/// let ranked = vec![("doc1".to_string(), 0.9)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 1);
+/// qrels.insert("missed".to_string(), 3);
+///
+/// assert_eq!(compute_ndcg_retrieved_ideal(&ranked, &qrels, 10), 1.0);
+/// assert!(compute_ndcg(&ranked, &qrels, 10) < 1.0);
+/// ```
+pub fn compute_ndcg_retrieved_ideal(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+) -> f64 {
+    let mut dcg = 0.0;
+    for (rank, (doc_id, _)) in ranked.iter().take(k).enumerate() {
+        if let Some(&relevance) = qrels.get(doc_id.as_str()) {
+            if relevance > 0 {
                 dcg += (relevance as f64) / ((rank + 2) as f64).log2();
             }
         }
     }
 
+    let mut seen = HashSet::new();
+    let mut ideal_gains: Vec<u32> = ranked
+        .iter()
+        .filter(|(doc_id, _)| seen.insert(doc_id.as_str()))
+        .filter_map(|(doc_id, _)| qrels.get(doc_id.as_str()).copied())
+        .filter(|&r| r > 0)
+        .collect();
+    ideal_gains.sort_by(|a, b| b.cmp(a));
+
     let mut idcg = 0.0;
     for (rank, &gain) in ideal_gains.iter().take(k).enumerate() {
         idcg += (gain as f64) / ((rank + 2) as f64).log2();
@@ -79,75 +382,1019 @@ pub fn compute_ndcg(
     }
 }
 
-/// Compute Mean Average Precision (MAP) for graded relevance.
+/// Compute graded ERR@k with a custom gain.
+///
+/// Formula: `ERR@k = Σᵢ (1/i) × Rᵢ × Πⱼ<ᵢ (1 - Rⱼ)`
+///
+/// Where `Rᵢ = gain(rel(i)) / max_gain`, clamped to [0, 1], is the probability
+/// that the user is satisfied at rank i. `max_gain` is fixed by the grading
+/// scale rather than by the query's own judgments, so scores are comparable
+/// across queries: with [`ExponentialGain`] and `max_gain = 2^max_grade` this
+/// is [`err_at_k`]. Returns 0.0 if `max_gain` is not positive.
+///
+/// Reference: Chapelle et al. (2009) "Expected reciprocal rank for graded relevance"
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::{compute_err_with_gain, err_at_k, ExponentialGain};
+///
+/// let ranked = vec![("doc1".to_string(), 0.9), ("doc2".to_string(), 0.8)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc2".to_string(), 2);
+///
+/// // 0-3 scale: R = (2^2 - 1) / 2^3 at rank 2
+/// let err = compute_err_with_gain(&ranked, &qrels, 10, &ExponentialGain, 8.0);
+/// assert!((err - 3.0 / 16.0).abs() < 1e-9);
+/// assert!((err - err_at_k(&ranked, &qrels, 10, 3)).abs() < 1e-12);
+/// ```
+pub fn compute_err_with_gain(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+    gain: &dyn Gain,
+    max_gain: f64,
+) -> f64 {
+    if max_gain <= 0.0 {
+        return 0.0;
+    }
+
+    let grades = ranked
+        .iter()
+        .map(|(doc_id, _)| qrels.get(doc_id.as_str()).copied().unwrap_or(0));
+    err_from_satisfaction(grades, k, |grade| gain.gain(grade) / max_gain)
+}
+
+/// Compute graded RBP@k with a custom gain.
+///
+/// Formula: `RBP = (1-p) × Σᵢ p^(i-1) × gain(rel(i)) / max_gain`
+///
+/// Where `max_gain` is the gain of the top grade on the scale, so every
+/// per-document term lies in [0, 1] (larger values are clamped). Returns 0.0
+/// unless `0 < persistence < 1` and `max_gain` is positive.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::{compute_rbp_with_gain, LinearGain};
+///
+/// let ranked = vec![("doc1".to_string(), 0.9)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 2);
+///
+/// let rbp = compute_rbp_with_gain(&ranked, &qrels, 10, 0.8, &LinearGain, 2.0);
+/// assert!((rbp - 0.2).abs() < 1e-9);
+/// ```
+pub fn compute_rbp_with_gain(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+    persistence: f64,
+    gain: &dyn Gain,
+    max_gain: f64,
+) -> f64 {
+    if persistence <= 0.0 || persistence >= 1.0 || max_gain <= 0.0 {
+        return 0.0;
+    }
+
+    let mut rbp = 0.0;
+    let mut p_power = 1.0;
+
+    for (doc_id, _) in ranked.iter().take(k) {
+        let relevance = qrels.get(doc_id.as_str()).copied().unwrap_or(0);
+        rbp += p_power * (gain.gain(relevance) / max_gain).clamp(0.0, 1.0);
+        p_power *= persistence;
+    }
+
+    (1.0 - persistence) * rbp
+}
+
+/// ERR over a sequence of grades, using Chapelle's satisfaction probability
+/// `(2^g - 1) / 2^max_grade` (grades are capped at `max_grade`).
+fn err_from_grades(grades: impl Iterator<Item = u32>, k: usize, max_grade: u32) -> f64 {
+    let max_gain = 2f64.powi(max_grade as i32);
+    err_from_satisfaction(grades, k, |grade| {
+        (2f64.powi(grade.min(max_grade) as i32) - 1.0) / max_gain
+    })
+}
+
+/// ERR over a sequence of grades with satisfaction probability `sat(grade)`,
+/// clamped to [0, 1].
+fn err_from_satisfaction(
+    grades: impl Iterator<Item = u32>,
+    k: usize,
+    sat: impl Fn(u32) -> f64,
+) -> f64 {
+    let mut p_continue = 1.0;
+    let mut err = 0.0;
+
+    for (i, grade) in grades.take(k).enumerate() {
+        let r = sat(grade).clamp(0.0, 1.0);
+        err += p_continue * r / (i + 1) as f64;
+        p_continue *= 1.0 - r;
+    }
+
+    err
+}
+
+/// ERR@k of the ideal ranking: judged documents in descending grade order.
+fn ideal_err(qrels: &HashMap<String, u32>, k: usize, max_grade: u32) -> f64 {
+    let mut ideal_grades: Vec<u32> = qrels.values().copied().filter(|&g| g > 0).collect();
+    ideal_grades.sort_unstable_by(|a, b| b.cmp(a));
+    err_from_grades(ideal_grades.into_iter(), k, max_grade)
+}
+
+/// Compute graded ERR@k.
+///
+/// Formula: `ERR@k = Σᵢ (1/i) × Rᵢ × Πⱼ<ᵢ (1 - Rⱼ)`
+///
+/// Where `Rᵢ = (2^rel(i) - 1) / 2^max_grade`. Unjudged documents have grade 0.
+///
+/// Reference: Chapelle et al. (2009) "Expected reciprocal rank for graded relevance"
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::err_at_k;
+///
+/// let ranked = vec![("doc1".to_string(), 0.9)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 1);
+///
+/// // R = (2^1 - 1) / 2^1 = 0.5
+/// assert!((err_at_k(&ranked, &qrels, 10, 1) - 0.5).abs() < 1e-9);
+/// ```
+pub fn err_at_k(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+    max_grade: u32,
+) -> f64 {
+    let grades = ranked
+        .iter()
+        .map(|(doc_id, _)| qrels.get(doc_id.as_str()).copied().unwrap_or(0));
+    err_from_grades(grades, k, max_grade)
+}
+
+/// Compute normalized ERR@k (nERR).
+///
+/// Formula: `nERR@k = ERR@k / ideal ERR@k`
+///
+/// The ideal ranking places the query's judged documents in descending grade
+/// order. Returns 0.0 if the query has no relevant documents.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::nerr_at_k;
+///
+/// let ranked = vec![("doc1".to_string(), 0.9)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 1);
+///
+/// assert!((nerr_at_k(&ranked, &qrels, 10, 3) - 1.0).abs() < 1e-9);
+/// ```
+pub fn nerr_at_k(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+    max_grade: u32,
+) -> f64 {
+    let ideal = ideal_err(qrels, k, max_grade);
+    if ideal > 0.0 {
+        err_at_k(ranked, qrels, k, max_grade) / ideal
+    } else {
+        0.0
+    }
+}
+
+/// Compute ERR@k with a caller-supplied satisfaction probability per grade.
+///
+/// Formula: `ERR@k = Σᵢ (1/i) × Rᵢ × Πⱼ<ᵢ (1 - Rⱼ)` with `Rᵢ = sat(rel(i))`
+///
+/// [`err_at_k`] fixes `sat(g) = (2^g - 1) / 2^max_grade`; here `sat` returns
+/// the probability that a user who reaches a document of grade `g` is
+/// satisfied and stops. Values are clamped to [0, 1]. Unjudged documents have
+/// grade 0, so `sat(0)` should normally be 0. This is
+/// [`compute_err_with_gain`] with `sat` as the gain and a `max_gain` of 1.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::{err_at_k, err_with_satisfaction};
+///
+/// let ranked = vec![("doc1".to_string(), 0.9)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 1);
+///
+/// // Linear satisfaction on a 0-2 scale
+/// let linear = err_with_satisfaction(&ranked, &qrels, 10, |g| g as f64 / 2.0);
+/// assert_eq!(linear, 0.5);
+/// assert_eq!(err_at_k(&ranked, &qrels, 10, 2), 0.25);
+/// ```
+pub fn err_with_satisfaction(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+    sat: impl Fn(u32) -> f64,
+) -> f64 {
+    compute_err_with_gain(ranked, qrels, k, &FnGain(sat), 1.0)
+}
+
+/// Compute ERR@k and nERR@k in one pass over the ideal ranking.
+///
+/// Returns `(err, nerr)`, equal to [`err_at_k`] and [`nerr_at_k`].
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::err_and_nerr_at_k;
+///
+/// let ranked = vec![("doc1".to_string(), 0.9), ("doc2".to_string(), 0.8)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc2".to_string(), 2);
+///
+/// let (err, nerr) = err_and_nerr_at_k(&ranked, &qrels, 10, 2);
+/// assert!(err < nerr && nerr < 1.0);
+/// ```
+pub fn err_and_nerr_at_k(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+    max_grade: u32,
+) -> (f64, f64) {
+    let err = err_at_k(ranked, qrels, k, max_grade);
+    let ideal = ideal_err(qrels, k, max_grade);
+    let nerr = if ideal > 0.0 { err / ideal } else { 0.0 };
+    (err, nerr)
+}
+
+/// Compute nDCG@k for graded relevance, treating unjudged documents as `default_grade`.
+///
+/// Identical to [`compute_ndcg`] except that retrieved documents missing from
+/// `qrels` are assigned `default_grade` instead of 0. Those documents also
+/// enter the ideal ranking, so the result stays within [0.0, 1.0].
+///
+/// With `default_grade = 0` this is exactly [`compute_ndcg`]. A non-zero
+/// default is appropriate when the pool is known to be mostly relevant.
+///
+/// # Arguments
+///
+/// * `ranked` - List of (document_id, score) tuples in ranked order
+/// * `qrels` - Map from document_id to relevance score
+/// * `k` - Cutoff rank for nDCG calculation
+/// * `default_grade` - Grade assumed for retrieved documents not in `qrels`
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::compute_ndcg_with_default;
+///
+/// let ranked = vec![
+///     ("unjudged".to_string(), 0.9),
+///     ("doc1".to_string(), 0.8),
+/// ];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 2);
+///
+/// let strict = compute_ndcg_with_default(&ranked, &qrels, 10, 0);
+/// let lenient = compute_ndcg_with_default(&ranked, &qrels, 10, 1);
+/// assert!(lenient > strict);
+/// ```
+pub fn compute_ndcg_with_default(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+    default_grade: u32,
+) -> f64 {
+    let grade_of = |doc_id: &str| qrels.get(doc_id).copied().unwrap_or(default_grade);
+
+    let mut dcg = 0.0;
+    for (rank, (doc_id, _)) in ranked.iter().take(k).enumerate() {
+        let relevance = grade_of(doc_id);
+        if relevance > 0 {
+            dcg += (relevance as f64) / ((rank + 2) as f64).log2();
+        }
+    }
+
+    let mut ideal_gains: Vec<u32> = qrels.values().copied().filter(|&r| r > 0).collect();
+    if default_grade > 0 {
+        let mut seen = HashSet::new();
+        for (doc_id, _) in ranked {
+            if !qrels.contains_key(doc_id) && seen.insert(doc_id.as_str()) {
+                ideal_gains.push(default_grade);
+            }
+        }
+    }
+    ideal_gains.sort_by(|a, b| b.cmp(a));
+
+    let mut idcg = 0.0;
+    for (rank, &gain) in ideal_gains.iter().take(k).enumerate() {
+        idcg += (gain as f64) / ((rank + 2) as f64).log2();
+    }
+
+    if idcg > 0.0 {
+        dcg / idcg
+    } else {
+        0.0
+    }
+}
+
+/// Split the nDCG@k shortfall into missing-document and mis-ordering losses.
+///
+/// Let `DCG*` be the DCG obtained by re-sorting only the relevant documents
+/// already in the top k by grade (descending) into the top positions. Then:
+///
+/// - `missing = (IDCG@k - DCG*) / IDCG@k` — loss from relevant documents absent
+///   from the top k (or present at lower grades than the ideal)
+/// - `ordering = (DCG* - DCG@k) / IDCG@k` — loss from the order of the
+///   documents that are present, including non-relevant documents ranked above them
+///
+/// The two parts sum to `1 - nDCG@k` (as computed by [`compute_ndcg`]).
+/// Returns `(0.0, 0.0)` if there are no relevant documents.
+///
+/// # Arguments
+///
+/// * `ranked` - List of (document_id, score) tuples in ranked order
+/// * `qrels` - Map from document_id to relevance score
+/// * `k` - Cutoff rank
+///
+/// # Returns
+///
+/// `(missing_loss, ordering_loss)`
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::{compute_ndcg, ndcg_loss_decomposition};
+///
+/// let ranked = vec![("doc2".to_string(), 0.9), ("doc1".to_string(), 0.8)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 2);
+/// qrels.insert("doc2".to_string(), 1);
+/// qrels.insert("doc3".to_string(), 1);
+///
+/// let (missing, ordering) = ndcg_loss_decomposition(&ranked, &qrels, 2);
+/// let ndcg = compute_ndcg(&ranked, &qrels, 2);
+/// assert!((missing + ordering - (1.0 - ndcg)).abs() < 1e-9);
+/// ```
+pub fn ndcg_loss_decomposition(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+) -> (f64, f64) {
+    let dcg_of = |gains: &[u32]| -> f64 {
+        gains
+            .iter()
+            .take(k)
+            .enumerate()
+            .map(|(rank, &gain)| gain as f64 / ((rank + 2) as f64).log2())
+            .sum()
+    };
+
+    let actual_gains: Vec<u32> = ranked
+        .iter()
+        .take(k)
+        .map(|(doc_id, _)| qrels.get(doc_id.as_str()).copied().unwrap_or(0))
+        .collect();
+
+    let mut present_gains: Vec<u32> = actual_gains.iter().copied().filter(|&g| g > 0).collect();
+    present_gains.sort_by(|a, b| b.cmp(a));
+
+    let mut ideal_gains: Vec<u32> = qrels.values().copied().filter(|&r| r > 0).collect();
+    ideal_gains.sort_by(|a, b| b.cmp(a));
+
+    let idcg = dcg_of(&ideal_gains);
+    if idcg <= 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let dcg = dcg_of(&actual_gains);
+    let present_dcg = dcg_of(&present_gains);
+
+    ((idcg - present_dcg) / idcg, (present_dcg - dcg) / idcg)
+}
+
+/// Split DCG@k and ideal DCG@k into per-grade contributions.
+///
+/// Formula: `DCG_g@k = Σ_{i ≤ k, rel(i) = g} g / log₂(i + 2)`
+///
+/// Returns grade -> `(actual, ideal)`, where `actual` sums over the top k of
+/// `ranked` and `ideal` over the top k of the ideal ranking (judged documents
+/// sorted by grade, as in [`compute_ndcg`]). The actual contributions sum to
+/// DCG@k and the ideal ones to IDCG@k. A grade whose actual share is far below
+/// its ideal share is where the ranking loses gain. Only grades > 0 that
+/// contribute to either side are reported.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::dcg_by_grade;
+///
+/// let ranked = vec![("doc1".to_string(), 0.9)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 1);
+/// qrels.insert("doc2".to_string(), 3);
+///
+/// let by_grade = dcg_by_grade(&ranked, &qrels, 10);
+/// assert_eq!(by_grade[&3].0, 0.0); // the grade-3 document was never retrieved
+/// assert_eq!(by_grade[&3].1, 3.0); // but ideally contributes 3 / log₂(2)
+/// ```
+pub fn dcg_by_grade(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+) -> HashMap<u32, (f64, f64)> {
+    let mut contributions: HashMap<u32, (f64, f64)> = HashMap::new();
+
+    for (rank, (doc_id, _)) in ranked.iter().take(k).enumerate() {
+        let grade = qrels.get(doc_id.as_str()).copied().unwrap_or(0);
+        if grade > 0 {
+            contributions.entry(grade).or_default().0 += grade as f64 / ((rank + 2) as f64).log2();
+        }
+    }
+
+    let mut ideal_grades: Vec<u32> = qrels.values().copied().filter(|&g| g > 0).collect();
+    ideal_grades.sort_unstable_by(|a, b| b.cmp(a));
+    for (rank, &grade) in ideal_grades.iter().take(k).enumerate() {
+        contributions.entry(grade).or_default().1 += grade as f64 / ((rank + 2) as f64).log2();
+    }
+
+    contributions
+}
+
+/// Compute recall@k separately for each relevance grade.
+///
+/// Formula: `recall_g@k = |{d in top k : grade(d) = g}| / |{d : grade(d) = g}|`
+///
+/// Shows whether the top k is missing the most relevant documents specifically,
+/// which overall recall hides. Only grades > 0 present in `qrels` are reported.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::per_grade_recall_at_k;
+///
+/// let ranked = vec![("doc1".to_string(), 0.9), ("doc3".to_string(), 0.8)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 2);
+/// qrels.insert("doc2".to_string(), 1);
+/// qrels.insert("doc3".to_string(), 1);
+///
+/// let recall = per_grade_recall_at_k(&ranked, &qrels, 10);
+/// assert_eq!(recall[&2], 1.0);
+/// assert_eq!(recall[&1], 0.5);
+/// ```
+pub fn per_grade_recall_at_k(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+) -> HashMap<u32, f64> {
+    let mut totals: HashMap<u32, usize> = HashMap::new();
+    for &grade in qrels.values().filter(|&&g| g > 0) {
+        *totals.entry(grade).or_insert(0) += 1;
+    }
+
+    let mut seen = HashSet::new();
+    let mut found: HashMap<u32, usize> = HashMap::new();
+    for (doc_id, _) in ranked.iter().take(k) {
+        if let Some(&grade) = qrels.get(doc_id.as_str()) {
+            if grade > 0 && seen.insert(doc_id.as_str()) {
+                *found.entry(grade).or_insert(0) += 1;
+            }
+        }
+    }
+
+    totals
+        .into_iter()
+        .map(|(grade, total)| {
+            let hits = found.get(&grade).copied().unwrap_or(0);
+            (grade, hits as f64 / total as f64)
+        })
+        .collect()
+}
+
+/// Normalized area under the cumulative gain curve over the top k.
+///
+/// Formula: `Σᵢ₌₁ᵏ CG@i / Σᵢ₌₁ᵏ ICG@i`
+///
+/// Where `CG@i` is the (undiscounted) sum of grades in the top i and `ICG@i`
+/// is the same for the ideal ordering. Rewarding early gain through the area
+/// rather than a log discount, this is a discount-free alternative to nDCG:
+/// perfect ordering gives 1.0. Returns 0.0 if there are no relevant documents.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::normalized_cumulative_gain_area;
+///
+/// let ranked = vec![("doc1".to_string(), 0.9), ("doc2".to_string(), 0.8)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc2".to_string(), 1);
+///
+/// // CG curve (0, 1) vs ideal (1, 1)
+/// assert!((normalized_cumulative_gain_area(&ranked, &qrels, 2) - 0.5).abs() < 1e-9);
+/// ```
+pub fn normalized_cumulative_gain_area(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+) -> f64 {
+    let mut ideal_gains: Vec<u32> = qrels.values().copied().filter(|&r| r > 0).collect();
+    ideal_gains.sort_by(|a, b| b.cmp(a));
+
+    // Area of a cumulative curve over k positions; the curve stays flat past
+    // the end of the gain list
+    let area = |gains: &mut dyn Iterator<Item = u32>| -> f64 {
+        let mut cumulative = 0.0;
+        let mut area = 0.0;
+        let mut positions = 0;
+        for gain in gains.take(k) {
+            cumulative += gain as f64;
+            area += cumulative;
+            positions += 1;
+        }
+        area + cumulative * (k - positions) as f64
+    };
+
+    let ideal_area = area(&mut ideal_gains.iter().copied());
+    if ideal_area <= 0.0 {
+        return 0.0;
+    }
+
+    let actual_area = area(
+        &mut ranked
+            .iter()
+            .map(|(doc_id, _)| qrels.get(doc_id.as_str()).copied().unwrap_or(0)),
+    );
+
+    actual_area / ideal_area
+}
+
+/// Fraction of queries retrieving at least one document of each grade in the top k.
+///
+/// For each grade g > 0, the denominator is the number of evaluated queries
+/// with at least one grade-g document in `qrels`, and the numerator is how
+/// many of those retrieve one in their top k. This shows whether a system
+/// reliably surfaces the top grade. Queries without runs are skipped; when a
+/// run file holds several tags, the lexicographically smallest is used.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::graded::success_rate_by_grade;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new().add("1", "doc1", 0.9, "run").build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).add("1", "doc2", 3).build();
+///
+/// let rates = success_rate_by_grade(&runs, &qrels, 10);
+/// assert_eq!(rates[&1], 1.0);
+/// assert_eq!(rates[&3], 0.0);
+/// ```
+pub fn success_rate_by_grade(runs: &[TrecRun], qrels: &[Qrel], k: usize) -> HashMap<u32, f64> {
+    let runs_by_query = group_runs_by_query(runs);
+    let qrels_by_query = group_qrels_by_query(qrels);
+
+    let mut judged: HashMap<u32, usize> = HashMap::new();
+    let mut successes: HashMap<u32, usize> = HashMap::new();
+
+    for (query_id, query_qrels) in &qrels_by_query {
+        let ranked = match runs_by_query.get(query_id).and_then(first_tag_run) {
+            Some(ranked) => ranked,
+            None => continue,
+        };
+
+        let grades: HashSet<u32> = query_qrels.values().copied().filter(|&g| g > 0).collect();
+        let retrieved: HashSet<u32> = ranked
+            .iter()
+            .take(k)
+            .filter_map(|(doc_id, _)| query_qrels.get(doc_id).copied())
+            .collect();
+
+        for grade in grades {
+            *judged.entry(grade).or_insert(0) += 1;
+            if retrieved.contains(&grade) {
+                *successes.entry(grade).or_insert(0) += 1;
+            }
+        }
+    }
+
+    judged
+        .into_iter()
+        .map(|(grade, total)| {
+            let hits = successes.get(&grade).copied().unwrap_or(0);
+            (grade, hits as f64 / total as f64)
+        })
+        .collect()
+}
+
+/// Compute Mean Average Precision (MAP) for graded relevance.
+///
+/// Formula: `AP = (1/|R|) × Σᵢ (P@i × rel(i))`
+///
+/// Where:
+/// - `R` is the set of documents with relevance > 0
+/// - `P@i` is precision at position i
+/// - `rel(i)` = 1 if document at position i has relevance > 0, 0 otherwise
+///
+/// For a single query, this is Average Precision (AP). When averaged across queries,
+/// it becomes Mean Average Precision (MAP).
+///
+/// Note: MAP is traditionally defined for binary relevance, so this implementation
+/// treats any relevance > 0 as relevant (binary conversion).
+///
+/// Reference: Manning et al. (2008) [Introduction to Information Retrieval, Chapter 8](https://nlp.stanford.edu/IR-book/html/htmledition/evaluation-in-information-retrieval-1.html)
+///
+/// Uses binary relevance (relevance > 0) for MAP calculation, as MAP is
+/// traditionally defined for binary relevance.
+///
+/// # Arguments
+///
+/// * `ranked` - List of (document_id, score) tuples in ranked order
+/// * `qrels` - Map from document_id to relevance score
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::compute_map;
+///
+/// let ranked = vec![
+///     ("doc1".to_string(), 0.9),
+///     ("doc2".to_string(), 0.8),
+///     ("doc3".to_string(), 0.7),
+/// ];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 2);
+/// qrels.insert("doc2".to_string(), 1);
+/// qrels.insert("doc3".to_string(), 0);
+///
+/// let map = compute_map(&ranked, &qrels);
+/// assert!(map >= 0.0 && map <= 1.0);
+/// ```
+pub fn compute_map(ranked: &[(String, f32)], qrels: &HashMap<String, u32>) -> f64 {
+    let relevant_docs: Vec<&String> = qrels
+        .iter()
+        .filter(|(_, &rel)| rel > 0)
+        .map(|(doc_id, _)| doc_id)
+        .collect();
+
+    if relevant_docs.is_empty() {
+        return 0.0;
+    }
+
+    let mut sum_precision = 0.0;
+    let mut relevant_found = 0;
+
+    for (rank, (doc_id, _)) in ranked.iter().enumerate() {
+        if qrels.get(doc_id.as_str()).unwrap_or(&0) > &0 {
+            relevant_found += 1;
+            sum_precision += relevant_found as f64 / (rank + 1) as f64;
+        }
+    }
+
+    if relevant_found > 0 {
+        sum_precision / relevant_docs.len() as f64
+    } else {
+        0.0
+    }
+}
+
+/// Compute the effective (gain-weighted) number of relevant documents.
+///
+/// Formula: `Σ_d min(grade(d) / max_grade, 1)` over all judged documents
+///
+/// Each judged document counts in proportion to its grade, so a qrels set
+/// with grades {1, 2, 3} and `max_grade = 3` has 2.0 effective relevant
+/// documents. Used as the denominator of graded metrics such as
+/// [`graded_average_precision`]. Returns 0.0 if `max_grade` is 0.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::effective_relevant;
+///
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 1);
+/// qrels.insert("doc2".to_string(), 2);
+/// qrels.insert("doc3".to_string(), 3);
+///
+/// assert!((effective_relevant(&qrels, 3) - 2.0).abs() < 1e-9);
+/// ```
+pub fn effective_relevant(qrels: &HashMap<String, u32>, max_grade: u32) -> f64 {
+    if max_grade == 0 {
+        return 0.0;
+    }
+    qrels
+        .values()
+        .map(|&grade| (grade as f64 / max_grade as f64).min(1.0))
+        .sum()
+}
+
+/// Sum of `min(grade / max_grade, 1)` over the top k of `ranked`.
+fn expected_relevant_in_top_k(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+    max_grade: u32,
+) -> f64 {
+    ranked
+        .iter()
+        .take(k)
+        .map(|(doc_id, _)| {
+            let grade = qrels.get(doc_id.as_str()).copied().unwrap_or(0);
+            (grade as f64 / max_grade as f64).min(1.0)
+        })
+        .sum()
+}
+
+/// Compute expected precision@k, reading `grade / max_grade` as P(relevant).
+///
+/// Formula: `E[P@k] = Σᵢ₌₁..ₖ min(rel(i) / max_grade, 1) / k`
+///
+/// When every judged grade equals `max_grade` this is binary precision@k.
+/// Returns 0.0 if `k` or `max_grade` is 0.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::expected_precision_at_k;
+///
+/// let ranked = vec![("doc1".to_string(), 0.9), ("doc2".to_string(), 0.8)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 1);
+///
+/// // doc1 is relevant with probability 1/2
+/// assert!((expected_precision_at_k(&ranked, &qrels, 2, 2) - 0.25).abs() < 1e-9);
+/// ```
+pub fn expected_precision_at_k(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+    max_grade: u32,
+) -> f64 {
+    if k == 0 || max_grade == 0 {
+        return 0.0;
+    }
+    expected_relevant_in_top_k(ranked, qrels, k, max_grade) / k as f64
+}
+
+/// Compute expected recall@k, reading `grade / max_grade` as P(relevant).
+///
+/// Formula: `E[R@k] = Σᵢ₌₁..ₖ min(rel(i) / max_grade, 1) / effective_relevant`
+///
+/// The denominator is the expected number of relevant documents,
+/// [`effective_relevant`]. When every judged grade equals `max_grade` this is
+/// binary recall@k. Returns 0.0 if the expected total is 0.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::expected_recall_at_k;
+///
+/// let ranked = vec![("doc1".to_string(), 0.9)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 1);
+/// qrels.insert("doc2".to_string(), 2);
+///
+/// // 0.5 of the 1.5 expected relevant documents retrieved
+/// assert!((expected_recall_at_k(&ranked, &qrels, 10, 2) - 1.0 / 3.0).abs() < 1e-9);
+/// ```
+pub fn expected_recall_at_k(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    k: usize,
+    max_grade: u32,
+) -> f64 {
+    let total = effective_relevant(qrels, max_grade);
+    if total <= 0.0 {
+        return 0.0;
+    }
+    expected_relevant_in_top_k(ranked, qrels, k, max_grade) / total
+}
+
+/// Compute graded Average Precision.
+///
+/// Formula: `GAP = Σᵢ (w(i) × P@i) / Σ_d w(d)`
+///
+/// Where:
+/// - `w(d) = grade(d) / max_grade` is the weight of a judged relevant document
+/// - `P@i` is (binary) precision at position i, counting any grade > 0 as relevant
+/// - the numerator sums over retrieved relevant documents, the denominator over
+///   all relevant documents in `qrels`
+///
+/// Unlike [`compute_map`], which binarizes grades, each relevant document is
+/// credited in proportion to its grade. With a single grade level this reduces
+/// to standard AP. Grades above `max_grade` are capped at weight 1.0.
+///
+/// # Arguments
+///
+/// * `ranked` - List of (document_id, score) tuples in ranked order
+/// * `qrels` - Map from document_id to relevance score
+/// * `max_grade` - Highest grade in the relevance scale
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::graded_average_precision;
+///
+/// let ranked = vec![("doc1".to_string(), 0.9), ("doc2".to_string(), 0.8)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 3);
+/// qrels.insert("doc2".to_string(), 1);
+///
+/// let gap = graded_average_precision(&ranked, &qrels, 3);
+/// assert!(gap > 0.0 && gap <= 1.0);
+/// ```
+pub fn graded_average_precision(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    max_grade: u32,
+) -> f64 {
+    if max_grade == 0 {
+        return 0.0;
+    }
+    let weight = |grade: u32| (grade as f64 / max_grade as f64).min(1.0);
+
+    let total_weight = effective_relevant(qrels, max_grade);
+    if total_weight == 0.0 {
+        return 0.0;
+    }
+
+    let mut sum = 0.0;
+    let mut relevant_found = 0;
+    for (rank, (doc_id, _)) in ranked.iter().enumerate() {
+        let grade = qrels.get(doc_id.as_str()).copied().unwrap_or(0);
+        if grade > 0 {
+            relevant_found += 1;
+            sum += weight(grade) * relevant_found as f64 / (rank + 1) as f64;
+        }
+    }
+
+    sum / total_weight
+}
+
+/// Compute Q-measure for graded relevance.
+///
+/// Formula: `Q = (1/R) × Σᵢ rel(i) × (C(i) + β × cg(i)) / (i + β × cg*(i))`
+///
+/// Where:
+/// - `R` is the number of relevant documents (grade > 0) in `qrels`
+/// - `rel(i)` = 1 if the document at rank i is relevant, 0 otherwise
+/// - `C(i)` is the number of relevant documents in the top i
+/// - `cg(i)` and `cg*(i)` are the cumulative gains (grade) of the ranking and
+///   of the ideal ranking at rank i
+///
+/// Q-measure is a graded generalization of AP: with `β = 0` it is exactly AP,
+/// and larger `β` rewards placing high grades early. `β = 1` is standard.
+///
+/// Reference: Sakai (2004) "New performance metrics based on multigrade relevance"
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::graded::compute_q_measure;
+///
+/// let ranked = vec![("doc1".to_string(), 0.9), ("doc2".to_string(), 0.8)];
+/// let mut qrels = HashMap::new();
+/// qrels.insert("doc1".to_string(), 2);
+/// qrels.insert("doc2".to_string(), 1);
+///
+/// assert!((compute_q_measure(&ranked, &qrels, 1.0) - 1.0).abs() < 1e-9);
+/// ```
+pub fn compute_q_measure(ranked: &[(String, f32)], qrels: &HashMap<String, u32>, beta: f64) -> f64 {
+    let mut ideal_grades: Vec<u32> = qrels.values().copied().filter(|&g| g > 0).collect();
+    if ideal_grades.is_empty() {
+        return 0.0;
+    }
+    ideal_grades.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut relevant_seen = 0.0;
+    let mut cumulative_gain = 0.0;
+    let mut ideal_cumulative_gain = 0.0;
+    let mut sum = 0.0;
+
+    for (i, (doc_id, _)) in ranked.iter().enumerate() {
+        // The ideal ranking has no gain past its last relevant document
+        ideal_cumulative_gain += ideal_grades.get(i).copied().unwrap_or(0) as f64;
+
+        let grade = qrels.get(doc_id.as_str()).copied().unwrap_or(0);
+        if grade > 0 {
+            relevant_seen += 1.0;
+            cumulative_gain += grade as f64;
+            sum += (relevant_seen + beta * cumulative_gain)
+                / ((i + 1) as f64 + beta * ideal_cumulative_gain);
+        }
+    }
+
+    sum / ideal_grades.len() as f64
+}
+
+/// Half-life, in seconds, of the user's probability of continuing to read in
+/// [`time_biased_gain`]: the weight of a document reached after this much time
+/// is one half. 224 seconds is the value Smucker & Clarke fitted to user data.
+pub const TBG_HALF_LIFE_SECS: f64 = 224.0;
+
+/// Compute time-biased gain (TBG).
 ///
-/// Formula: `AP = (1/|R|) × Σᵢ (P@i × rel(i))`
+/// Formula: `TBG = Σᵢ gain(i) × exp(-T(i) × ln 2 / h)`
 ///
 /// Where:
-/// - `R` is the set of documents with relevance > 0
-/// - `P@i` is precision at position i
-/// - `rel(i)` = 1 if document at position i has relevance > 0, 0 otherwise
-///
-/// For a single query, this is Average Precision (AP). When averaged across queries,
-/// it becomes Mean Average Precision (MAP).
-///
-/// Note: MAP is traditionally defined for binary relevance, so this implementation
-/// treats any relevance > 0 as relevant (binary conversion).
-///
-/// Reference: Manning et al. (2008) [Introduction to Information Retrieval, Chapter 8](https://nlp.stanford.edu/IR-book/html/htmledition/evaluation-in-information-retrieval-1.html)
-///
-/// Uses binary relevance (relevance > 0) for MAP calculation, as MAP is
-/// traditionally defined for binary relevance.
+/// - `gain(i)` = `grade / max_grade`, the probability the document at rank i
+///   is useful to the user
+/// - `T(i)` = `Σⱼ<ᵢ summary_len(grade(j))`, the time in seconds to reach rank i
+/// - `h` = [`TBG_HALF_LIFE_SECS`], the decay half-life
 ///
-/// # Arguments
+/// `summary_len` maps a grade to the seconds a user spends at a document of
+/// that grade (reading its summary, plus the document itself when clicked),
+/// so relevant documents that take long to read delay everything below them.
+/// Unlike rank-based discounts, the decay is in elapsed time. The score is not
+/// normalized.
 ///
-/// * `ranked` - List of (document_id, score) tuples in ranked order
-/// * `qrels` - Map from document_id to relevance score
+/// Reference: Smucker & Clarke (2012) "Time-based calibration of effectiveness
+/// measures"
 ///
 /// # Example
 ///
 /// ```
 /// use std::collections::HashMap;
-/// use rank_eval::graded::compute_map;
+/// use rank_eval::graded::time_biased_gain;
 ///
-/// let ranked = vec![
-///     ("doc1".to_string(), 0.9),
-///     ("doc2".to_string(), 0.8),
-///     ("doc3".to_string(), 0.7),
-/// ];
+/// let ranked = vec![("doc1".to_string(), 0.9), ("doc2".to_string(), 0.8)];
 /// let mut qrels = HashMap::new();
 /// qrels.insert("doc1".to_string(), 2);
-/// qrels.insert("doc2".to_string(), 1);
-/// qrels.insert("doc3".to_string(), 0);
 ///
-/// let map = compute_map(&ranked, &qrels);
-/// assert!(map >= 0.0 && map <= 1.0);
+/// // 4.4s per summary, plus reading time for relevant documents
+/// let time = |grade: u32| if grade > 0 { 4.4 + 30.0 } else { 4.4 };
+/// assert_eq!(time_biased_gain(&ranked, &qrels, time, 2), 1.0);
 /// ```
-pub fn compute_map(ranked: &[(String, f32)], qrels: &HashMap<String, u32>) -> f64 {
-    let relevant_docs: Vec<&String> = qrels
-        .iter()
-        .filter(|(_, &rel)| rel > 0)
-        .map(|(doc_id, _)| doc_id)
-        .collect();
-
-    if relevant_docs.is_empty() {
+pub fn time_biased_gain(
+    ranked: &[(String, f32)],
+    qrels: &HashMap<String, u32>,
+    summary_len: impl Fn(u32) -> f64,
+    max_grade: u32,
+) -> f64 {
+    if max_grade == 0 {
         return 0.0;
     }
 
-    let mut sum_precision = 0.0;
-    let mut relevant_found = 0;
+    let decay_rate = std::f64::consts::LN_2 / TBG_HALF_LIFE_SECS;
+    let mut elapsed = 0.0;
+    let mut tbg = 0.0;
 
-    for (rank, (doc_id, _)) in ranked.iter().enumerate() {
-        if qrels.get(doc_id.as_str()).unwrap_or(&0) > &0 {
-            relevant_found += 1;
-            sum_precision += relevant_found as f64 / (rank + 1) as f64;
-        }
+    for (doc_id, _) in ranked {
+        let grade = qrels.get(doc_id.as_str()).copied().unwrap_or(0);
+        let gain = grade.min(max_grade) as f64 / max_grade as f64;
+        tbg += gain * (-elapsed * decay_rate).exp();
+        elapsed += summary_len(grade);
     }
 
-    if relevant_found > 0 {
-        sum_precision / relevant_docs.len() as f64
-    } else {
-        0.0
+    tbg
+}
+
+/// All metrics for a single ranking (graded relevance).
+///
+/// Graded counterpart of [`crate::binary::Metrics`]. nDCG uses
+/// [`ExponentialGain`], and `graded_map` is [`graded_average_precision`].
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct GradedMetrics {
+    pub ndcg_at_5: f64,
+    pub ndcg_at_10: f64,
+    pub err_at_10: f64,
+    pub graded_map: f64,
+    pub q_measure: f64,
+}
+
+#[cfg(feature = "serde")]
+impl GradedMetrics {
+    /// Compute all metrics for a ranking. `max_grade` is the highest grade in
+    /// the relevance scale (used by ERR and graded AP).
+    pub fn compute(ranked: &[(String, f32)], qrels: &HashMap<String, u32>, max_grade: u32) -> Self {
+        Self {
+            ndcg_at_5: compute_ndcg_with_gain(ranked, qrels, 5, &ExponentialGain),
+            ndcg_at_10: compute_ndcg_with_gain(ranked, qrels, 10, &ExponentialGain),
+            err_at_10: err_at_k(ranked, qrels, 10, max_grade),
+            graded_map: graded_average_precision(ranked, qrels, max_grade),
+            q_measure: compute_q_measure(ranked, qrels, 1.0),
+        }
     }
 }
 
@@ -175,6 +1422,24 @@ mod tests {
         assert!(ndcg > 0.5); // Should be reasonably high since highly relevant doc is first
     }
 
+    #[test]
+    fn test_compute_ndcg_with_default() {
+        let ranked = vec![
+            ("unjudged1".to_string(), 0.9),
+            ("doc1".to_string(), 0.8),
+            ("unjudged2".to_string(), 0.7),
+        ];
+        let mut qrels = HashMap::new();
+        qrels.insert("doc1".to_string(), 2);
+
+        let strict = compute_ndcg_with_default(&ranked, &qrels, 3, 0);
+        assert!((strict - compute_ndcg(&ranked, &qrels, 3)).abs() < 1e-9);
+
+        let lenient = compute_ndcg_with_default(&ranked, &qrels, 3, 1);
+        assert!(lenient > strict);
+        assert!(lenient <= 1.0);
+    }
+
     #[test]
     fn test_compute_map_graded() {
         let ranked = vec![
@@ -188,7 +1453,7 @@ mod tests {
         qrels.insert("doc3".to_string(), 0);
 
         let map = compute_map(&ranked, &qrels);
-        assert!(map >= 0.0 && map <= 1.0);
+        assert!((0.0..=1.0).contains(&map));
         
         // Both doc1 and doc2 are relevant (relevance > 0)
         // doc1 at rank 0: precision = 1/1 = 1.0
@@ -220,5 +1485,448 @@ mod tests {
         let map = compute_map(&ranked, &qrels);
         assert_eq!(map, 0.0);
     }
-}
 
+    #[test]
+    fn test_effective_relevant() {
+        let mut qrels = HashMap::new();
+        qrels.insert("doc1".to_string(), 1);
+        qrels.insert("doc2".to_string(), 2);
+        qrels.insert("doc3".to_string(), 3);
+        qrels.insert("doc4".to_string(), 0);
+
+        assert!((effective_relevant(&qrels, 3) - 2.0).abs() < 1e-9);
+        assert_eq!(effective_relevant(&qrels, 0), 0.0);
+    }
+
+    #[test]
+    fn test_graded_average_precision() {
+        let mut qrels = HashMap::new();
+        qrels.insert("high".to_string(), 3);
+        qrels.insert("low".to_string(), 1);
+
+        let high_first = vec![("high".to_string(), 0.9), ("none".to_string(), 0.8)];
+        let low_first = vec![("low".to_string(), 0.9), ("none".to_string(), 0.8)];
+
+        let gap_high = graded_average_precision(&high_first, &qrels, 3);
+        let gap_low = graded_average_precision(&low_first, &qrels, 3);
+        assert!((gap_high - 0.75).abs() < 1e-9);
+        assert!((gap_low - 0.25).abs() < 1e-9);
+
+        // Single grade level reduces to standard AP
+        let mut binary = HashMap::new();
+        binary.insert("doc1".to_string(), 1);
+        binary.insert("doc3".to_string(), 1);
+        let ranked = vec![
+            ("doc1".to_string(), 0.9),
+            ("doc2".to_string(), 0.8),
+            ("doc3".to_string(), 0.7),
+        ];
+        assert!(
+            (graded_average_precision(&ranked, &binary, 1) - compute_map(&ranked, &binary)).abs()
+                < 1e-9
+        );
+    }
+
+    #[test]
+    fn test_ndcg_loss_decomposition() {
+        let mut qrels = HashMap::new();
+        qrels.insert("doc1".to_string(), 3);
+        qrels.insert("doc2".to_string(), 2);
+        qrels.insert("doc3".to_string(), 1);
+
+        // All relevant docs present, but in reverse grade order
+        let reversed = vec![
+            ("doc3".to_string(), 0.9),
+            ("doc2".to_string(), 0.8),
+            ("doc1".to_string(), 0.7),
+        ];
+        let (missing, ordering) = ndcg_loss_decomposition(&reversed, &qrels, 3);
+        let ndcg = compute_ndcg(&reversed, &qrels, 3);
+        assert!(missing.abs() < 1e-9);
+        assert!((ordering - (1.0 - ndcg)).abs() < 1e-9);
+        assert!(ordering > 0.0);
+
+        // Correctly ordered but missing doc1: all loss is missing
+        let partial = vec![("doc2".to_string(), 0.9), ("doc3".to_string(), 0.8)];
+        let (missing, ordering) = ndcg_loss_decomposition(&partial, &qrels, 3);
+        assert!(ordering.abs() < 1e-9);
+        assert!((missing - (1.0 - compute_ndcg(&partial, &qrels, 3))).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_per_grade_recall_at_k() {
+        let mut qrels = HashMap::new();
+        qrels.insert("a".to_string(), 3);
+        qrels.insert("b".to_string(), 3);
+        qrels.insert("c".to_string(), 1);
+        qrels.insert("d".to_string(), 1);
+        qrels.insert("e".to_string(), 0);
+
+        let ranked = vec![
+            ("a".to_string(), 0.9),
+            ("e".to_string(), 0.8),
+            ("b".to_string(), 0.7),
+            ("c".to_string(), 0.6),
+            ("d".to_string(), 0.5),
+        ];
+
+        let recall = per_grade_recall_at_k(&ranked, &qrels, 4);
+        assert_eq!(recall.len(), 2);
+        assert!((recall[&3] - 1.0).abs() < 1e-9);
+        assert!((recall[&1] - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_custom_gain() {
+        // Only the top grade earns any gain
+        struct TopGradeOnly;
+        impl Gain for TopGradeOnly {
+            fn gain(&self, grade: u32) -> f64 {
+                if grade >= 3 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+
+        let mut qrels = HashMap::new();
+        qrels.insert("high".to_string(), 3);
+        qrels.insert("low".to_string(), 1);
+        let ranked = vec![("low".to_string(), 0.9), ("high".to_string(), 0.8)];
+
+        let linear = compute_ndcg_with_gain(&ranked, &qrels, 2, &LinearGain);
+        assert!((linear - compute_ndcg(&ranked, &qrels, 2)).abs() < 1e-9);
+
+        // Grade 1 at rank 1 earns nothing; only "high" at rank 2 counts
+        let custom = compute_ndcg_with_gain(&ranked, &qrels, 2, &TopGradeOnly);
+        assert!((custom - 1.0 / 3.0_f64.log2()).abs() < 1e-9);
+        assert!(custom < linear);
+
+        assert!((compute_err_with_gain(&ranked, &qrels, 2, &TopGradeOnly, 1.0) - 0.5).abs() < 1e-9);
+        let rbp = compute_rbp_with_gain(&ranked, &qrels, 2, 0.5, &TopGradeOnly, 1.0);
+        assert!((rbp - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_err_with_gain_uses_scale_maximum() {
+        // On a 0-3 scale, a query whose best document is grade 1 must not
+        // score as if grade 1 were fully satisfying
+        let ranked = vec![("doc1".to_string(), 0.9)];
+        let mut qrels = HashMap::new();
+        qrels.insert("doc1".to_string(), 1);
+
+        let err = compute_err_with_gain(&ranked, &qrels, 10, &ExponentialGain, 8.0);
+        assert!((err - 1.0 / 8.0).abs() < 1e-12);
+        assert_eq!(err, err_at_k(&ranked, &qrels, 10, 3));
+    }
+
+    #[test]
+    fn test_normalized_cumulative_gain_area() {
+        let mut qrels = HashMap::new();
+        qrels.insert("a".to_string(), 3);
+        qrels.insert("b".to_string(), 1);
+
+        let perfect = vec![
+            ("a".to_string(), 0.9),
+            ("b".to_string(), 0.8),
+            ("c".to_string(), 0.7),
+        ];
+        assert!((normalized_cumulative_gain_area(&perfect, &qrels, 3) - 1.0).abs() < 1e-9);
+
+        // CG curve (1, 4, 4) vs ideal (3, 4, 4)
+        let swapped = vec![("b".to_string(), 0.9), ("a".to_string(), 0.8)];
+        assert!((normalized_cumulative_gain_area(&swapped, &qrels, 3) - 9.0 / 11.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_success_rate_by_grade() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "low1", 0.9, "run")
+            .add("1", "high1", 0.8, "run")
+            .add("2", "low2", 0.9, "run")
+            .add("2", "other", 0.8, "run")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "low1", 1)
+            .add("1", "high1", 3)
+            .add("2", "low2", 1)
+            .add("2", "high2", 3)
+            .build();
+
+        let rates = success_rate_by_grade(&runs, &qrels, 10);
+        assert!((rates[&1] - 1.0).abs() < 1e-9);
+        assert!((rates[&3] - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_compute_ndcg_retrieved_ideal() {
+        let mut qrels = HashMap::new();
+        qrels.insert("high".to_string(), 3);
+        qrels.insert("mid".to_string(), 2);
+        qrels.insert("low".to_string(), 1);
+
+        // The run misses the grade-3 document and misorders the rest
+        let ranked = vec![
+            ("low".to_string(), 0.9),
+            ("mid".to_string(), 0.8),
+            ("none".to_string(), 0.7),
+        ];
+
+        let standard = compute_ndcg(&ranked, &qrels, 3);
+        let retrieved = compute_ndcg_retrieved_ideal(&ranked, &qrels, 3);
+        assert!(retrieved > standard);
+        assert!(retrieved < 1.0);
+
+        let reordered = vec![("mid".to_string(), 0.9), ("low".to_string(), 0.8)];
+        assert!((compute_ndcg_retrieved_ideal(&reordered, &qrels, 3) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_err_and_nerr_at_k() {
+        let ranked = vec![
+            ("doc1".to_string(), 0.9),
+            ("doc2".to_string(), 0.8),
+            ("doc3".to_string(), 0.7),
+        ];
+        let mut qrels = HashMap::new();
+        qrels.insert("doc1".to_string(), 1);
+        qrels.insert("doc3".to_string(), 3);
+        qrels.insert("doc4".to_string(), 2);
+
+        let (err, nerr) = err_and_nerr_at_k(&ranked, &qrels, 10, 3);
+        assert!((err - err_at_k(&ranked, &qrels, 10, 3)).abs() < 1e-12);
+        assert!((nerr - nerr_at_k(&ranked, &qrels, 10, 3)).abs() < 1e-12);
+        assert!(err > 0.0 && err < nerr && nerr < 1.0);
+
+        // No relevant documents: nERR is defined as 0
+        assert_eq!(err_and_nerr_at_k(&ranked, &HashMap::new(), 10, 3), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_compute_ndcg_weighted() {
+        let ranked = vec![
+            ("doc1".to_string(), 0.9),
+            ("doc2".to_string(), 0.8),
+        ];
+        let ids: Vec<String> = ranked.iter().map(|(id, _)| id.clone()).collect();
+
+        let mut qrels = HashMap::new();
+        qrels.insert("doc1".to_string(), 1);
+        qrels.insert("doc2".to_string(), 3);
+
+        // Full confidence matches unweighted nDCG
+        let full: HashMap<String, (u32, f32)> =
+            qrels.iter().map(|(id, &g)| (id.clone(), (g, 1.0))).collect();
+        let full_ndcg = compute_ndcg_weighted(&ids, &full, 10);
+        assert!((full_ndcg - compute_ndcg(&ranked, &qrels, 10)).abs() < 1e-12);
+
+        // Trusting the misranked grade-3 judgment less softens the penalty
+        let mut low = full.clone();
+        low.insert("doc2".to_string(), (3, 0.2));
+        assert!(compute_ndcg_weighted(&ids, &low, 10) > full_ndcg);
+    }
+
+    #[test]
+    fn test_compute_q_measure() {
+        let ranked = vec![
+            ("doc1".to_string(), 0.9),
+            ("doc2".to_string(), 0.8),
+            ("doc3".to_string(), 0.7),
+        ];
+        let mut qrels = HashMap::new();
+        qrels.insert("doc2".to_string(), 1);
+        qrels.insert("doc3".to_string(), 3);
+
+        // beta = 0 reduces to AP: (1/2 + 2/3) / 2
+        let ap = (0.5 + 2.0 / 3.0) / 2.0;
+        assert!((compute_q_measure(&ranked, &qrels, 0.0) - ap).abs() < 1e-9);
+
+        let q = compute_q_measure(&ranked, &qrels, 1.0);
+        assert!(q > 0.0 && q < 1.0);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_graded_metrics_in_unit_range() {
+        let ranked = vec![
+            ("doc1".to_string(), 0.9),
+            ("doc2".to_string(), 0.8),
+            ("doc3".to_string(), 0.7),
+            ("doc4".to_string(), 0.6),
+        ];
+        let mut qrels = HashMap::new();
+        qrels.insert("doc1".to_string(), 1);
+        qrels.insert("doc3".to_string(), 3);
+        qrels.insert("doc9".to_string(), 2);
+
+        let m = GradedMetrics::compute(&ranked, &qrels, 3);
+        for value in [m.ndcg_at_5, m.ndcg_at_10, m.err_at_10, m.graded_map, m.q_measure] {
+            assert!((0.0..=1.0).contains(&value));
+        }
+        assert!(m.ndcg_at_10 > 0.0 && m.q_measure > 0.0);
+        assert_eq!(m.graded_map, graded_average_precision(&ranked, &qrels, 3));
+        assert!(m.graded_map < compute_map(&ranked, &qrels));
+    }
+
+    #[test]
+    fn test_dcg_by_grade_sums_to_dcg() {
+        let ranked = vec![
+            ("doc1".to_string(), 0.9),
+            ("doc2".to_string(), 0.8),
+            ("doc3".to_string(), 0.7),
+            ("doc4".to_string(), 0.6),
+        ];
+        let mut qrels = HashMap::new();
+        qrels.insert("doc1".to_string(), 1);
+        qrels.insert("doc3".to_string(), 2);
+        qrels.insert("doc4".to_string(), 2);
+        qrels.insert("doc9".to_string(), 3);
+
+        let by_grade = dcg_by_grade(&ranked, &qrels, 3);
+        let dcg: f64 = by_grade.values().map(|c| c.0).sum();
+        let idcg: f64 = by_grade.values().map(|c| c.1).sum();
+
+        let expected_dcg = 1.0 + 2.0 / 4f64.log2();
+        assert!((dcg - expected_dcg).abs() < 1e-12);
+        assert!((dcg / idcg - compute_ndcg(&ranked, &qrels, 3)).abs() < 1e-12);
+        assert_eq!(by_grade[&3].0, 0.0);
+    }
+
+    #[test]
+    fn test_compute_ndcg_decayed() {
+        let ranked = vec![
+            ("doc1".to_string(), 0.9),
+            ("doc2".to_string(), 0.8),
+        ];
+        let ids: Vec<String> = ranked.iter().map(|(id, _)| id.clone()).collect();
+        let mut qrels = HashMap::new();
+        qrels.insert("doc1".to_string(), 2);
+        qrels.insert("doc2".to_string(), 1);
+
+        // No decay matches plain nDCG
+        let fresh = compute_ndcg_decayed(&ids, &qrels, &HashMap::new(), 10);
+        assert!((fresh - compute_ndcg(&ranked, &qrels, 10)).abs() < 1e-12);
+
+        let mut decay = HashMap::new();
+        decay.insert("doc1".to_string(), 0.25);
+        assert!(compute_ndcg_decayed(&ids, &qrels, &decay, 10) < fresh);
+    }
+
+    #[test]
+    fn test_expected_precision_recall_at_max_grade_match_binary() {
+        use crate::binary::{precision_at_k, recall_at_k};
+
+        let ranked = vec![
+            ("doc1".to_string(), 0.9),
+            ("doc2".to_string(), 0.8),
+            ("doc3".to_string(), 0.7),
+        ];
+        let mut qrels = HashMap::new();
+        qrels.insert("doc1".to_string(), 3);
+        qrels.insert("doc3".to_string(), 3);
+        qrels.insert("doc5".to_string(), 3);
+
+        let ids: Vec<&String> = ranked.iter().map(|(id, _)| id).collect();
+        let relevant: HashSet<&String> = qrels.keys().collect();
+
+        for k in 1..=3 {
+            let p = expected_precision_at_k(&ranked, &qrels, k, 3);
+            let r = expected_recall_at_k(&ranked, &qrels, k, 3);
+            assert!((p - precision_at_k(&ids, &relevant, k)).abs() < 1e-12);
+            assert!((r - recall_at_k(&ids, &relevant, k)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_time_biased_gain_decreases_with_depth() {
+        let mut qrels = HashMap::new();
+        qrels.insert("rel".to_string(), 1);
+        let ranking = |order: &[&str]| -> Vec<(String, f32)> {
+            order.iter().map(|id| (id.to_string(), 0.0)).collect()
+        };
+        let time = |grade: u32| if grade > 0 { 30.0 } else { 10.0 };
+
+        let top = time_biased_gain(&ranking(&["rel", "a", "b"]), &qrels, time, 1);
+        let middle = time_biased_gain(&ranking(&["a", "rel", "b"]), &qrels, time, 1);
+        let bottom = time_biased_gain(&ranking(&["a", "b", "rel"]), &qrels, time, 1);
+
+        assert_eq!(top, 1.0);
+        assert!(top > middle && middle > bottom);
+        // One 10s summary ahead of it: weight 2^(-10/224)
+        assert!((middle - (-10.0 / TBG_HALF_LIFE_SECS).exp2()).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_compute_ndcg_with_mapper() {
+        let ranked = vec![
+            ("doc1".to_string(), 0.9),
+            ("doc2".to_string(), 0.8),
+            ("doc3".to_string(), 0.7),
+        ];
+        let mut qrels = HashMap::new();
+        qrels.insert("doc1".to_string(), 1);
+        qrels.insert("doc2".to_string(), 1);
+        qrels.insert("doc3".to_string(), 2);
+
+        let only_grade_2 = |grade: u32| if grade == 2 { 1.0 } else { 0.0 };
+        let mapped = compute_ndcg_with_mapper(&ranked, &qrels, 3, only_grade_2);
+        assert!((mapped - 0.5).abs() < 1e-9);
+        assert!((mapped - compute_ndcg(&ranked, &qrels, 3)).abs() > 0.1);
+
+        // The identity mapping is linear gain
+        let identity = compute_ndcg_with_mapper(&ranked, &qrels, 3, |grade| grade as f64);
+        assert_eq!(identity, compute_ndcg(&ranked, &qrels, 3));
+    }
+
+    #[test]
+    fn test_compute_ndcg_with_min_gain_lifts_grade_1() {
+        let ranked = vec![
+            ("low".to_string(), 0.9),
+            ("none".to_string(), 0.8),
+            ("high".to_string(), 0.7),
+        ];
+        let mut qrels = HashMap::new();
+        qrels.insert("low".to_string(), 1);
+        qrels.insert("none".to_string(), 0);
+        qrels.insert("high".to_string(), 3);
+
+        let plain = compute_ndcg(&ranked, &qrels, 3);
+        let floored = compute_ndcg_with_min_gain(&ranked, &qrels, 3, 3.0);
+
+        // With both relevant documents at gain 3, only the rank-2 gap costs
+        let expected = (3.0 + 3.0 / 2.0) / (3.0 + 3.0 / 3f64.log2());
+        assert!((floored - expected).abs() < 1e-9);
+        assert!(floored > plain);
+        assert_eq!(compute_ndcg_with_min_gain(&ranked, &qrels, 3, 1.0), plain);
+    }
+
+    #[test]
+    fn test_err_with_satisfaction_linear_vs_exponential() {
+        let ranked = vec![
+            ("doc1".to_string(), 0.9),
+            ("doc2".to_string(), 0.8),
+            ("doc3".to_string(), 0.7),
+        ];
+        let mut qrels = HashMap::new();
+        qrels.insert("doc1".to_string(), 1);
+        qrels.insert("doc2".to_string(), 2);
+        qrels.insert("doc3".to_string(), 3);
+
+        let max_grade = 3;
+        let exponential = |g: u32| (2f64.powi(g as i32) - 1.0) / 2f64.powi(max_grade as i32);
+        let linear = |g: u32| g as f64 / max_grade as f64;
+
+        // The exponential curve reproduces the default model
+        let default = err_at_k(&ranked, &qrels, 10, max_grade);
+        assert_eq!(err_with_satisfaction(&ranked, &qrels, 10, exponential), default);
+
+        // R = 1/3, 2/3, 1: 1/3 + (2/3)(2/3)/2 + (2/3)(1/3)(1)/3
+        let linear_err = err_with_satisfaction(&ranked, &qrels, 10, linear);
+        let expected = 1.0 / 3.0 + 2.0 / 9.0 + 2.0 / 27.0;
+        assert!((linear_err - expected).abs() < 1e-12);
+        assert!((linear_err - default).abs() > 0.1);
+    }
+}
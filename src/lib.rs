@@ -67,9 +67,9 @@ pub use trec::{TrecRun, Qrel, load_trec_runs, load_qrels, group_runs_by_query, g
 pub use validation::{ValidationError, validate_metric_inputs, validate_persistence, validate_beta};
 pub use batch::{BatchResults, QueryResults, evaluate_batch_binary, evaluate_trec_batch};
 pub use statistics::{TTestResult, paired_t_test, confidence_interval, cohens_d};
-pub use export::{export_to_csv};
+pub use export::{export_to_csv, import_from_csv};
 
 #[cfg(feature = "serde")]
 pub use binary::Metrics;
 #[cfg(feature = "serde")]
-pub use export::export_to_json;
+pub use export::{export_to_json, import_from_json};
@@ -1,5 +1,15 @@
 //! Statistical testing utilities for evaluation results.
 
+use crate::batch::{evaluate_trec_batch, BatchResults};
+use crate::trec::{Qrel, TrecRun};
+use std::collections::{HashMap, HashSet};
+
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Default minimum number of queries below which significance tests warn.
+pub const MIN_RECOMMENDED_SAMPLE_SIZE: usize = 20;
+
 /// Result of a paired t-test.
 #[derive(Debug, Clone)]
 pub struct TTestResult {
@@ -9,6 +19,19 @@ pub struct TTestResult {
     pub mean_difference: f64,
     pub std_error: f64,
     pub significant: bool, // p < 0.05
+    pub warnings: Vec<String>, // e.g. sample size too small for a reliable test
+}
+
+/// Warnings for a significance test run on `n` paired observations.
+fn sample_size_warnings(n: usize, min_sample_size: usize) -> Vec<String> {
+    if n < min_sample_size {
+        vec![format!(
+            "Small sample: {} queries (at least {} recommended); results may be unreliable",
+            n, min_sample_size
+        )]
+    } else {
+        Vec::new()
+    }
 }
 
 /// Perform a paired t-test on two sets of scores.
@@ -23,7 +46,8 @@ pub struct TTestResult {
 ///
 /// # Returns
 ///
-/// `TTestResult` with t-statistic, p-value, and significance.
+/// `TTestResult` with t-statistic, p-value, and significance. `warnings` is
+/// populated when fewer than [`MIN_RECOMMENDED_SAMPLE_SIZE`] pairs are given.
 ///
 /// # Example
 ///
@@ -37,6 +61,31 @@ pub struct TTestResult {
 /// println!("t-statistic: {}, p-value: {}", result.t_statistic, result.p_value);
 /// ```
 pub fn paired_t_test(method_a: &[f64], method_b: &[f64], alpha: f64) -> TTestResult {
+    paired_t_test_with_min_sample(method_a, method_b, alpha, MIN_RECOMMENDED_SAMPLE_SIZE)
+}
+
+/// Perform a paired t-test, warning when fewer than `min_sample_size` pairs are given.
+///
+/// Identical to [`paired_t_test`] but with a configurable sample-size threshold
+/// for the small-sample warning.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::paired_t_test_with_min_sample;
+///
+/// let method_a = vec![0.5, 0.6, 0.7];
+/// let method_b = vec![0.4, 0.5, 0.6];
+///
+/// let result = paired_t_test_with_min_sample(&method_a, &method_b, 0.05, 3);
+/// assert!(result.warnings.is_empty());
+/// ```
+pub fn paired_t_test_with_min_sample(
+    method_a: &[f64],
+    method_b: &[f64],
+    alpha: f64,
+    min_sample_size: usize,
+) -> TTestResult {
     assert_eq!(
         method_a.len(),
         method_b.len(),
@@ -51,6 +100,7 @@ pub fn paired_t_test(method_a: &[f64], method_b: &[f64], alpha: f64) -> TTestRes
             mean_difference: 0.0,
             std_error: 0.0,
             significant: false,
+            warnings: sample_size_warnings(method_a.len(), min_sample_size),
         };
     }
 
@@ -103,7 +153,165 @@ pub fn paired_t_test(method_a: &[f64], method_b: &[f64], alpha: f64) -> TTestRes
         mean_difference: mean_diff,
         std_error,
         significant: p_value < alpha,
+        warnings: sample_size_warnings(differences.len(), min_sample_size),
+    }
+}
+
+/// Per-query values of `metric` for the queries both result sets report it for,
+/// paired by query ID in the order of `a`.
+pub(crate) fn paired_metric_values(
+    a: &BatchResults,
+    b: &BatchResults,
+    metric: &str,
+) -> (Vec<f64>, Vec<f64>) {
+    let b_values: HashMap<&str, f64> = b
+        .query_results
+        .iter()
+        .filter_map(|qr| qr.metrics.get(metric).map(|v| (qr.query_id.as_str(), *v)))
+        .collect();
+
+    let mut a_scores = Vec::new();
+    let mut b_scores = Vec::new();
+    for qr in &a.query_results {
+        if let (Some(&a_value), Some(&b_value)) =
+            (qr.metrics.get(metric), b_values.get(qr.query_id.as_str()))
+        {
+            a_scores.push(a_value);
+            b_scores.push(b_value);
+        }
+    }
+
+    (a_scores, b_scores)
+}
+
+/// Run paired t-tests for every system against a baseline, for every metric.
+///
+/// Per-query values are paired by `query_id`; queries missing from either
+/// system (or lacking the metric) are left out of that comparison. The
+/// baseline itself is not compared against itself. Tests use `alpha = 0.05`.
+///
+/// With the `rayon` feature enabled, the (system, metric) comparisons run in
+/// parallel.
+///
+/// # Arguments
+///
+/// * `systems` - Batch results keyed by system name (including the baseline)
+/// * `baseline` - Name of the baseline system in `systems`
+/// * `metrics` - Metric names to compare
+///
+/// # Returns
+///
+/// Map from `(system, metric)` to the paired t-test of system vs. baseline.
+/// Empty if `baseline` is not in `systems`.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::{HashMap, HashSet};
+/// use rank_eval::batch::evaluate_batch_binary;
+/// use rank_eval::statistics::compare_all;
+///
+/// let qrels = vec![["doc1"].into_iter().collect::<HashSet<_>>(); 3];
+/// let base = evaluate_batch_binary(&vec![vec!["doc2", "doc1"]; 3], &qrels, &["mrr"]);
+/// let new = evaluate_batch_binary(&vec![vec!["doc1", "doc2"]; 3], &qrels, &["mrr"]);
+///
+/// let mut systems = HashMap::new();
+/// systems.insert("base".to_string(), base);
+/// systems.insert("new".to_string(), new);
+///
+/// let tests = compare_all(&systems, "base", &["mrr"]);
+/// assert!(tests.contains_key(&("new".to_string(), "mrr".to_string())));
+/// ```
+pub fn compare_all(
+    systems: &HashMap<String, BatchResults>,
+    baseline: &str,
+    metrics: &[&str],
+) -> HashMap<(String, String), TTestResult> {
+    let base = match systems.get(baseline) {
+        Some(base) => base,
+        None => return HashMap::new(),
+    };
+
+    let jobs: Vec<(&String, &BatchResults, &str)> = systems
+        .iter()
+        .filter(|(name, _)| name.as_str() != baseline)
+        .flat_map(|(name, results)| metrics.iter().map(move |m| (name, results, *m)))
+        .collect();
+
+    let compare = |(name, results, metric): (&String, &BatchResults, &str)| {
+        let (system_scores, base_scores) = paired_metric_values(results, base, metric);
+
+        (
+            (name.clone(), metric.to_string()),
+            paired_t_test(&system_scores, &base_scores, 0.05),
+        )
+    };
+
+    #[cfg(feature = "rayon")]
+    let comparisons = jobs.into_par_iter().map(compare).collect();
+    #[cfg(not(feature = "rayon"))]
+    let comparisons = jobs.into_iter().map(compare).collect();
+
+    comparisons
+}
+
+/// Standard error of the mean of a set of scores.
+///
+/// Formula: `SE = s / √n`, where `s` is the sample standard deviation
+/// (`n - 1` denominator).
+///
+/// Applied to per-query AP values this is the analytic standard error of MAP,
+/// and likewise for any other metric's mean; it is the spread that
+/// [`confidence_interval`] scales by its critical value. Returns NaN if fewer
+/// than 2 scores are given, since the sample standard deviation is undefined.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::standard_error;
+///
+/// // Mean 0.5, sample variance 0.025
+/// let ap = vec![0.3, 0.4, 0.5, 0.6, 0.7];
+/// assert!((standard_error(&ap) - (0.025f64 / 5.0).sqrt()).abs() < 1e-12);
+/// ```
+pub fn standard_error(scores: &[f64]) -> f64 {
+    if scores.len() < 2 {
+        return f64::NAN;
     }
+
+    let n = scores.len() as f64;
+    let mean = scores.iter().sum::<f64>() / n;
+    let variance = scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.0);
+
+    variance.sqrt() / n.sqrt()
+}
+
+/// Mean of the lowest-scoring fraction of queries.
+///
+/// Sorts `scores` ascending and averages the bottom `⌈fraction · n⌉` values
+/// (at least one), so a system that wins on average but collapses on its
+/// hardest queries shows up. `fraction = 1.0` is the plain mean. Returns NaN
+/// if `scores` is empty or `fraction` is not in `(0, 1]`.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::tail_mean;
+///
+/// let ndcg = vec![0.9, 0.1, 0.8, 0.3, 0.7, 0.6, 0.5, 0.4];
+/// // Bottom quarter: 0.1 and 0.3
+/// assert!((tail_mean(&ndcg, 0.25) - 0.2).abs() < 1e-12);
+/// ```
+pub fn tail_mean(scores: &[f64], fraction: f64) -> f64 {
+    if scores.is_empty() || !(fraction > 0.0 && fraction <= 1.0) {
+        return f64::NAN;
+    }
+
+    let mut sorted = scores.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let count = ((fraction * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+
+    sorted[..count].iter().sum::<f64>() / count as f64
 }
 
 /// Compute confidence interval for a set of scores.
@@ -127,29 +335,261 @@ pub fn paired_t_test(method_a: &[f64], method_b: &[f64], alpha: f64) -> TTestRes
 /// println!("95% CI: [{:.3}, {:.3}]", lower, upper);
 /// ```
 pub fn confidence_interval(scores: &[f64], confidence: f64) -> (f64, f64) {
+    confidence_interval_with_dist(scores, confidence, CIDist::Normal)
+}
+
+/// Sampling distribution used for the critical value of a confidence interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CIDist {
+    /// Standard normal z-quantile (appropriate for large samples).
+    #[default]
+    Normal,
+    /// Student's t-quantile with `n - 1` degrees of freedom.
+    StudentT,
+}
+
+/// Compute a confidence interval using the given sampling distribution.
+///
+/// Identical to [`confidence_interval`] but with a choice of critical value.
+/// `CIDist::StudentT` gives correct coverage for small query sets, where the
+/// normal interval is too narrow.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::{confidence_interval_with_dist, CIDist};
+///
+/// let scores = vec![0.5, 0.6, 0.7, 0.8, 0.9];
+/// let (z_lower, z_upper) = confidence_interval_with_dist(&scores, 0.95, CIDist::Normal);
+/// let (t_lower, t_upper) = confidence_interval_with_dist(&scores, 0.95, CIDist::StudentT);
+/// assert!(t_upper - t_lower > z_upper - z_lower);
+/// ```
+pub fn confidence_interval_with_dist(
+    scores: &[f64],
+    confidence: f64,
+    distribution: CIDist,
+) -> (f64, f64) {
     if scores.is_empty() {
         return (0.0, 0.0);
     }
 
     let mean = scores.iter().sum::<f64>() / scores.len() as f64;
-    let variance = scores
-        .iter()
-        .map(|s| (s - mean).powi(2))
-        .sum::<f64>()
-        / (scores.len() - 1) as f64;
-    let std_dev = variance.sqrt();
+    let se = standard_error(scores);
 
-    // Standard error
-    let se = std_dev / (scores.len() as f64).sqrt();
-
-    // z-score for confidence level (using normal approximation)
+    // Critical value for confidence level
     let alpha = 1.0 - confidence;
-    let z = normal_quantile(1.0 - alpha / 2.0);
+    let critical = match distribution {
+        CIDist::Normal => normal_quantile(1.0 - alpha / 2.0),
+        CIDist::StudentT => student_t_quantile(1.0 - alpha / 2.0, scores.len() - 1),
+    };
 
-    let margin = z * se;
+    let margin = critical * se;
     (mean - margin, mean + margin)
 }
 
+/// Minimum uniform improvement detectable with a paired test at the given power.
+///
+/// Formula: `MDE = (z₁₋α/₂ + z_power) × σ / √n`
+///
+/// Where `σ` is the sample standard deviation of `scores` (used as the spread of
+/// per-query differences) and `n` is the number of queries. Uses a normal
+/// approximation, so it is optimistic for small query sets.
+///
+/// Returns `f64::INFINITY` if fewer than 2 scores are given.
+///
+/// # Arguments
+///
+/// * `scores` - Per-query scores (or per-query differences from a pilot comparison)
+/// * `alpha` - Two-sided significance level (e.g., 0.05)
+/// * `power` - Desired statistical power (e.g., 0.8)
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::min_detectable_effect;
+///
+/// let scores = vec![0.4, 0.5, 0.6, 0.5, 0.45, 0.55];
+/// let mde = min_detectable_effect(&scores, 0.05, 0.8);
+/// assert!(mde > 0.0);
+/// ```
+pub fn min_detectable_effect(scores: &[f64], alpha: f64, power: f64) -> f64 {
+    if scores.len() < 2 {
+        return f64::INFINITY;
+    }
+
+    let n = scores.len() as f64;
+    let mean = scores.iter().sum::<f64>() / n;
+    let variance = scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.0);
+
+    let z_alpha = normal_quantile(1.0 - alpha / 2.0);
+    let z_power = normal_quantile(power);
+
+    (z_alpha + z_power) * variance.sqrt() / n.sqrt()
+}
+
+/// Compute the `p`-th quantile of a set of values (0.0 ≤ p ≤ 1.0).
+///
+/// Linearly interpolates between the two nearest order statistics (the same
+/// convention as NumPy's default), so `percentile(v, 0.5)` is the median.
+/// Returns 0.0 for empty input.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::percentile;
+///
+/// let values = vec![0.4, 0.1, 0.3, 0.2];
+/// assert!((percentile(&values, 0.5) - 0.25).abs() < 1e-9);
+/// ```
+pub fn percentile(values: &[f64], p: f64) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let position = p.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    let fraction = position - lower as f64;
+
+    sorted[lower] + (sorted[upper] - sorted[lower]) * fraction
+}
+
+/// Summary of a bootstrap distribution of the mean.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BootstrapSummary {
+    /// Mean of the bootstrap means.
+    pub mean: f64,
+    /// Standard deviation of the bootstrap means (the standard error).
+    pub std: f64,
+    /// Lower bound of the percentile confidence interval.
+    pub lower: f64,
+    /// Upper bound of the percentile confidence interval.
+    pub upper: f64,
+    /// Warnings such as too few scores for a reliable interval.
+    pub warnings: Vec<String>,
+}
+
+/// Bootstrap the mean of `scores` and summarize it in a single resampling pass.
+///
+/// Draws `n_resamples` resamples of `scores` with replacement (driven by
+/// `rng`) and records each resample's mean. The mean and std are accumulated
+/// during the same loop that collects the means for the percentile interval
+/// `[(1 - confidence)/2, (1 + confidence)/2]`, so a full report needs no second
+/// pass.
+///
+/// Returns all zeros if `scores` is empty or `n_resamples` is 0. `warnings`
+/// is populated when fewer than [`MIN_RECOMMENDED_SAMPLE_SIZE`] scores are
+/// given, as for [`paired_t_test`].
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::{bootstrap_summary, RngConfig};
+///
+/// let scores = vec![0.2, 0.4, 0.6, 0.8];
+/// let summary = bootstrap_summary(&scores, 1000, 0.95, &RngConfig::default());
+/// assert!(summary.lower <= 0.5 && 0.5 <= summary.upper);
+/// ```
+pub fn bootstrap_summary(
+    scores: &[f64],
+    n_resamples: usize,
+    confidence: f64,
+    rng: &RngConfig,
+) -> BootstrapSummary {
+    let warnings = sample_size_warnings(scores.len(), MIN_RECOMMENDED_SAMPLE_SIZE);
+    if scores.is_empty() || n_resamples == 0 {
+        return BootstrapSummary {
+            mean: 0.0,
+            std: 0.0,
+            lower: 0.0,
+            upper: 0.0,
+            warnings,
+        };
+    }
+
+    let mut rng = Rng::new(rng);
+    let n = scores.len();
+    let mut means = Vec::with_capacity(n_resamples);
+    let mut sum = 0.0;
+    let mut sum_sq = 0.0;
+
+    for _ in 0..n_resamples {
+        let resample_mean = (0..n).map(|_| scores[rng.next_below(n)]).sum::<f64>() / n as f64;
+        sum += resample_mean;
+        sum_sq += resample_mean * resample_mean;
+        means.push(resample_mean);
+    }
+
+    let mean = sum / n_resamples as f64;
+    let std = if n_resamples > 1 {
+        ((sum_sq - n_resamples as f64 * mean * mean) / (n_resamples - 1) as f64)
+            .max(0.0)
+            .sqrt()
+    } else {
+        0.0
+    };
+    let tail = (1.0 - confidence.clamp(0.0, 1.0)) / 2.0;
+
+    BootstrapSummary {
+        mean,
+        std,
+        lower: percentile(&means, tail),
+        upper: percentile(&means, 1.0 - tail),
+        warnings,
+    }
+}
+
+/// Geometric mean average precision (GMAP).
+///
+/// Formula: `GMAP = exp((1/n) × Σ_q log(AP_q + ε))`
+///
+/// The geometric mean emphasizes poorly performing queries; `epsilon` keeps
+/// queries with AP = 0 from sending the mean to zero. Returns 0.0 for empty input.
+///
+/// Reference: Robertson (2006) "On GMAP: and other transformations"
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::gmap;
+///
+/// let gm = gmap(&[0.25, 1.0], 0.0);
+/// assert!((gm - 0.5).abs() < 1e-9);
+/// ```
+pub fn gmap(ap_scores: &[f64], epsilon: f64) -> f64 {
+    if ap_scores.is_empty() {
+        return 0.0;
+    }
+    let log_sum: f64 = ap_scores.iter().map(|ap| (ap + epsilon).ln()).sum();
+    (log_sum / ap_scores.len() as f64).exp()
+}
+
+/// Per-query additive contributions to GMAP, in input order.
+///
+/// Each query contributes `log(AP_q + ε)`; the most negative contributions
+/// are the hard queries dragging GMAP down. Dividing the sum of contributions
+/// by `n` and exponentiating reproduces [`gmap`].
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::{gmap, gmap_contributions};
+///
+/// let ap = vec![("q1".to_string(), 0.8), ("q2".to_string(), 0.01)];
+/// let contributions = gmap_contributions(&ap, 1e-5);
+/// let total: f64 = contributions.iter().map(|(_, c)| c).sum();
+/// assert!(((total / 2.0).exp() - gmap(&[0.8, 0.01], 1e-5)).abs() < 1e-9);
+/// ```
+pub fn gmap_contributions(ap_scores: &[(String, f64)], epsilon: f64) -> Vec<(String, f64)> {
+    ap_scores
+        .iter()
+        .map(|(query_id, ap)| (query_id.clone(), (ap + epsilon).ln()))
+        .collect()
+}
+
 /// Compute Cohen's d effect size.
 ///
 /// Measures the standardized difference between two means.
@@ -199,61 +639,975 @@ pub fn cohens_d(method_a: &[f64], method_b: &[f64]) -> f64 {
     (mean_a - mean_b) / pooled_std
 }
 
-/// Normal CDF approximation (using error function).
-fn normal_cdf(x: f64) -> f64 {
-    0.5 * (1.0 + erf(x / (2.0_f64).sqrt()))
-}
-
-/// Error function approximation.
-fn erf(x: f64) -> f64 {
-    // Abramowitz and Stegun approximation
-    let a1 = 0.254829592;
-    let a2 = -0.284496736;
-    let a3 = 1.421413741;
-    let a4 = -1.453152027;
-    let a5 = 1.061405429;
-    let p = 0.3275911;
+/// Fraction of queries on which two metrics agree about which system is better.
+///
+/// For each query i, metric A's difference `a_sys1[i] - a_sys2[i]` and metric
+/// B's difference `b_sys1[i] - b_sys2[i]` are compared by sign; a tie counts as
+/// its own sign, so it agrees only with another tie. System-level concordance
+/// can hide metrics that disagree on many individual queries but balance out
+/// in the mean.
+///
+/// Returns 0.0 for empty input.
+///
+/// # Panics
+///
+/// Panics if the four slices do not have the same length.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::per_query_agreement;
+///
+/// let a_sys1 = vec![0.8, 0.4];
+/// let a_sys2 = vec![0.6, 0.5];
+/// let b_sys1 = vec![0.9, 0.7];
+/// let b_sys2 = vec![0.1, 0.2];
+///
+/// // Both metrics prefer system 1 on the first query; only B does on the second
+/// assert_eq!(per_query_agreement(&a_sys1, &a_sys2, &b_sys1, &b_sys2), 0.5);
+/// ```
+pub fn per_query_agreement(a_sys1: &[f64], a_sys2: &[f64], b_sys1: &[f64], b_sys2: &[f64]) -> f64 {
+    let n = a_sys1.len();
+    assert!(
+        a_sys2.len() == n && b_sys1.len() == n && b_sys2.len() == n,
+        "all per-query score vectors must have same length"
+    );
 
-    let sign = if x < 0.0 { -1.0 } else { 1.0 };
-    let x = x.abs();
+    if n == 0 {
+        return 0.0;
+    }
 
-    let t = 1.0 / (1.0 + p * x);
-    let y = 1.0 - (((((a5 * t + a4) * t) + a3) * t + a2) * t + a1) * t * (-x * x).exp();
+    let agreeing = (0..n)
+        .filter(|&i| {
+            let a = (a_sys1[i] - a_sys2[i]).partial_cmp(&0.0);
+            let b = (b_sys1[i] - b_sys2[i]).partial_cmp(&0.0);
+            a == b
+        })
+        .count();
 
-    sign * y
+    agreeing as f64 / n as f64
 }
 
-/// Normal quantile (inverse CDF) approximation.
-fn normal_quantile(p: f64) -> f64 {
-    // Beasley-Springer-Moro algorithm approximation
-    if p < 0.5 {
-        -normal_quantile(1.0 - p)
-    } else if p > 0.5 {
-        let t = (-2.0 * (1.0 - p).ln()).sqrt();
-        t - (2.515517 + 0.802853 * t + 0.010328 * t * t) / (1.0 + 1.432788 * t + 0.189269 * t * t + 0.001308 * t * t * t)
-    } else {
-        0.0
-    }
-}
+/// Split the total per-query difference between two systems into wins and losses.
+///
+/// With `δᵢ = a[i] - b[i]`, returns `(Σ δᵢ over δᵢ > 0, Σ δᵢ over δᵢ < 0)`; the
+/// second value is ≤ 0 and the two add up to `n × (mean(a) - mean(b))`. The
+/// same mean improvement can come from a few large wins or from avoiding
+/// losses, and this shows which.
+///
+/// # Panics
+///
+/// Panics if `a` and `b` do not have the same length.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::win_magnitude_breakdown;
+///
+/// let a = vec![0.9, 0.5, 0.25];
+/// let b = vec![0.4, 0.5, 0.5];
+///
+/// assert_eq!(win_magnitude_breakdown(&a, &b), (0.5, -0.25));
+/// ```
+pub fn win_magnitude_breakdown(a: &[f64], b: &[f64]) -> (f64, f64) {
+    assert_eq!(a.len(), b.len(), "a and b must have same length");
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| x - y)
+        .fold((0.0, 0.0), |(wins, losses), delta| {
+            if delta > 0.0 {
+                (wins + delta, losses)
+            } else {
+                (wins, losses + delta.min(0.0))
+            }
+        })
+}
 
-    #[test]
-    fn test_paired_t_test() {
-        // Use data with variance in differences to get non-zero t-statistic
-        let method_a = vec![0.5, 0.6, 0.7, 0.8, 0.9];
-        let method_b = vec![0.4, 0.55, 0.6, 0.75, 0.8];
+/// Per-query regret of a chosen system relative to the best system.
+///
+/// `systems` maps system name -> query ID -> metric value. For each query the
+/// chosen system has a value for, regret is the best value any system reaches
+/// on that query minus the chosen system's value, so it is never negative.
+/// The sum over queries is the upside of routing each query to its best
+/// system; an oracle router has zero total regret.
+///
+/// Returns an empty map if `chosen` is not in `systems`.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::statistics::per_query_regret;
+///
+/// let mut systems: HashMap<String, HashMap<String, f64>> = HashMap::new();
+/// systems.insert("bm25".into(), [("q1".into(), 0.5), ("q2".into(), 0.75)].into());
+/// systems.insert("dense".into(), [("q1".into(), 0.75), ("q2".into(), 0.25)].into());
+///
+/// let regret = per_query_regret(&systems, "bm25");
+/// assert_eq!(regret["q1"], 0.25);
+/// assert_eq!(regret["q2"], 0.0);
+/// ```
+pub fn per_query_regret(
+    systems: &HashMap<String, HashMap<String, f64>>,
+    chosen: &str,
+) -> HashMap<String, f64> {
+    let Some(chosen_values) = systems.get(chosen) else {
+        return HashMap::new();
+    };
 
-        let result = paired_t_test(&method_a, &method_b, 0.05);
-        // t-statistic can be positive or negative depending on which method is better
-        assert!(result.t_statistic.abs() >= 0.0 || result.t_statistic == 0.0);
-        assert!(result.p_value >= 0.0 && result.p_value <= 1.0);
-        assert_eq!(result.degrees_of_freedom, 4);
-    }
+    chosen_values
+        .iter()
+        .map(|(query_id, &value)| {
+            let best = systems
+                .values()
+                .filter_map(|per_query| per_query.get(query_id))
+                .fold(value, |best, &other| best.max(other));
+            (query_id.clone(), best - value)
+        })
+        .collect()
+}
 
-    #[test]
+/// Detect score outliers within each (query, run tag) group using z-scores.
+///
+/// A document is flagged when its score lies more than `z_threshold` sample
+/// standard deviations from the mean score of its group. Groups with fewer
+/// than two entries or with constant scores are never flagged.
+///
+/// # Arguments
+///
+/// * `runs` - TREC run entries
+/// * `z_threshold` - Number of standard deviations beyond which a score is an outlier
+///
+/// # Returns
+///
+/// `(query_id, run_tag, doc_id)` triples for each outlier, sorted.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::score_outliers;
+/// use rank_eval::trec::TrecRun;
+///
+/// let mut runs: Vec<TrecRun> = (0..10)
+///     .map(|i| TrecRun {
+///         query_id: "1".to_string(),
+///         doc_id: format!("doc{}", i),
+///         rank: i + 1,
+///         score: 0.5,
+///         run_tag: "bm25".to_string(),
+///     })
+///     .collect();
+/// runs[0].score = 100.0;
+///
+/// let outliers = score_outliers(&runs, 2.0);
+/// assert_eq!(outliers[0].2, "doc0");
+/// ```
+pub fn score_outliers(runs: &[TrecRun], z_threshold: f64) -> Vec<(String, String, String)> {
+    let mut groups: HashMap<(&str, &str), Vec<&TrecRun>> = HashMap::new();
+    for run in runs {
+        groups
+            .entry((run.query_id.as_str(), run.run_tag.as_str()))
+            .or_default()
+            .push(run);
+    }
+
+    let mut outliers = Vec::new();
+    for ((query_id, run_tag), entries) in &groups {
+        if entries.len() < 2 {
+            continue;
+        }
+
+        let n = entries.len() as f64;
+        let mean = entries.iter().map(|r| r.score as f64).sum::<f64>() / n;
+        let variance = entries
+            .iter()
+            .map(|r| (r.score as f64 - mean).powi(2))
+            .sum::<f64>()
+            / (n - 1.0);
+        let std_dev = variance.sqrt();
+        if std_dev < 1e-10 {
+            continue;
+        }
+
+        for run in entries {
+            let z = (run.score as f64 - mean) / std_dev;
+            if z.abs() > z_threshold {
+                outliers.push((
+                    query_id.to_string(),
+                    run_tag.to_string(),
+                    run.doc_id.clone(),
+                ));
+            }
+        }
+    }
+
+    outliers.sort();
+    outliers
+}
+
+/// Minimal number of adjacent swaps needed to move all relevant documents to the top.
+///
+/// This is the Kendall distance between `ranked` and its ideal reordering
+/// (relevant documents first, relative order otherwise preserved): the number
+/// of (non-relevant, relevant) pairs in which the non-relevant document is
+/// ranked higher. Unlike nDCG it applies no positional discounting.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashSet;
+/// use rank_eval::statistics::swaps_to_ideal;
+///
+/// let ranked = vec!["doc1", "doc2", "doc3"];
+/// let relevant: HashSet<_> = ["doc3"].into_iter().collect();
+///
+/// assert_eq!(swaps_to_ideal(&ranked, &relevant), 2);
+/// ```
+pub fn swaps_to_ideal<I: Eq + std::hash::Hash>(ranked: &[I], relevant: &HashSet<I>) -> usize {
+    let mut non_relevant_seen = 0;
+    let mut swaps = 0;
+
+    for id in ranked {
+        if relevant.contains(id) {
+            swaps += non_relevant_seen;
+        } else {
+            non_relevant_seen += 1;
+        }
+    }
+
+    swaps
+}
+
+/// nDCG@k of a system ranking against a reference ranking, without qrels.
+///
+/// The reference's top k documents are treated as graded judgments: the
+/// document at reference rank r (1-based) gets grade `k - r + 1`, so the
+/// reference's first document has the highest grade and documents outside
+/// its top k have grade 0. The system is then scored with linear-gain nDCG@k,
+/// whose ideal ranking is the reference itself.
+///
+/// Returns 1.0 when the system's top k matches the reference's, and 0.0 if
+/// `k` is 0 or the reference is empty.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::ranking_similarity_ndcg;
+///
+/// let reference = ["a", "b", "c"];
+/// assert_eq!(ranking_similarity_ndcg(&reference, &reference, 3), 1.0);
+///
+/// let swapped = ranking_similarity_ndcg(&["b", "a", "c"], &reference, 3);
+/// assert!(swapped > 0.9 && swapped < 1.0);
+/// ```
+pub fn ranking_similarity_ndcg<I: Eq + std::hash::Hash>(
+    system: &[I],
+    reference: &[I],
+    k: usize,
+) -> f64 {
+    let mut grades: HashMap<&I, usize> = HashMap::new();
+    for (rank, id) in reference.iter().take(k).enumerate() {
+        // Keep the grade of a document's first (best) reference position
+        grades.entry(id).or_insert(k - rank);
+    }
+
+    let dcg = |grades_in_order: &mut dyn Iterator<Item = usize>| -> f64 {
+        grades_in_order
+            .take(k)
+            .enumerate()
+            .map(|(i, grade)| grade as f64 / ((i + 2) as f64).log2())
+            .sum()
+    };
+
+    let mut ideal_grades: Vec<usize> = grades.values().copied().collect();
+    ideal_grades.sort_unstable_by(|a, b| b.cmp(a));
+    let ideal = dcg(&mut ideal_grades.into_iter());
+    if ideal == 0.0 {
+        return 0.0;
+    }
+
+    // A document repeated in the system ranking only earns its grade once
+    let mut seen = HashSet::new();
+    let mut system_grades = system.iter().map(|id| {
+        if seen.insert(id) {
+            grades.get(id).copied().unwrap_or(0)
+        } else {
+            0
+        }
+    });
+    dcg(&mut system_grades) / ideal
+}
+
+/// Per-query rank of the first relevant document in two runs.
+///
+/// For every query with at least one relevant judgment, returns
+/// `(query_id, rank_in_a, rank_in_b)` where each rank is 1-based and `None`
+/// if that run retrieved no relevant document (or nothing at all) for the
+/// query. Comparing the two ranks shows which queries drive an MRR change.
+/// When a run file holds several tags, the lexicographically smallest tag is
+/// used for each query. Rows are sorted by query ID.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::first_relevant_rank_deltas;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let a = RunBuilder::new().add("1", "doc2", 0.9, "a").add("1", "doc1", 0.8, "a").build();
+/// let b = RunBuilder::new().add("1", "doc1", 0.9, "b").build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).build();
+///
+/// let deltas = first_relevant_rank_deltas(&a, &b, &qrels);
+/// assert_eq!(deltas, vec![("1".to_string(), Some(2), Some(1))]);
+/// ```
+pub fn first_relevant_rank_deltas(
+    a_runs: &[TrecRun],
+    b_runs: &[TrecRun],
+    qrels: &[Qrel],
+) -> Vec<(String, Option<usize>, Option<usize>)> {
+    use crate::binary::first_relevant_rank;
+    use crate::trec::{first_tag_run, group_qrels_by_query, group_runs_by_query};
+
+    let a_by_query = group_runs_by_query(a_runs);
+    let b_by_query = group_runs_by_query(b_runs);
+
+    // Smallest run tag for the query, if it was retrieved at all
+    let rank_in = |query_runs: Option<&HashMap<String, Vec<(String, f32)>>>,
+                   relevant: &HashSet<&String>| {
+        let ranked: Vec<&String> = first_tag_run(query_runs?)?
+            .iter()
+            .map(|(id, _)| id)
+            .collect();
+        first_relevant_rank(&ranked, relevant)
+    };
+
+    let mut deltas: Vec<(String, Option<usize>, Option<usize>)> = group_qrels_by_query(qrels)
+        .iter()
+        .filter_map(|(query_id, query_qrels)| {
+            let relevant: HashSet<&String> = query_qrels
+                .iter()
+                .filter(|(_, &rel)| rel > 0)
+                .map(|(id, _)| id)
+                .collect();
+            if relevant.is_empty() {
+                return None;
+            }
+            Some((
+                query_id.clone(),
+                rank_in(a_by_query.get(query_id), &relevant),
+                rank_in(b_by_query.get(query_id), &relevant),
+            ))
+        })
+        .collect();
+    deltas.sort_by(|x, y| x.0.cmp(&y.0));
+
+    deltas
+}
+
+/// Count relevant documents that moved up, down, or stayed between two runs.
+///
+/// For every query with at least one relevant judgment (grade > 0), each
+/// relevant document's 1-based rank in `a_runs` is compared with its rank in
+/// `b_runs` (ordered by score, using the lexicographically smallest run tag
+/// when a file holds several). A document only
+/// one run retrieves counts as moving toward that run: retrieved only by `b`
+/// is moved up, only by `a` moved down. Relevant documents neither run
+/// retrieves are not counted.
+///
+/// Returns query_id -> `(moved_up, moved_down, unchanged)`, where "up" means
+/// a better (smaller) rank in `b` than in `a`.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::relevant_movement;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let a = RunBuilder::new().add("1", "doc2", 0.9, "a").add("1", "doc1", 0.8, "a").build();
+/// let b = RunBuilder::new().add("1", "doc1", 0.9, "b").add("1", "doc2", 0.8, "b").build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).add("1", "doc2", 1).build();
+///
+/// assert_eq!(relevant_movement(&a, &b, &qrels)["1"], (1, 1, 0));
+/// ```
+pub fn relevant_movement(
+    a_runs: &[TrecRun],
+    b_runs: &[TrecRun],
+    qrels: &[Qrel],
+) -> HashMap<String, (usize, usize, usize)> {
+    use crate::trec::{first_tag_run, group_qrels_by_query, group_runs_by_query};
+
+    let a_by_query = group_runs_by_query(a_runs);
+    let b_by_query = group_runs_by_query(b_runs);
+
+    // 1-based ranks in the smallest run tag for the query
+    let ranks_in = |query_runs: Option<&HashMap<String, Vec<(String, f32)>>>| {
+        let mut ranks: HashMap<String, usize> = HashMap::new();
+        if let Some(ranked) = query_runs.and_then(first_tag_run) {
+            for (i, (doc_id, _)) in ranked.iter().enumerate() {
+                ranks.entry(doc_id.clone()).or_insert(i + 1);
+            }
+        }
+        ranks
+    };
+
+    let mut movement = HashMap::new();
+    for (query_id, query_qrels) in group_qrels_by_query(qrels) {
+        let relevant: Vec<&String> = query_qrels
+            .iter()
+            .filter(|(_, &rel)| rel > 0)
+            .map(|(id, _)| id)
+            .collect();
+        if relevant.is_empty() {
+            continue;
+        }
+
+        let a_ranks = ranks_in(a_by_query.get(&query_id));
+        let b_ranks = ranks_in(b_by_query.get(&query_id));
+
+        let mut counts = (0, 0, 0);
+        for doc_id in relevant {
+            match (a_ranks.get(doc_id), b_ranks.get(doc_id)) {
+                (Some(a), Some(b)) if b < a => counts.0 += 1,
+                (Some(a), Some(b)) if b > a => counts.1 += 1,
+                (Some(_), Some(_)) => counts.2 += 1,
+                (None, Some(_)) => counts.0 += 1,
+                (Some(_), None) => counts.1 += 1,
+                (None, None) => {}
+            }
+        }
+        movement.insert(query_id, counts);
+    }
+
+    movement
+}
+
+/// Reliability-diagram bins of run scores against observed relevance.
+///
+/// Scores are min-max normalized to [0, 1] over all of `runs`, then split into
+/// `n_bins` equal-width bins (the last bin includes 1.0). A document counts as
+/// relevant if it has grade > 0 in `qrels` for its query; unjudged documents
+/// count as non-relevant. For calibrated scores, each bin's relevance rate is
+/// close to its mean score.
+///
+/// # Returns
+///
+/// `(mean normalized score, observed relevance rate, count)` for each
+/// non-empty bin, in ascending score order. Empty if `runs` is empty or
+/// `n_bins` is 0.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::score_calibration;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new()
+///     .add("1", "doc1", 10.0, "run")
+///     .add("1", "doc2", 0.0, "run")
+///     .build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).build();
+///
+/// let bins = score_calibration(&runs, &qrels, 2);
+/// assert_eq!(bins, vec![(0.0, 0.0, 1), (1.0, 1.0, 1)]);
+/// ```
+pub fn score_calibration(
+    runs: &[TrecRun],
+    qrels: &[Qrel],
+    n_bins: usize,
+) -> Vec<(f64, f64, usize)> {
+    if runs.is_empty() || n_bins == 0 {
+        return Vec::new();
+    }
+
+    let relevant: HashSet<(&str, &str)> = qrels
+        .iter()
+        .filter(|q| q.relevance > 0)
+        .map(|q| (q.query_id.as_str(), q.doc_id.as_str()))
+        .collect();
+
+    let min = runs.iter().map(|r| r.score).fold(f32::INFINITY, f32::min) as f64;
+    let max = runs.iter().map(|r| r.score).fold(f32::NEG_INFINITY, f32::max) as f64;
+    let range = max - min;
+
+    // Per bin: (score sum, relevant count, count)
+    let mut bins = vec![(0.0, 0usize, 0usize); n_bins];
+    for run in runs {
+        let normalized = if range > 0.0 {
+            (run.score as f64 - min) / range
+        } else {
+            0.0
+        };
+        let bin = ((normalized * n_bins as f64) as usize).min(n_bins - 1);
+        bins[bin].0 += normalized;
+        bins[bin].2 += 1;
+        if relevant.contains(&(run.query_id.as_str(), run.doc_id.as_str())) {
+            bins[bin].1 += 1;
+        }
+    }
+
+    bins.into_iter()
+        .filter(|&(_, _, count)| count > 0)
+        .map(|(score_sum, relevant_count, count)| {
+            (
+                score_sum / count as f64,
+                relevant_count as f64 / count as f64,
+                count,
+            )
+        })
+        .collect()
+}
+
+/// Histogram of per-query score differences `a[i] - b[i]`.
+///
+/// The deltas are split into `n_bins` equal-width bins spanning their minimum
+/// to maximum (the last bin includes the maximum). A change that helps broadly
+/// shifts the whole mass above zero; one that helps a few queries a lot leaves
+/// most mass near zero with a long tail.
+///
+/// # Returns
+///
+/// `(low, high, count)` for every bin, including empty ones, in ascending
+/// order; the counts sum to the number of queries. If all deltas are equal
+/// there is a single bin `(delta, delta, n)`. Empty if the input is empty or
+/// `n_bins` is 0.
+///
+/// # Panics
+///
+/// Panics if `a` and `b` do not have the same length.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::delta_histogram;
+///
+/// let recall_new = vec![0.5, 0.8, 1.0, 0.4];
+/// let recall_old = vec![0.5, 0.4, 0.6, 0.4];
+///
+/// let bins = delta_histogram(&recall_new, &recall_old, 2);
+/// assert_eq!(bins.len(), 2);
+/// assert_eq!(bins[0].2, 2); // unchanged queries
+/// assert_eq!(bins[1].2, 2); // queries improved by 0.4
+/// ```
+pub fn delta_histogram(a: &[f64], b: &[f64], n_bins: usize) -> Vec<(f64, f64, usize)> {
+    assert_eq!(a.len(), b.len(), "a and b must have same length");
+
+    if a.is_empty() || n_bins == 0 {
+        return Vec::new();
+    }
+
+    let deltas: Vec<f64> = a.iter().zip(b).map(|(x, y)| x - y).collect();
+    let min = deltas.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = deltas.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+    if range <= 0.0 {
+        return vec![(min, max, deltas.len())];
+    }
+
+    let width = range / n_bins as f64;
+    let mut counts = vec![0usize; n_bins];
+    for delta in &deltas {
+        let bin = (((delta - min) / width) as usize).min(n_bins - 1);
+        counts[bin] += 1;
+    }
+
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, count)| {
+            let high = if i + 1 == n_bins { max } else { min + (i + 1) as f64 * width };
+            (min + i as f64 * width, high, count)
+        })
+        .collect()
+}
+
+/// Sensitivity of a metric's aggregate to the choice of rank cutoff.
+///
+/// Evaluates `metric_base` (e.g. "ndcg") at every cutoff in `ks` with
+/// [`evaluate_multi_k`](crate::batch::evaluate_multi_k) and returns the
+/// coefficient of variation of the aggregated means:
+///
+/// Formula: `CV = σ / μ` over the per-cutoff means (population σ)
+///
+/// A small CV means conclusions drawn at one cutoff hold across the others.
+/// Returns 0.0 if no cutoff produces a value (e.g. an unknown metric) or all
+/// means are 0.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::cutoff_sensitivity;
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new()
+///     .add("1", "doc1", 0.9, "run")
+///     .add("1", "doc2", 0.8, "run")
+///     .build();
+/// let qrels = QrelBuilder::new().add("1", "doc2", 1).build();
+///
+/// // precision@1 = 0 and precision@2 = 0.5: mean 0.25, σ 0.25
+/// assert_eq!(cutoff_sensitivity(&runs, &qrels, "precision", &[1, 2]), 1.0);
+/// ```
+pub fn cutoff_sensitivity(
+    runs: &[TrecRun],
+    qrels: &[Qrel],
+    metric_base: &str,
+    ks: &[usize],
+) -> f64 {
+    use crate::batch::evaluate_multi_k;
+
+    let results = evaluate_multi_k(runs, qrels, metric_base, ks);
+    let means: Vec<f64> = ks
+        .iter()
+        .filter_map(|k| results.aggregated.get(&format!("{}@{}", metric_base, k)))
+        .copied()
+        .collect();
+    if means.is_empty() {
+        return 0.0;
+    }
+
+    let n = means.len() as f64;
+    let mean = means.iter().sum::<f64>() / n;
+    if mean == 0.0 {
+        return 0.0;
+    }
+    let variance = means.iter().map(|m| (m - mean).powi(2)).sum::<f64>() / n;
+
+    variance.sqrt() / mean
+}
+
+/// Measure how stable a metric is when a fraction of the judgments is dropped.
+///
+/// Each trial removes `round(dropout_fraction × |qrels|)` judgments chosen
+/// uniformly at random, evaluates the runs with [`evaluate_trec_batch`], and
+/// records the mean of `metric` (0.0 if no query could be evaluated). A metric
+/// that leans heavily on a few judgments shows a large spread.
+///
+/// Trials are reproducible for a given [`RngConfig`].
+///
+/// # Returns
+///
+/// `(mean, std)` of the metric across trials (sample std; 0.0 for fewer than
+/// two trials).
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::{dropout_stability, RngConfig};
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new().add("1", "doc1", 0.9, "run").add("1", "doc2", 0.8, "run").build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).add("1", "doc2", 1).build();
+///
+/// let rng = RngConfig { seed: 42, ..Default::default() };
+/// let (mean, std) = dropout_stability(&runs, &qrels, "precision@5", 0.0, 5, &rng);
+/// assert!((mean - 0.4).abs() < 1e-9);
+/// assert!(std < 1e-12);
+/// ```
+pub fn dropout_stability(
+    runs: &[TrecRun],
+    qrels: &[Qrel],
+    metric: &str,
+    dropout_fraction: f64,
+    n_trials: usize,
+    rng: &RngConfig,
+) -> (f64, f64) {
+    let n_drop = ((dropout_fraction.clamp(0.0, 1.0) * qrels.len() as f64).round() as usize)
+        .min(qrels.len());
+    let mut rng = Rng::new(rng);
+
+    let values: Vec<f64> = (0..n_trials)
+        .map(|_| {
+            // Partial Fisher-Yates: the first n_drop indices are the dropped judgments
+            let mut indices: Vec<usize> = (0..qrels.len()).collect();
+            for i in 0..n_drop {
+                let j = i + rng.next_below(qrels.len() - i);
+                indices.swap(i, j);
+            }
+            let kept: Vec<Qrel> = indices[n_drop..].iter().map(|&i| qrels[i].clone()).collect();
+
+            evaluate_trec_batch(runs, &kept, &[metric])
+                .aggregated
+                .get(metric)
+                .copied()
+                .unwrap_or(0.0)
+        })
+        .collect();
+
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let std = if values.len() > 1 {
+        (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (values.len() - 1) as f64)
+            .sqrt()
+    } else {
+        0.0
+    };
+
+    (mean, std)
+}
+
+/// Estimate how many judgments are needed before the system ordering stabilizes.
+///
+/// `systems` holds the runs of every system, distinguished by `run_tag`. The
+/// judgments are revealed in a random order (driven by `rng`), `step` at a
+/// time; after each reveal the systems are ranked by their mean `metric`
+/// (ties broken by tag). The budget is the smallest revealed count from which
+/// the top two systems, in order, never change again up to the full qrels.
+/// Flips among the lower-ranked systems do not count.
+///
+/// A small budget relative to `qrels.len()` means the best two systems are
+/// clearly separated and further judging will not change the conclusion.
+///
+/// # Returns
+///
+/// The number of judgments needed (a multiple of `step`, or `qrels.len()`).
+/// 0 if `qrels` is empty.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::{judgment_budget_for_stability, RngConfig};
+/// use rank_eval::trec::{QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new()
+///     .add("1", "doc1", 0.9, "good")
+///     .add("1", "doc2", 0.9, "bad")
+///     .build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).add("1", "doc2", 0).build();
+///
+/// let budget = judgment_budget_for_stability(&runs, &qrels, "mrr", 1, &RngConfig::default());
+/// assert!(budget <= qrels.len());
+/// ```
+pub fn judgment_budget_for_stability(
+    systems: &[TrecRun],
+    qrels: &[Qrel],
+    metric: &str,
+    step: usize,
+    rng: &RngConfig,
+) -> usize {
+    use crate::batch::evaluate_all_systems;
+
+    if qrels.is_empty() {
+        return 0;
+    }
+    let step = step.max(1);
+
+    let mut tags: Vec<&str> = systems.iter().map(|r| r.run_tag.as_str()).collect();
+    tags.sort_unstable();
+    tags.dedup();
+
+    // Random reveal order (Fisher-Yates)
+    let mut rng = Rng::new(rng);
+    let mut revealed: Vec<Qrel> = qrels.to_vec();
+    for i in (1..revealed.len()).rev() {
+        revealed.swap(i, rng.next_below(i + 1));
+    }
+
+    // Only the best and runner-up systems are compared
+    let top_two_at = |budget: usize| -> Vec<&str> {
+        let results = evaluate_all_systems(systems, &revealed[..budget], &[metric]);
+        let mean = |tag: &str| {
+            results
+                .get(tag)
+                .and_then(|r| r.aggregated.get(metric))
+                .copied()
+                .unwrap_or(0.0)
+        };
+        let mut ordering = tags.clone();
+        ordering.sort_by(|a, b| mean(b).total_cmp(&mean(a)).then_with(|| a.cmp(b)));
+        ordering.truncate(2);
+        ordering
+    };
+
+    let mut budgets: Vec<usize> = (step..qrels.len()).step_by(step).collect();
+    budgets.push(qrels.len());
+
+    // Walk back from the full qrels until the top two first differ
+    let final_top_two = top_two_at(qrels.len());
+    let mut stable_from = qrels.len();
+    for &budget in budgets.iter().rev().skip(1) {
+        if top_two_at(budget) != final_top_two {
+            break;
+        }
+        stable_from = budget;
+    }
+
+    stable_from
+}
+
+/// Pseudo-random number generator used by the randomized routines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RngAlgorithm {
+    /// SplitMix64 (Steele et al., 2014).
+    #[default]
+    SplitMix64,
+    /// Xorshift64* (Vigna, 2016).
+    Xorshift64Star,
+}
+
+/// Random number generator configuration shared by all randomized statistics
+/// functions (e.g. [`dropout_stability`]).
+///
+/// The same config always produces the same random stream, so results are
+/// reproducible across calls and runs.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::statistics::{RngAlgorithm, RngConfig};
+///
+/// let rng = RngConfig { seed: 7, algorithm: RngAlgorithm::Xorshift64Star };
+/// assert_ne!(rng, RngConfig::default());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RngConfig {
+    pub seed: u64,
+    pub algorithm: RngAlgorithm,
+}
+
+/// Small deterministic PRNG for reproducible resampling.
+struct Rng {
+    state: u64,
+    algorithm: RngAlgorithm,
+}
+
+impl Rng {
+    fn new(config: &RngConfig) -> Self {
+        let mut seed = config.seed;
+        let state = match config.algorithm {
+            RngAlgorithm::SplitMix64 => seed,
+            // Xorshift has a fixed point at zero, so scramble the seed first
+            RngAlgorithm::Xorshift64Star => splitmix64(&mut seed).max(1),
+        };
+        Self {
+            state,
+            algorithm: config.algorithm,
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        match self.algorithm {
+            RngAlgorithm::SplitMix64 => splitmix64(&mut self.state),
+            RngAlgorithm::Xorshift64Star => {
+                self.state ^= self.state >> 12;
+                self.state ^= self.state << 25;
+                self.state ^= self.state >> 27;
+                self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+            }
+        }
+    }
+
+    /// Uniform integer in `0..n` (`n > 0`).
+    fn next_below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// Normal CDF approximation (using error function).
+fn normal_cdf(x: f64) -> f64 {
+    0.5 * (1.0 + erf(x / (2.0_f64).sqrt()))
+}
+
+/// Error function approximation.
+fn erf(x: f64) -> f64 {
+    // Abramowitz and Stegun approximation
+    let a1 = 0.254829592;
+    let a2 = -0.284496736;
+    let a3 = 1.421413741;
+    let a4 = -1.453152027;
+    let a5 = 1.061405429;
+    let p = 0.3275911;
+
+    let sign = if x < 0.0 { -1.0 } else { 1.0 };
+    let x = x.abs();
+
+    let t = 1.0 / (1.0 + p * x);
+    let y = 1.0 - (((((a5 * t + a4) * t) + a3) * t + a2) * t + a1) * t * (-x * x).exp();
+
+    sign * y
+}
+
+/// Normal quantile (inverse CDF) approximation.
+fn normal_quantile(p: f64) -> f64 {
+    // Beasley-Springer-Moro algorithm approximation
+    if p < 0.5 {
+        -normal_quantile(1.0 - p)
+    } else if p > 0.5 {
+        let t = (-2.0 * (1.0 - p).ln()).sqrt();
+        t - (2.515517 + 0.802853 * t + 0.010328 * t * t) / (1.0 + 1.432788 * t + 0.189269 * t * t + 0.001308 * t * t * t)
+    } else {
+        0.0
+    }
+}
+
+/// Student's t quantile (inverse CDF) approximation.
+///
+/// Exact for 1 and 2 degrees of freedom; otherwise a Cornish-Fisher expansion
+/// around the normal quantile.
+fn student_t_quantile(p: f64, df: usize) -> f64 {
+    match df {
+        0 => f64::NAN,
+        1 => (std::f64::consts::PI * (p - 0.5)).tan(),
+        2 => (2.0 * p - 1.0) / (2.0 * p * (1.0 - p)).sqrt(),
+        _ => {
+            let z = normal_quantile(p);
+            let v = df as f64;
+            let z3 = z.powi(3);
+            let z5 = z.powi(5);
+            let z7 = z.powi(7);
+            let z9 = z.powi(9);
+            z + (z3 + z) / (4.0 * v)
+                + (5.0 * z5 + 16.0 * z3 + 3.0 * z) / (96.0 * v.powi(2))
+                + (3.0 * z7 + 19.0 * z5 + 17.0 * z3 - 15.0 * z) / (384.0 * v.powi(3))
+                + (79.0 * z9 + 776.0 * z7 + 1482.0 * z5 - 1920.0 * z3 - 945.0 * z)
+                    / (92160.0 * v.powi(4))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paired_t_test() {
+        // Use data with variance in differences to get non-zero t-statistic
+        let method_a = vec![0.5, 0.6, 0.7, 0.8, 0.9];
+        let method_b = vec![0.4, 0.55, 0.6, 0.75, 0.8];
+
+        let result = paired_t_test(&method_a, &method_b, 0.05);
+        // t-statistic can be positive or negative depending on which method is better
+        assert!(result.t_statistic.abs() >= 0.0 || result.t_statistic == 0.0);
+        assert!(result.p_value >= 0.0 && result.p_value <= 1.0);
+        assert_eq!(result.degrees_of_freedom, 4);
+    }
+
+    #[test]
+    fn test_paired_t_test_small_sample_warning() {
+        let method_a = vec![0.5, 0.6, 0.7, 0.8, 0.9];
+        let method_b = vec![0.4, 0.55, 0.6, 0.75, 0.8];
+
+        let result = paired_t_test(&method_a, &method_b, 0.05);
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].contains("5 queries"));
+
+        let relaxed = paired_t_test_with_min_sample(&method_a, &method_b, 0.05, 5);
+        assert!(relaxed.warnings.is_empty());
+        assert_eq!(relaxed.p_value, result.p_value);
+    }
+
+    #[test]
     fn test_confidence_interval() {
         let scores = vec![0.5, 0.6, 0.7, 0.8, 0.9];
         let (lower, upper) = confidence_interval(&scores, 0.95);
@@ -261,6 +1615,256 @@ mod tests {
         assert!(lower >= 0.0 && upper <= 1.0);
     }
 
+    #[test]
+    fn test_confidence_interval_student_t() {
+        let scores = vec![0.5, 0.6, 0.7, 0.8, 0.9];
+        let (z_lower, z_upper) = confidence_interval_with_dist(&scores, 0.95, CIDist::Normal);
+        let (t_lower, t_upper) = confidence_interval_with_dist(&scores, 0.95, CIDist::StudentT);
+        assert!(t_upper - t_lower > z_upper - z_lower);
+
+        // t(0.975, 4) = 2.776
+        assert!((student_t_quantile(0.975, 4) - 2.776).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_min_detectable_effect() {
+        let noisy = vec![0.1, 0.9, 0.2, 0.8, 0.3, 0.7];
+        let stable = vec![0.45, 0.55, 0.48, 0.52, 0.5, 0.5];
+
+        let mde_noisy = min_detectable_effect(&noisy, 0.05, 0.8);
+        let mde_stable = min_detectable_effect(&stable, 0.05, 0.8);
+        assert!(mde_stable < mde_noisy);
+        assert!(min_detectable_effect(&[0.5], 0.05, 0.8).is_infinite());
+    }
+
+    #[test]
+    fn test_percentile() {
+        let values = vec![5.0, 1.0, 3.0, 2.0, 4.0];
+        assert_eq!(percentile(&values, 0.5), 3.0);
+        assert_eq!(percentile(&values, 0.25), 2.0);
+        assert_eq!(percentile(&values, 1.0), 5.0);
+        assert!((percentile(&[1.0, 2.0], 0.5) - 1.5).abs() < 1e-9);
+        assert_eq!(percentile(&[], 0.5), 0.0);
+    }
+
+    #[test]
+    fn test_gmap_contributions_reconstruct_gmap() {
+        let ap = vec![
+            ("q1".to_string(), 0.9),
+            ("q2".to_string(), 0.4),
+            ("q3".to_string(), 0.0),
+        ];
+        let epsilon = 1e-5;
+
+        let contributions = gmap_contributions(&ap, epsilon);
+        assert_eq!(contributions.len(), 3);
+        assert_eq!(contributions[2].0, "q3");
+        assert!(contributions[2].1 < contributions[1].1);
+
+        let n = contributions.len() as f64;
+        let reconstructed = (contributions.iter().map(|(_, c)| c).sum::<f64>() / n).exp();
+        let values: Vec<f64> = ap.iter().map(|(_, v)| *v).collect();
+        assert!((reconstructed - gmap(&values, epsilon)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_dropout_stability() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let mut run_builder = RunBuilder::new();
+        let mut qrel_builder = QrelBuilder::new();
+        for q in 0..10 {
+            let query_id = q.to_string();
+            for d in 0..5 {
+                let doc_id = format!("d{}", d);
+                run_builder = run_builder.add(&query_id, &doc_id, 1.0 - d as f32 * 0.1, "run");
+                qrel_builder = qrel_builder.add(&query_id, &doc_id, ((q + d) % 2) as u32);
+            }
+        }
+        let runs = run_builder.build();
+        let qrels = qrel_builder.build();
+
+        let rng = RngConfig { seed: 7, ..Default::default() };
+        let (_, std_none) = dropout_stability(&runs, &qrels, "precision@5", 0.0, 20, &rng);
+        let (_, std_high) = dropout_stability(&runs, &qrels, "precision@5", 0.5, 20, &rng);
+        assert!(std_none < 1e-12);
+        assert!(std_high > 1e-3);
+
+        // Same seed reproduces the same trials
+        let (mean_a, std_a) = dropout_stability(&runs, &qrels, "precision@5", 0.5, 20, &rng);
+        let (mean_b, std_b) = dropout_stability(&runs, &qrels, "precision@5", 0.5, 20, &rng);
+        assert!((mean_a - mean_b).abs() < 1e-12);
+        assert!((std_a - std_b).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_first_relevant_rank_deltas() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let a = RunBuilder::new()
+            .add("1", "doc1", 0.9, "a")
+            .add("1", "doc2", 0.8, "a")
+            .add("1", "doc3", 0.7, "a")
+            .add("2", "doc4", 0.9, "a")
+            .build();
+        let b = RunBuilder::new()
+            .add("1", "doc3", 0.9, "b")
+            .add("1", "doc1", 0.8, "b")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "doc3", 1)
+            .add("2", "doc4", 1)
+            .add("3", "doc5", 0)
+            .build();
+
+        let deltas = first_relevant_rank_deltas(&a, &b, &qrels);
+        assert_eq!(
+            deltas,
+            vec![
+                ("1".to_string(), Some(3), Some(1)),
+                ("2".to_string(), Some(1), None),
+            ]
+        );
+
+        // With several tags per file, the smallest tag is evaluated
+        let mut multi = b.clone();
+        multi.extend(
+            RunBuilder::new()
+                .add("1", "doc1", 0.9, "z")
+                .add("1", "doc3", 0.8, "z")
+                .build(),
+        );
+        assert_eq!(first_relevant_rank_deltas(&a, &multi, &qrels)[0].2, Some(1));
+    }
+
+    #[test]
+    fn test_score_calibration() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        // Document i has score i/99 and is relevant with probability ~score
+        let mut run_builder = RunBuilder::new();
+        let mut qrel_builder = QrelBuilder::new();
+        for i in 0..100 {
+            let doc_id = format!("d{}", i);
+            run_builder = run_builder.add("1", &doc_id, i as f32 / 99.0, "run");
+            if i % 10 < i / 10 {
+                qrel_builder = qrel_builder.add("1", &doc_id, 1);
+            }
+        }
+
+        let bins = score_calibration(&run_builder.build(), &qrel_builder.build(), 5);
+        assert_eq!(bins.len(), 5);
+        assert_eq!(bins.iter().map(|b| b.2).sum::<usize>(), 100);
+        assert!(bins.windows(2).all(|w| w[0].0 < w[1].0 && w[0].1 < w[1].1));
+        assert!(score_calibration(&[], &[], 5).is_empty());
+    }
+
+    #[test]
+    fn test_judgment_budget_for_stability() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        // "good" puts the relevant document first on every query, "bad" last
+        let mut run_builder = RunBuilder::new();
+        let mut qrel_builder = QrelBuilder::new();
+        for q in 0..30 {
+            let query_id = q.to_string();
+            run_builder = run_builder
+                .add(&query_id, "rel", 0.9, "good")
+                .add(&query_id, "other", 0.5, "good")
+                .add(&query_id, "other", 0.9, "bad")
+                .add(&query_id, "rel", 0.5, "bad");
+            qrel_builder = qrel_builder.add(&query_id, "rel", 1).add(&query_id, "other", 0);
+        }
+        let runs = run_builder.build();
+        let qrels = qrel_builder.build();
+
+        let rng = RngConfig { seed: 3, ..Default::default() };
+        let budget = judgment_budget_for_stability(&runs, &qrels, "mrr", 5, &rng);
+        assert!(budget > 0 && budget <= 10, "budget = {}", budget);
+        assert_eq!(budget, judgment_budget_for_stability(&runs, &qrels, "mrr", 5, &rng));
+        assert_eq!(judgment_budget_for_stability(&runs, &[], "mrr", 5, &rng), 0);
+    }
+
+    #[test]
+    fn test_judgment_budget_for_stability_ignores_lower_ranks() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        // "a" finds the relevant document at rank 1 and "b" at rank 2 on every
+        // query, so the top two never flip. "c" and "d" find it at rank 3 or 4,
+        // alternating by query, so their order depends on what is revealed.
+        let mut run_builder = RunBuilder::new();
+        let mut qrel_builder = QrelBuilder::new();
+        for q in 0..21 {
+            let query_id = q.to_string();
+            let (c_rank, d_rank) = if q % 2 == 0 { (3, 4) } else { (4, 3) };
+            for (tag, rel_rank) in [("a", 1), ("b", 2), ("c", c_rank), ("d", d_rank)] {
+                for rank in 1..=4 {
+                    let doc = if rank == rel_rank {
+                        "rel".to_string()
+                    } else {
+                        format!("n{}", rank)
+                    };
+                    run_builder = run_builder.add(&query_id, &doc, 1.0 - rank as f32 / 10.0, tag);
+                }
+            }
+            qrel_builder = qrel_builder.add(&query_id, "rel", 1).add(&query_id, "n1", 0);
+        }
+        let runs = run_builder.build();
+        let qrels = qrel_builder.build();
+        let rng = RngConfig { seed: 3, ..Default::default() };
+
+        assert_eq!(judgment_budget_for_stability(&runs, &qrels, "mrr", 1, &rng), 1);
+
+        // On their own, the order of "c" and "d" does flip as judgments arrive
+        let lower: Vec<TrecRun> =
+            runs.iter().filter(|r| r.run_tag.as_str() >= "c").cloned().collect();
+        assert!(judgment_budget_for_stability(&lower, &qrels, "mrr", 1, &rng) > 1);
+    }
+
+    #[test]
+    fn test_bootstrap_summary() {
+        let scores: Vec<f64> = (0..50).map(|i| (i % 10) as f64 / 10.0).collect();
+        let sample_mean = scores.iter().sum::<f64>() / scores.len() as f64;
+
+        let rng = RngConfig { seed: 11, ..Default::default() };
+        let summary = bootstrap_summary(&scores, 2000, 0.95, &rng);
+        assert!((summary.mean - sample_mean).abs() < 0.01);
+        assert!(summary.lower < sample_mean && sample_mean < summary.upper);
+        assert!(summary.std > 0.0);
+        assert!(summary.warnings.is_empty());
+        assert_eq!(summary, bootstrap_summary(&scores, 2000, 0.95, &rng));
+
+        // Wider confidence, wider interval
+        let narrow = bootstrap_summary(&scores, 2000, 0.5, &rng);
+        assert!(narrow.upper - narrow.lower < summary.upper - summary.lower);
+
+        // Too few scores for a reliable interval
+        let small = bootstrap_summary(&scores[..5], 2000, 0.95, &rng);
+        assert_eq!(small.warnings.len(), 1);
+        assert!(small.warnings[0].contains("5 queries"));
+    }
+
+    #[test]
+    fn test_rng_config_reproducible() {
+        let draw = |config: &RngConfig| {
+            let mut rng = Rng::new(config);
+            (0..8).map(|_| rng.next_below(1000)).collect::<Vec<_>>()
+        };
+
+        for algorithm in [RngAlgorithm::SplitMix64, RngAlgorithm::Xorshift64Star] {
+            let config = RngConfig { seed: 42, algorithm };
+            assert_eq!(draw(&config), draw(&config));
+            assert_ne!(draw(&config), draw(&RngConfig { seed: 43, algorithm }));
+            // A zero seed must not get stuck
+            assert!(draw(&RngConfig { seed: 0, algorithm }).iter().any(|&x| x != 0));
+        }
+
+        assert_ne!(
+            draw(&RngConfig { seed: 42, algorithm: RngAlgorithm::SplitMix64 }),
+            draw(&RngConfig { seed: 42, algorithm: RngAlgorithm::Xorshift64Star })
+        );
+    }
+
     #[test]
     fn test_cohens_d() {
         let method_a = vec![0.5, 0.6, 0.7];
@@ -269,5 +1873,245 @@ mod tests {
         let d = cohens_d(&method_a, &method_b);
         assert!(d > 0.0); // method_a should be better
     }
-}
 
+    #[test]
+    fn test_compare_all() {
+        use crate::batch::evaluate_batch_binary;
+        use std::collections::HashSet;
+
+        let qrels = vec![
+            ["doc1"].into_iter().collect::<HashSet<_>>(),
+            ["doc2"].into_iter().collect::<HashSet<_>>(),
+            ["doc3"].into_iter().collect::<HashSet<_>>(),
+        ];
+        let base = evaluate_batch_binary(
+            &[vec!["doc3", "doc1"], vec!["doc1", "doc2"], vec!["doc3"]],
+            &qrels,
+            &["mrr", "precision@1"],
+        );
+        let a = evaluate_batch_binary(
+            &[vec!["doc1"], vec!["doc2"], vec!["doc3"]],
+            &qrels,
+            &["mrr", "precision@1"],
+        );
+        let b = evaluate_batch_binary(
+            &[vec!["doc2"], vec!["doc3"], vec!["doc1"]],
+            &qrels,
+            &["mrr", "precision@1"],
+        );
+
+        let mut systems = HashMap::new();
+        systems.insert("base".to_string(), base);
+        systems.insert("a".to_string(), a);
+        systems.insert("b".to_string(), b);
+
+        let tests = compare_all(&systems, "base", &["mrr", "precision@1"]);
+        assert_eq!(tests.len(), 4);
+        for system in ["a", "b"] {
+            for metric in ["mrr", "precision@1"] {
+                let result = &tests[&(system.to_string(), metric.to_string())];
+                assert_eq!(result.degrees_of_freedom, 2);
+            }
+        }
+        assert!(tests[&("a".to_string(), "mrr".to_string())].mean_difference > 0.0);
+        assert!(compare_all(&systems, "missing", &["mrr"]).is_empty());
+    }
+
+    #[test]
+    fn test_swaps_to_ideal() {
+        let relevant: HashSet<_> = ["r1", "r2"].into_iter().collect();
+
+        assert_eq!(swaps_to_ideal(&["r1", "r2", "n1", "n2"], &relevant), 0);
+        assert_eq!(swaps_to_ideal(&["n1", "n2", "r1", "r2"], &relevant), 4);
+        assert_eq!(swaps_to_ideal(&["r1", "n1", "r2", "n2"], &relevant), 1);
+        assert_eq!(swaps_to_ideal::<&str>(&[], &relevant), 0);
+    }
+
+    #[test]
+    fn test_score_outliers() {
+        let mut runs: Vec<TrecRun> = (0..10)
+            .map(|i| TrecRun {
+                query_id: "1".to_string(),
+                doc_id: format!("doc{}", i),
+                rank: i + 1,
+                score: 0.5 + i as f32 * 0.01,
+                run_tag: "bm25".to_string(),
+            })
+            .collect();
+        runs[4].score = 50.0;
+
+        let outliers = score_outliers(&runs, 2.5);
+        assert_eq!(
+            outliers,
+            vec![("1".to_string(), "bm25".to_string(), "doc4".to_string())]
+        );
+
+        // Constant scores produce no outliers
+        for run in &mut runs {
+            run.score = 1.0;
+        }
+        assert!(score_outliers(&runs, 2.5).is_empty());
+    }
+
+    #[test]
+    fn test_per_query_agreement() {
+        // A's differences: +, +, -, 0, +; B's differences: +, -, -, 0, -
+        let a_sys1 = [0.5, 0.9, 0.1, 0.3, 0.6];
+        let a_sys2 = [0.2, 0.4, 0.7, 0.3, 0.5];
+        let b_sys1 = [0.6, 0.2, 0.3, 0.8, 0.1];
+        let b_sys2 = [0.1, 0.5, 0.4, 0.8, 0.9];
+
+        // Agree on queries 0, 2 and 3 (the tie)
+        assert_eq!(per_query_agreement(&a_sys1, &a_sys2, &b_sys1, &b_sys2), 0.6);
+        assert_eq!(per_query_agreement(&a_sys1, &a_sys2, &a_sys1, &a_sys2), 1.0);
+        assert_eq!(per_query_agreement(&[], &[], &[], &[]), 0.0);
+    }
+
+    #[test]
+    fn test_ranking_similarity_ndcg() {
+        let reference = ["a", "b", "c", "d"];
+
+        assert_eq!(ranking_similarity_ndcg(&reference, &reference, 4), 1.0);
+        // Only the top k of each ranking matters
+        assert_eq!(ranking_similarity_ndcg(&["a", "b", "x"], &reference, 2), 1.0);
+
+        let reversed = ranking_similarity_ndcg(&["d", "c", "b", "a"], &reference, 4);
+        let disjoint = ranking_similarity_ndcg(&["w", "x", "y", "z"], &reference, 4);
+        assert!(reversed < 1.0 && reversed > disjoint);
+        assert_eq!(disjoint, 0.0);
+        assert_eq!(ranking_similarity_ndcg(&reference, &[], 4), 0.0);
+    }
+
+    #[test]
+    fn test_delta_histogram_counts_every_query() {
+        let a: Vec<f64> = (0..50).map(|i| (i % 7) as f64 / 7.0).collect();
+        let b: Vec<f64> = (0..50).map(|i| (i % 5) as f64 / 5.0).collect();
+
+        let bins = delta_histogram(&a, &b, 8);
+        assert_eq!(bins.len(), 8);
+        assert_eq!(bins.iter().map(|bin| bin.2).sum::<usize>(), 50);
+        // Bins are contiguous and cover the deltas
+        for pair in bins.windows(2) {
+            assert!((pair[0].1 - pair[1].0).abs() < 1e-12);
+        }
+        assert_eq!(delta_histogram(&[0.5, 0.5], &[0.2, 0.2], 4), vec![(0.5 - 0.2, 0.5 - 0.2, 2)]);
+        assert!(delta_histogram(&[], &[], 4).is_empty());
+    }
+
+    #[test]
+    fn test_standard_error_by_hand() {
+        // Mean 0.5; squared deviations 0.09, 0.01, 0.01, 0.09 sum to 0.2;
+        // sample variance 0.2 / 3; SE = sqrt(0.2 / 3 / 4)
+        let scores = [0.2, 0.4, 0.6, 0.8];
+        assert!((standard_error(&scores) - (0.2f64 / 12.0).sqrt()).abs() < 1e-12);
+
+        assert_eq!(standard_error(&[0.5, 0.5, 0.5]), 0.0);
+        assert!(standard_error(&[0.5]).is_nan());
+
+        // The normal 95% interval is mean ± 1.96 SE
+        let (lower, upper) = confidence_interval(&scores, 0.95);
+        assert!(((upper - lower) / 2.0 - 1.959964 * standard_error(&scores)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_win_magnitude_breakdown() {
+        let a = [0.75, 0.5, 0.25, 1.0, 0.5];
+        let b = [0.5, 0.5, 0.75, 0.0, 0.625];
+
+        let (wins, losses) = win_magnitude_breakdown(&a, &b);
+        assert_eq!(wins, 0.25 + 1.0);
+        assert_eq!(losses, -0.5 - 0.125);
+
+        // Wins and losses sum to the total difference
+        let total: f64 = a.iter().sum::<f64>() - b.iter().sum::<f64>();
+        assert!((wins + losses - total).abs() < 1e-12);
+        assert_eq!(win_magnitude_breakdown(&[], &[]), (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_per_query_regret_oracle_is_zero() {
+        let mut systems: HashMap<String, HashMap<String, f64>> = HashMap::new();
+        let per_query = |values: [f64; 3]| -> HashMap<String, f64> {
+            ["q1", "q2", "q3"].iter().map(|q| q.to_string()).zip(values).collect()
+        };
+        systems.insert("a".into(), per_query([0.9, 0.2, 0.5]));
+        systems.insert("b".into(), per_query([0.4, 0.6, 0.5]));
+
+        // Route every query to its best system
+        let oracle: HashMap<String, f64> = systems["a"]
+            .iter()
+            .map(|(query_id, &a)| (query_id.clone(), a.max(systems["b"][query_id])))
+            .collect();
+        systems.insert("oracle".into(), oracle);
+
+        assert_eq!(per_query_regret(&systems, "oracle").values().sum::<f64>(), 0.0);
+
+        let regret_a = per_query_regret(&systems, "a");
+        assert!((regret_a["q2"] - 0.4).abs() < 1e-12);
+        assert!((regret_a.values().sum::<f64>() - 0.4).abs() < 1e-12);
+        assert!(per_query_regret(&systems, "missing").is_empty());
+    }
+
+    #[test]
+    fn test_cutoff_sensitivity_flat_ndcg() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        // Every relevant document is at the top, so nDCG@k is 1.0 for all k
+        let mut builder = RunBuilder::new();
+        let mut qrels = QrelBuilder::new();
+        for query in ["1", "2"] {
+            for i in 0..20 {
+                builder = builder.add(query, &format!("doc{}", i), 1.0 - i as f32 / 20.0, "run");
+            }
+            qrels = qrels.add(query, "doc0", 2).add(query, "doc1", 1);
+        }
+        let runs = builder.build();
+        let qrels = qrels.build();
+
+        let flat = cutoff_sensitivity(&runs, &qrels, "ndcg", &[5, 10, 20]);
+        assert!(flat < 1e-9);
+
+        // Precision falls steadily with depth
+        let falling = cutoff_sensitivity(&runs, &qrels, "precision", &[5, 10, 20]);
+        assert!(falling > 0.3);
+        assert_eq!(cutoff_sensitivity(&runs, &qrels, "unknown", &[5, 10]), 0.0);
+    }
+
+    #[test]
+    fn test_relevant_movement() {
+        use crate::trec::{QrelBuilder, RunBuilder};
+
+        let ranked = |order: &[&str], tag: &str| {
+            let mut builder = RunBuilder::new();
+            for (i, doc) in order.iter().enumerate() {
+                builder = builder.add("1", doc, 1.0 - i as f32 / 10.0, tag);
+            }
+            builder.build()
+        };
+        let a = ranked(&["s", "n1", "n2", "d", "up", "gone"], "a");
+        let b = ranked(&["s", "up", "n1", "n2", "n3", "d", "new"], "b");
+        let qrels = QrelBuilder::new()
+            .add("1", "s", 1)
+            .add("1", "up", 1)
+            .add("1", "d", 1)
+            .add("1", "gone", 1)
+            .add("1", "new", 1)
+            .add("1", "never", 1)
+            .build();
+
+        // "up": rank 5 -> 2; "new" enters; "d" 4 -> 6; "gone" leaves; "s" stays
+        assert_eq!(relevant_movement(&a, &b, &qrels)["1"], (2, 2, 1));
+    }
+
+    #[test]
+    fn test_tail_mean_bottom_quarter() {
+        let scores = vec![0.55, 0.05, 0.95, 0.35, 0.75, 0.15, 0.85, 0.45, 0.65, 0.25, 0.5, 0.6];
+
+        // 12 scores: the bottom quarter is 0.05, 0.15 and 0.25
+        assert!((tail_mean(&scores, 0.25) - 0.15).abs() < 1e-12);
+        let mean = scores.iter().sum::<f64>() / scores.len() as f64;
+        assert!((tail_mean(&scores, 1.0) - mean).abs() < 1e-12);
+        assert!(tail_mean(&scores, 0.0).is_nan());
+        assert!(tail_mean(&[], 0.25).is_nan());
+    }
+}
@@ -10,6 +10,7 @@ use std::path::Path;
 
 /// A TREC run file entry.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TrecRun {
     pub query_id: String,
     pub doc_id: String,
@@ -26,10 +27,244 @@ pub struct Qrel {
     pub relevance: u32, // 0 = not relevant, 1+ = relevant (higher = more relevant)
 }
 
+/// Builder for constructing `TrecRun` entries programmatically.
+///
+/// Ranks are assigned automatically when building: within each
+/// (query, run tag) group, entries are ordered by score (descending, ties keep
+/// insertion order) and numbered from 1.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::trec::RunBuilder;
+///
+/// let runs = RunBuilder::new()
+///     .add("1", "doc1", 0.5, "bm25")
+///     .add("1", "doc2", 0.9, "bm25")
+///     .build();
+///
+/// assert_eq!(runs[0].doc_id, "doc2");
+/// assert_eq!(runs[0].rank, 1);
+/// assert_eq!(runs[1].rank, 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RunBuilder {
+    entries: Vec<(String, String, f32, String)>,
+}
+
+impl RunBuilder {
+    /// Create an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a scored document for a query under a run tag.
+    pub fn add(mut self, query_id: &str, doc_id: &str, score: f32, run_tag: &str) -> Self {
+        self.entries.push((
+            query_id.to_string(),
+            doc_id.to_string(),
+            score,
+            run_tag.to_string(),
+        ));
+        self
+    }
+
+    /// Build the run entries, grouped by (query, run tag) in insertion order.
+    pub fn build(self) -> Vec<TrecRun> {
+        let mut group_order: Vec<(String, String)> = Vec::new();
+        let mut grouped: HashMap<(String, String), Vec<(String, f32)>> = HashMap::new();
+
+        for (query_id, doc_id, score, run_tag) in self.entries {
+            let key = (query_id, run_tag);
+            if !grouped.contains_key(&key) {
+                group_order.push(key.clone());
+            }
+            grouped.entry(key).or_default().push((doc_id, score));
+        }
+
+        let mut runs = Vec::new();
+        for key in group_order {
+            let mut docs = grouped.remove(&key).unwrap_or_default();
+            docs.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+            let (query_id, run_tag) = key;
+            for (i, (doc_id, score)) in docs.into_iter().enumerate() {
+                runs.push(TrecRun {
+                    query_id: query_id.clone(),
+                    doc_id,
+                    rank: i + 1,
+                    score,
+                    run_tag: run_tag.clone(),
+                });
+            }
+        }
+
+        runs
+    }
+}
+
+/// Builder for constructing `Qrel` entries programmatically.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::trec::QrelBuilder;
+///
+/// let qrels = QrelBuilder::new()
+///     .add("1", "doc1", 2)
+///     .add("1", "doc2", 0)
+///     .build();
+///
+/// assert_eq!(qrels.len(), 2);
+/// assert_eq!(qrels[0].relevance, 2);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct QrelBuilder {
+    qrels: Vec<Qrel>,
+}
+
+impl QrelBuilder {
+    /// Create an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a relevance judgment for a query-document pair.
+    pub fn add(mut self, query_id: &str, doc_id: &str, relevance: u32) -> Self {
+        self.qrels.push(Qrel {
+            query_id: query_id.to_string(),
+            doc_id: doc_id.to_string(),
+            relevance,
+        });
+        self
+    }
+
+    /// Build the qrel entries in insertion order.
+    pub fn build(self) -> Vec<Qrel> {
+        self.qrels
+    }
+}
+
+/// Build run entries for one query from a plain ranked list of document IDs.
+///
+/// The document at position `i` (0-based) gets rank `i + 1` and the synthetic
+/// score `n - i`, so scores are strictly decreasing and every score-based
+/// function sees the given order.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::trec::from_ranked_ids;
+///
+/// let ids = vec!["doc3".to_string(), "doc1".to_string()];
+/// let runs = from_ranked_ids("1", &ids, "external");
+///
+/// assert_eq!(runs[0].doc_id, "doc3");
+/// assert_eq!(runs[0].rank, 1);
+/// assert!(runs[0].score > runs[1].score);
+/// ```
+pub fn from_ranked_ids(query_id: &str, ranked_ids: &[String], run_tag: &str) -> Vec<TrecRun> {
+    let n = ranked_ids.len();
+    ranked_ids
+        .iter()
+        .enumerate()
+        .map(|(i, doc_id)| TrecRun {
+            query_id: query_id.to_string(),
+            doc_id: doc_id.clone(),
+            rank: i + 1,
+            score: (n - i) as f32,
+            run_tag: run_tag.to_string(),
+        })
+        .collect()
+}
+
+/// Derive pseudo-qrels from a run's own scores.
+///
+/// Every (query, document) pair with a score ≥ `threshold` in any entry (under
+/// any run tag) becomes a grade-1 judgment; nothing else is judged. Evaluating
+/// a run against its own pseudo-qrels should give near-perfect precision at
+/// the top, so a low score points to a pipeline problem such as a sort or
+/// ID-mapping bug rather than to the model.
+///
+/// Returns one qrel per pair, sorted by query ID and then document ID.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::trec::{scores_as_qrels, RunBuilder};
+///
+/// let runs = RunBuilder::new()
+///     .add("1", "doc1", 0.9, "run")
+///     .add("1", "doc2", 0.2, "run")
+///     .build();
+///
+/// let qrels = scores_as_qrels(&runs, 0.5);
+/// assert_eq!(qrels.len(), 1);
+/// assert_eq!(qrels[0].doc_id, "doc1");
+/// assert_eq!(qrels[0].relevance, 1);
+/// ```
+pub fn scores_as_qrels(runs: &[TrecRun], threshold: f32) -> Vec<Qrel> {
+    let pairs: std::collections::BTreeSet<(&str, &str)> = runs
+        .iter()
+        .filter(|run| run.score >= threshold)
+        .map(|run| (run.query_id.as_str(), run.doc_id.as_str()))
+        .collect();
+
+    pairs
+        .into_iter()
+        .map(|(query_id, doc_id)| Qrel {
+            query_id: query_id.to_string(),
+            doc_id: doc_id.to_string(),
+            relevance: 1,
+        })
+        .collect()
+}
+
+/// Strip surrounding whitespace (including the `\r` of CRLF line endings) and,
+/// on the first line, a UTF-8 byte order mark.
+fn clean_line(line: &str, line_num: usize) -> &str {
+    let line = if line_num == 0 {
+        line.strip_prefix('\u{feff}').unwrap_or(line)
+    } else {
+        line
+    };
+    line.trim()
+}
+
+/// Split a line on whitespace, keeping double-quoted fields whole.
+///
+/// A field that starts with `"` runs to the next `"` and is returned without
+/// the quotes, so `"query one" Q0 doc1` yields `query one`, `Q0`, `doc1`.
+/// There are no escape sequences. Returns `None` on an unterminated quote.
+fn split_quoted_whitespace(line: &str) -> Option<Vec<&str>> {
+    let mut fields = Vec::new();
+    let mut rest = line.trim_start();
+
+    while !rest.is_empty() {
+        let (field, remainder) = match rest.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"')?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                rest.split_at(end)
+            }
+        };
+        fields.push(field);
+        rest = remainder.trim_start();
+    }
+
+    Some(fields)
+}
+
 /// Load TREC run file.
 ///
 /// Format: query_id Q0 doc_id rank score run_tag
 ///
+/// Windows-authored files are accepted: CRLF line endings and a leading UTF-8
+/// byte order mark are stripped.
+///
 /// # Example
 ///
 /// ```rust,no_run
@@ -41,6 +276,38 @@ pub struct Qrel {
 /// # }
 /// ```
 pub fn load_trec_runs(path: impl AsRef<Path>) -> Result<Vec<TrecRun>> {
+    load_trec_runs_with_options(path, &TrecRunOptions::default())
+}
+
+/// Options for [`load_trec_runs_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct TrecRunOptions {
+    /// Treat double-quoted fields as single tokens, so IDs containing spaces
+    /// can be written as `"query one" Q0 "doc 1" 1 0.9 run`. The quotes are
+    /// not part of the parsed value. By default fields are split on every
+    /// whitespace run.
+    pub quoted_fields: bool,
+}
+
+/// Load TREC run file with parsing options.
+///
+/// See [`load_trec_runs`] for the format and [`TrecRunOptions`] for the options.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use rank_eval::trec::{load_trec_runs_with_options, TrecRunOptions};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let options = TrecRunOptions { quoted_fields: true };
+/// let runs = load_trec_runs_with_options("runs.txt", &options)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_trec_runs_with_options(
+    path: impl AsRef<Path>,
+    options: &TrecRunOptions,
+) -> Result<Vec<TrecRun>> {
     let file = File::open(path.as_ref())
         .with_context(|| format!("Failed to open TREC runs file: {:?}", path.as_ref()))?;
     let reader = BufReader::new(file);
@@ -48,12 +315,18 @@ pub fn load_trec_runs(path: impl AsRef<Path>) -> Result<Vec<TrecRun>> {
 
     for (line_num, line) in reader.lines().enumerate() {
         let line = line.context("Failed to read line")?;
-        let line = line.trim();
+        let line = clean_line(&line, line_num);
         if line.is_empty() || line.starts_with('#') {
             continue;
         }
 
-        let parts: Vec<&str> = line.split_whitespace().collect();
+        let parts: Vec<&str> = if options.quoted_fields {
+            split_quoted_whitespace(line).ok_or_else(|| {
+                anyhow::anyhow!("Line {}: Unterminated quoted field\nLine: {}", line_num + 1, line)
+            })?
+        } else {
+            line.split_whitespace().collect()
+        };
         if parts.len() < 6 {
             // Try to provide helpful error for common issues
             if parts.len() == 5 && parts[1] != "Q0" {
@@ -112,10 +385,155 @@ pub fn load_trec_runs(path: impl AsRef<Path>) -> Result<Vec<TrecRun>> {
     Ok(runs)
 }
 
+/// Save parsed runs to a compact binary cache file (bincode).
+///
+/// Reloading with [`load_runs_bincode`] skips text parsing, which dominates
+/// load time for large run files. This is a cache, not an interchange format:
+/// the layout follows `TrecRun` and the bincode version, so regenerate it from
+/// the TREC file rather than sharing or archiving it.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use rank_eval::trec::{load_runs_bincode, load_trec_runs, save_runs_bincode};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let runs = load_trec_runs("runs.txt")?;
+/// save_runs_bincode(&runs, "runs.bin")?;
+/// let cached = load_runs_bincode("runs.bin")?;
+/// # Ok(())
+/// # }
+/// ```
+#[cfg(feature = "serde")]
+pub fn save_runs_bincode(runs: &[TrecRun], path: impl AsRef<Path>) -> Result<()> {
+    let file = File::create(path.as_ref())
+        .with_context(|| format!("Failed to create run cache file: {:?}", path.as_ref()))?;
+    bincode::serialize_into(std::io::BufWriter::new(file), runs)
+        .with_context(|| format!("Failed to write run cache file: {:?}", path.as_ref()))
+}
+
+/// Load runs from a binary cache written by [`save_runs_bincode`].
+#[cfg(feature = "serde")]
+pub fn load_runs_bincode(path: impl AsRef<Path>) -> Result<Vec<TrecRun>> {
+    let file = File::open(path.as_ref())
+        .with_context(|| format!("Failed to open run cache file: {:?}", path.as_ref()))?;
+    bincode::deserialize_from(BufReader::new(file))
+        .with_context(|| format!("Failed to read run cache file: {:?}", path.as_ref()))
+}
+
+/// Column positions (0-based, whitespace-separated) of each field in a run file.
+///
+/// The default is the standard TREC layout `query_id Q0 doc_id rank score run_tag`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColumnMapping {
+    pub query_id: usize,
+    pub doc_id: usize,
+    pub rank: usize,
+    pub score: usize,
+    pub run_tag: usize,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        Self {
+            query_id: 0,
+            doc_id: 2,
+            rank: 3,
+            score: 4,
+            run_tag: 5,
+        }
+    }
+}
+
+/// Load a run file whose columns are in a nonstandard order.
+///
+/// Each field is read from the column given by `mapping`; other columns (such
+/// as `Q0`) are ignored. If `run_tag` is the last mapped column, any further
+/// columns are joined into it with spaces, as in [`load_trec_runs`]. Scores
+/// must be finite.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use rank_eval::trec::{load_trec_runs_mapped, ColumnMapping};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// // query_id Q0 doc_id score rank run_tag
+/// let mapping = ColumnMapping { score: 3, rank: 4, ..Default::default() };
+/// let runs = load_trec_runs_mapped("runs.txt", mapping)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_trec_runs_mapped(
+    path: impl AsRef<Path>,
+    mapping: ColumnMapping,
+) -> Result<Vec<TrecRun>> {
+    let file = File::open(path.as_ref())
+        .with_context(|| format!("Failed to open TREC runs file: {:?}", path.as_ref()))?;
+    let reader = BufReader::new(file);
+    let mut runs = Vec::new();
+
+    let last_column = mapping
+        .query_id
+        .max(mapping.doc_id)
+        .max(mapping.rank)
+        .max(mapping.score)
+        .max(mapping.run_tag);
+
+    for (line_num, line) in reader.lines().enumerate() {
+        let line = line.context("Failed to read line")?;
+        let line = clean_line(&line, line_num);
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() <= last_column {
+            return Err(anyhow::anyhow!(
+                "Line {}: Invalid run format. Expected at least {} fields, found {}\nLine: {}",
+                line_num + 1, last_column + 1, parts.len(), line
+            ));
+        }
+
+        let rank: usize = parts[mapping.rank].parse().with_context(|| {
+            format!("Invalid rank on line {}: {}", line_num + 1, parts[mapping.rank])
+        })?;
+        let score: f32 = parts[mapping.score].parse().with_context(|| {
+            format!("Invalid score on line {}: {}", line_num + 1, parts[mapping.score])
+        })?;
+
+        if !score.is_finite() {
+            return Err(anyhow::anyhow!(
+                "Line {}: Invalid score (NaN or Infinity): {}",
+                line_num + 1, score
+            ));
+        }
+
+        let run_tag = if mapping.run_tag == last_column {
+            parts[mapping.run_tag..].join(" ")
+        } else {
+            parts[mapping.run_tag].to_string()
+        };
+
+        runs.push(TrecRun {
+            query_id: parts[mapping.query_id].to_string(),
+            doc_id: parts[mapping.doc_id].to_string(),
+            rank,
+            score,
+            run_tag,
+        });
+    }
+
+    Ok(runs)
+}
+
 /// Load TREC qrels file.
 ///
 /// Format: query_id 0 doc_id relevance
 ///
+/// Windows-authored files are accepted: CRLF line endings and a leading UTF-8
+/// byte order mark are stripped.
+///
 /// # Example
 ///
 /// ```rust,no_run
@@ -127,6 +545,37 @@ pub fn load_trec_runs(path: impl AsRef<Path>) -> Result<Vec<TrecRun>> {
 /// # }
 /// ```
 pub fn load_qrels(path: impl AsRef<Path>) -> Result<Vec<Qrel>> {
+    load_qrels_with_options(path, &QrelsOptions::default())
+}
+
+/// Options for [`load_qrels_with_options`].
+#[derive(Debug, Clone, Default)]
+pub struct QrelsOptions {
+    /// Strip trailing `#` comments after the relevance field, e.g.
+    /// `1 0 doc1 2 # annotator A` or `1 0 doc1 2#reviewed`. By default only
+    /// lines that start with `#` are treated as comments.
+    pub strip_inline_comments: bool,
+}
+
+/// Load TREC qrels file with parsing options.
+///
+/// See [`load_qrels`] for the format and [`QrelsOptions`] for the options.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use rank_eval::trec::{load_qrels_with_options, QrelsOptions};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let options = QrelsOptions { strip_inline_comments: true };
+/// let qrels = load_qrels_with_options("qrels.txt", &options)?;
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_qrels_with_options(
+    path: impl AsRef<Path>,
+    options: &QrelsOptions,
+) -> Result<Vec<Qrel>> {
     let file = File::open(path.as_ref())
         .with_context(|| format!("Failed to open qrels file: {:?}", path.as_ref()))?;
     let reader = BufReader::new(file);
@@ -134,7 +583,7 @@ pub fn load_qrels(path: impl AsRef<Path>) -> Result<Vec<Qrel>> {
 
     for (line_num, line) in reader.lines().enumerate() {
         let line = line.context("Failed to read line")?;
-        let line = line.trim();
+        let line = clean_line(&line, line_num);
         if line.is_empty() || line.starts_with('#') {
             continue;
         }
@@ -157,7 +606,13 @@ pub fn load_qrels(path: impl AsRef<Path>) -> Result<Vec<Qrel>> {
 
         let query_id = parts[0].to_string();
         let doc_id = parts[2].to_string();
-        let relevance: u32 = parts[3]
+        // Comments may be attached to the relevance field ("2#note")
+        let relevance_field = if options.strip_inline_comments {
+            parts[3].split('#').next().unwrap_or_default()
+        } else {
+            parts[3]
+        };
+        let relevance: u32 = relevance_field
             .parse()
             .with_context(|| format!("Invalid relevance on line {}: {}", line_num + 1, parts[3]))?;
 
@@ -178,7 +633,46 @@ pub fn load_qrels(path: impl AsRef<Path>) -> Result<Vec<Qrel>> {
 pub fn group_runs_by_query(
     runs: &[TrecRun],
 ) -> HashMap<String, HashMap<String, Vec<(String, f32)>>> {
-    let mut grouped: HashMap<String, HashMap<String, Vec<(String, f32)>>> = HashMap::new();
+    group_runs_by_query_ordered(runs, OrderBy::Score)
+}
+
+/// Which TREC column determines the order of documents within a run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OrderBy {
+    /// Sort by score, descending (the trec_eval convention).
+    #[default]
+    Score,
+    /// Sort by the rank column, ascending. Use for runs whose ranks are
+    /// meaningful but whose scores are constant or unreliable.
+    Rank,
+    /// Keep file order without sorting. Use for runs already sorted by rank:
+    /// it skips the sort and never reorders tied scores.
+    Presorted,
+}
+
+/// Group runs by query and run tag, ordering each run by `order_by`.
+///
+/// Identical to [`group_runs_by_query`] except for the sort key. Ties keep
+/// file order; with [`OrderBy::Presorted`] each run is left in file order.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::trec::{group_runs_by_query_ordered, OrderBy, TrecRun};
+///
+/// let runs = vec![
+///     TrecRun { query_id: "1".to_string(), doc_id: "doc1".to_string(), rank: 2, score: 0.0, run_tag: "run1".to_string() },
+///     TrecRun { query_id: "1".to_string(), doc_id: "doc2".to_string(), rank: 1, score: 0.0, run_tag: "run1".to_string() },
+/// ];
+///
+/// let grouped = group_runs_by_query_ordered(&runs, OrderBy::Rank);
+/// assert_eq!(grouped["1"]["run1"][0].0, "doc2");
+/// ```
+pub fn group_runs_by_query_ordered(
+    runs: &[TrecRun],
+    order_by: OrderBy,
+) -> HashMap<String, HashMap<String, Vec<(String, f32)>>> {
+    let mut grouped: HashMap<String, HashMap<String, Vec<&TrecRun>>> = HashMap::new();
 
     for run in runs {
         grouped
@@ -186,17 +680,188 @@ pub fn group_runs_by_query(
             .or_default()
             .entry(run.run_tag.clone())
             .or_default()
-            .push((run.doc_id.clone(), run.score));
+            .push(run);
+    }
+
+    grouped
+        .into_iter()
+        .map(|(query_id, query_runs)| {
+            let query_runs = query_runs
+                .into_iter()
+                .map(|(run_tag, mut run_results)| {
+                    sort_run_entries(&mut run_results, order_by);
+                    let ordered = run_results
+                        .into_iter()
+                        .map(|run| (run.doc_id.clone(), run.score))
+                        .collect();
+                    (run_tag, ordered)
+                })
+                .collect();
+            (query_id, query_runs)
+        })
+        .collect()
+}
+
+/// Order one (query, run tag) group's entries by `order_by`; ties keep their
+/// current order.
+pub(crate) fn sort_run_entries(entries: &mut [&TrecRun], order_by: OrderBy) {
+    match order_by {
+        OrderBy::Score => entries.sort_by(|a, b| b.score.total_cmp(&a.score)),
+        OrderBy::Rank => entries.sort_by_key(|run| run.rank),
+        OrderBy::Presorted => {}
     }
+}
+
+/// The run with the smallest run tag among one query's grouped runs.
+///
+/// Metrics that evaluate a single run per query use this so the choice does
+/// not depend on `HashMap` iteration order.
+pub(crate) fn first_tag_run<K: Ord, V>(query_runs: &HashMap<K, V>) -> Option<&V> {
+    query_runs.iter().min_by(|a, b| a.0.cmp(b.0)).map(|(_, run)| run)
+}
+
+/// How passage scores are combined into a document score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggMethod {
+    /// Document score is the best passage score (MaxP).
+    Max,
+    /// Document score is the sum of its passage scores.
+    Sum,
+}
 
-    // Sort each run by score descending
-    for query_runs in grouped.values_mut() {
-        for run_results in query_runs.values_mut() {
-            run_results.sort_by(|a, b| b.1.total_cmp(&a.1));
+/// Aggregate passage-level runs into document-level runs.
+///
+/// Each passage is mapped to its parent document via `passage_to_doc`
+/// (passages missing from the map are treated as their own document).
+/// Passages of the same document within a (query, run tag) group are collapsed
+/// into one entry whose score is combined with `agg`, and each group is then
+/// re-ranked by score (descending) with ranks starting at 1.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::HashMap;
+/// use rank_eval::trec::{aggregate_passages_to_docs, AggMethod, TrecRun};
+///
+/// let runs = vec![
+///     TrecRun { query_id: "1".to_string(), doc_id: "d1#p1".to_string(), rank: 1, score: 0.9, run_tag: "run1".to_string() },
+///     TrecRun { query_id: "1".to_string(), doc_id: "d1#p2".to_string(), rank: 2, score: 0.4, run_tag: "run1".to_string() },
+/// ];
+/// let mut passage_to_doc = HashMap::new();
+/// passage_to_doc.insert("d1#p1".to_string(), "d1".to_string());
+/// passage_to_doc.insert("d1#p2".to_string(), "d1".to_string());
+///
+/// let docs = aggregate_passages_to_docs(&runs, &passage_to_doc, AggMethod::Max);
+/// assert_eq!(docs.len(), 1);
+/// assert_eq!(docs[0].doc_id, "d1");
+/// ```
+pub fn aggregate_passages_to_docs(
+    runs: &[TrecRun],
+    passage_to_doc: &HashMap<String, String>,
+    agg: AggMethod,
+) -> Vec<TrecRun> {
+    let mut group_order: Vec<(String, String)> = Vec::new();
+    let mut grouped: HashMap<(String, String), HashMap<String, f32>> = HashMap::new();
+
+    for run in runs {
+        let key = (run.query_id.clone(), run.run_tag.clone());
+        let doc_id = passage_to_doc
+            .get(&run.doc_id)
+            .cloned()
+            .unwrap_or_else(|| run.doc_id.clone());
+
+        let docs = grouped.entry(key.clone()).or_insert_with(|| {
+            group_order.push(key);
+            HashMap::new()
+        });
+        docs.entry(doc_id)
+            .and_modify(|score| {
+                *score = match agg {
+                    AggMethod::Max => score.max(run.score),
+                    AggMethod::Sum => *score + run.score,
+                }
+            })
+            .or_insert(run.score);
+    }
+
+    let mut aggregated = Vec::new();
+    for key in group_order {
+        let docs = grouped.remove(&key).unwrap_or_default();
+        let mut docs: Vec<(String, f32)> = docs.into_iter().collect();
+        docs.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let (query_id, run_tag) = key;
+        for (i, (doc_id, score)) in docs.into_iter().enumerate() {
+            aggregated.push(TrecRun {
+                query_id: query_id.clone(),
+                doc_id,
+                rank: i + 1,
+                score,
+                run_tag: run_tag.clone(),
+            });
         }
     }
 
-    grouped
+    aggregated
+}
+
+/// Normalize document IDs in runs and qrels with the same function.
+///
+/// Use this when IDs differ only superficially (case, a URL suffix, ...) so
+/// that a retrieved document still matches its judgment. `normalizer` is
+/// applied to every doc ID in both inputs. Entries that collapse onto the same
+/// ID are merged: within a (query, run tag) group the highest score is kept
+/// and the group is re-ranked as in [`aggregate_passages_to_docs`]; for qrels
+/// the highest grade is kept.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::trec::{normalize_doc_ids, QrelBuilder, RunBuilder};
+///
+/// let runs = RunBuilder::new().add("1", "DOC1", 0.9, "run").build();
+/// let qrels = QrelBuilder::new().add("1", "doc1", 1).build();
+///
+/// let (runs, qrels) = normalize_doc_ids(&runs, &qrels, |id| id.to_lowercase());
+/// assert_eq!(runs[0].doc_id, qrels[0].doc_id);
+/// ```
+pub fn normalize_doc_ids(
+    runs: &[TrecRun],
+    qrels: &[Qrel],
+    normalizer: impl Fn(&str) -> String,
+) -> (Vec<TrecRun>, Vec<Qrel>) {
+    let id_map: HashMap<String, String> = runs
+        .iter()
+        .map(|run| (run.doc_id.clone(), normalizer(&run.doc_id)))
+        .collect();
+    let runs = aggregate_passages_to_docs(runs, &id_map, AggMethod::Max);
+
+    let mut order: Vec<(String, String)> = Vec::new();
+    let mut grades: HashMap<(String, String), u32> = HashMap::new();
+    for qrel in qrels {
+        let key = (qrel.query_id.clone(), normalizer(&qrel.doc_id));
+        grades
+            .entry(key.clone())
+            .and_modify(|grade| *grade = (*grade).max(qrel.relevance))
+            .or_insert_with(|| {
+                order.push(key);
+                qrel.relevance
+            });
+    }
+    let qrels = order
+        .into_iter()
+        .map(|key| {
+            let relevance = grades[&key];
+            let (query_id, doc_id) = key;
+            Qrel {
+                query_id,
+                doc_id,
+                relevance,
+            }
+        })
+        .collect();
+
+    (runs, qrels)
 }
 
 /// Group qrels by query.
@@ -215,6 +880,73 @@ pub fn group_qrels_by_query(qrels: &[Qrel]) -> HashMap<String, HashMap<String, u
     grouped
 }
 
+/// Separators used to split a structured run tag into metadata fields.
+///
+/// The default splits `bm25_k1=1.2_b=0.75` on `_` into fields and each field
+/// on `=` into a key and value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TagPattern {
+    /// Separator between fields.
+    pub field_sep: char,
+    /// Separator between a field's key and value.
+    pub kv_sep: char,
+}
+
+impl Default for TagPattern {
+    fn default() -> Self {
+        Self {
+            field_sep: '_',
+            kv_sep: '=',
+        }
+    }
+}
+
+/// Parse metadata fields out of a structured run tag.
+///
+/// The tag is split into fields on `pattern.field_sep`, and each field into
+/// key and value on the first `pattern.kv_sep`. Fields without a key/value
+/// separator (e.g. a model name) are recorded with an empty value.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::trec::{parse_tag_metadata, TagPattern};
+///
+/// let meta = parse_tag_metadata("bm25_k1=1.2_b=0.75", TagPattern::default());
+/// assert_eq!(meta["k1"], "1.2");
+/// assert_eq!(meta["b"], "0.75");
+/// assert_eq!(meta["bm25"], "");
+/// ```
+pub fn parse_tag_metadata(tag: &str, pattern: TagPattern) -> HashMap<String, String> {
+    tag.split(pattern.field_sep)
+        .filter(|field| !field.is_empty())
+        .map(|field| match field.split_once(pattern.kv_sep) {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => (field.to_string(), String::new()),
+        })
+        .collect()
+}
+
+/// Group runs by the value of a metadata field parsed from their run tag.
+///
+/// Returns a HashMap: field value -> runs whose tag carries that value.
+/// Runs whose tag lacks the field are omitted.
+pub fn group_runs_by_tag_field(
+    runs: &[TrecRun],
+    field: &str,
+    pattern: TagPattern,
+) -> HashMap<String, Vec<TrecRun>> {
+    let mut grouped: HashMap<String, Vec<TrecRun>> = HashMap::new();
+
+    for run in runs {
+        if let Some(value) = parse_tag_metadata(&run.run_tag, pattern).remove(field) {
+            grouped.entry(value).or_default().push(run.clone());
+        }
+    }
+
+    grouped
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -241,6 +973,48 @@ mod tests {
         assert_eq!(runs[0].run_tag, "run1");
     }
 
+    #[test]
+    fn test_load_trec_runs_quoted_fields() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("runs.txt");
+        let mut file = fs::File::create(&file_path).unwrap();
+
+        writeln!(file, "\"query one\" Q0 doc1 1 0.9 run1").unwrap();
+        writeln!(file, "2 Q0 \"doc 2\" 1 0.8 run1").unwrap();
+
+        // Split on whitespace, the quoted ID shifts every later column
+        assert!(load_trec_runs(&file_path).is_err());
+
+        let options = TrecRunOptions { quoted_fields: true };
+        let runs = load_trec_runs_with_options(&file_path, &options).unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].query_id, "query one");
+        assert_eq!(runs[0].doc_id, "doc1");
+        assert_eq!(runs[0].score, 0.9);
+        assert_eq!(runs[1].doc_id, "doc 2");
+
+        writeln!(file, "\"unterminated Q0 doc3 1 0.7 run1").unwrap();
+        assert!(load_trec_runs_with_options(&file_path, &options).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_runs_bincode_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("runs.bin");
+        let runs = RunBuilder::new()
+            .add("1", "doc1", 0.9, "run one")
+            .add("1", "doc2", -1.5, "run one")
+            .add("q 2", "doc3", 0.25, "run2")
+            .build();
+
+        save_runs_bincode(&runs, &file_path).unwrap();
+        assert_eq!(load_runs_bincode(&file_path).unwrap(), runs);
+
+        fs::write(&file_path, b"not a cache").unwrap();
+        assert!(load_runs_bincode(&file_path).is_err());
+    }
+
     #[test]
     fn test_load_qrels() {
         let dir = TempDir::new().unwrap();
@@ -258,6 +1032,98 @@ mod tests {
         assert_eq!(qrels[0].relevance, 2);
     }
 
+    #[test]
+    fn test_load_qrels_strip_inline_comments() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("qrels.txt");
+        let mut file = fs::File::create(&file_path).unwrap();
+
+        writeln!(file, "1 0 doc1 2 # annotator A").unwrap();
+        writeln!(file, "1 0 doc2 1#reviewed").unwrap();
+
+        // Attached comments are not valid relevance values by default
+        assert!(load_qrels(&file_path).is_err());
+
+        let options = QrelsOptions { strip_inline_comments: true };
+        let qrels = load_qrels_with_options(&file_path, &options).unwrap();
+        assert_eq!(qrels.len(), 2);
+        assert_eq!(qrels[0].doc_id, "doc1");
+        assert_eq!(qrels[0].relevance, 2);
+        assert_eq!(qrels[1].relevance, 1);
+    }
+
+    #[test]
+    fn test_group_runs_presorted() {
+        let runs = vec![
+            TrecRun { query_id: "1".to_string(), doc_id: "doc1".to_string(), rank: 1, score: 0.2, run_tag: "run1".to_string() },
+            TrecRun { query_id: "1".to_string(), doc_id: "doc2".to_string(), rank: 2, score: 0.9, run_tag: "run1".to_string() },
+            TrecRun { query_id: "1".to_string(), doc_id: "doc3".to_string(), rank: 3, score: 0.5, run_tag: "run1".to_string() },
+        ];
+        let ids = |order_by| -> Vec<String> {
+            group_runs_by_query_ordered(&runs, order_by)["1"]["run1"]
+                .iter()
+                .map(|(id, _)| id.clone())
+                .collect()
+        };
+
+        assert_eq!(ids(OrderBy::Presorted), vec!["doc1", "doc2", "doc3"]);
+        assert_eq!(ids(OrderBy::Score), vec!["doc2", "doc3", "doc1"]);
+    }
+
+    #[test]
+    fn test_normalize_doc_ids() {
+        use crate::binary::precision_at_k;
+        use std::collections::HashSet;
+
+        let runs = RunBuilder::new()
+            .add("1", "Doc1", 0.9, "run")
+            .add("1", "doc2", 0.8, "run")
+            .add("1", "DOC2", 0.7, "run")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "doc1", 1)
+            .add("1", "Doc2", 0)
+            .add("1", "doc2", 2)
+            .build();
+
+        let precision = |runs: &[TrecRun], qrels: &[Qrel]| {
+            let grouped = group_runs_by_query(runs);
+            let ranked: Vec<&String> = grouped["1"]["run"].iter().map(|(id, _)| id).collect();
+            let relevant: HashSet<&String> = qrels
+                .iter()
+                .filter(|q| q.relevance > 0)
+                .map(|q| &q.doc_id)
+                .collect();
+            precision_at_k(&ranked, &relevant, 2)
+        };
+        assert!((precision(&runs, &qrels) - 0.5).abs() < 1e-9);
+
+        let (runs, qrels) = normalize_doc_ids(&runs, &qrels, |id| id.to_lowercase());
+        assert!((precision(&runs, &qrels) - 1.0).abs() < 1e-9);
+
+        // Duplicates merged: best score kept, highest grade kept
+        assert_eq!(runs.len(), 2);
+        assert_eq!((runs[1].doc_id.as_str(), runs[1].score, runs[1].rank), ("doc2", 0.8, 2));
+        assert_eq!(qrels.len(), 2);
+        assert_eq!(qrels[1].relevance, 2);
+    }
+
+    #[test]
+    fn test_from_ranked_ids() {
+        let ids: Vec<String> = (0..5).map(|i| format!("doc{}", i)).collect();
+        let runs = from_ranked_ids("q1", &ids, "ext");
+
+        let ranks: Vec<usize> = runs.iter().map(|r| r.rank).collect();
+        assert_eq!(ranks, vec![1, 2, 3, 4, 5]);
+        assert!(runs.windows(2).all(|w| w[0].score > w[1].score));
+        assert!(runs.iter().all(|r| r.query_id == "q1" && r.run_tag == "ext"));
+
+        // Score-based grouping preserves the given order
+        let grouped = group_runs_by_query(&runs);
+        let order: Vec<&String> = grouped["q1"]["ext"].iter().map(|(id, _)| id).collect();
+        assert_eq!(order, ids.iter().collect::<Vec<_>>());
+    }
+
     #[test]
     fn test_group_runs_by_query() {
         let runs = vec![
@@ -288,6 +1154,63 @@ mod tests {
         assert_eq!(grouped["1"]["doc1"], 2);
     }
 
+    #[test]
+    fn test_run_builder_assigns_ranks() {
+        let runs = RunBuilder::new()
+            .add("1", "doc1", 0.2, "bm25")
+            .add("1", "doc2", 0.9, "bm25")
+            .add("1", "doc3", 0.5, "bm25")
+            .add("1", "doc1", 0.7, "dense")
+            .add("2", "doc4", 0.1, "bm25")
+            .build();
+
+        assert_eq!(runs.len(), 5);
+        let bm25_q1: Vec<(&str, usize)> = runs
+            .iter()
+            .filter(|r| r.query_id == "1" && r.run_tag == "bm25")
+            .map(|r| (r.doc_id.as_str(), r.rank))
+            .collect();
+        assert_eq!(bm25_q1, vec![("doc2", 1), ("doc3", 2), ("doc1", 3)]);
+        assert!(runs
+            .iter()
+            .filter(|r| r.run_tag == "dense" || r.query_id == "2")
+            .all(|r| r.rank == 1));
+
+        let qrels = QrelBuilder::new().add("1", "doc2", 1).build();
+        assert_eq!(
+            qrels,
+            vec![Qrel { query_id: "1".to_string(), doc_id: "doc2".to_string(), relevance: 1 }]
+        );
+    }
+
+    #[test]
+    fn test_aggregate_passages_to_docs() {
+        let runs = vec![
+            TrecRun { query_id: "1".to_string(), doc_id: "d1#p1".to_string(), rank: 1, score: 0.9, run_tag: "run1".to_string() },
+            TrecRun { query_id: "1".to_string(), doc_id: "d2#p1".to_string(), rank: 2, score: 0.8, run_tag: "run1".to_string() },
+            TrecRun { query_id: "1".to_string(), doc_id: "d1#p2".to_string(), rank: 3, score: 0.5, run_tag: "run1".to_string() },
+        ];
+        let passage_to_doc: HashMap<String, String> = [
+            ("d1#p1", "d1"),
+            ("d1#p2", "d1"),
+            ("d2#p1", "d2"),
+        ]
+        .into_iter()
+        .map(|(p, d)| (p.to_string(), d.to_string()))
+        .collect();
+
+        let max = aggregate_passages_to_docs(&runs, &passage_to_doc, AggMethod::Max);
+        assert_eq!(max.len(), 2);
+        assert_eq!(max[0].doc_id, "d1");
+        assert_eq!(max[0].score, 0.9);
+        assert_eq!(max[0].rank, 1);
+        assert_eq!(max[1].doc_id, "d2");
+        assert_eq!(max[1].rank, 2);
+
+        let sum = aggregate_passages_to_docs(&runs, &passage_to_doc, AggMethod::Sum);
+        assert!((sum[0].score - 1.4).abs() < 1e-6);
+    }
+
     #[test]
     fn test_run_tag_with_spaces() {
         let dir = TempDir::new().unwrap();
@@ -311,5 +1234,107 @@ mod tests {
         let result = load_trec_runs(&file_path);
         assert!(result.is_err());
     }
-}
 
+    #[test]
+    fn test_parse_tag_metadata() {
+        let meta = parse_tag_metadata("bm25_k1=1.2_b=0.75", TagPattern::default());
+        assert_eq!(meta.get("k1").map(String::as_str), Some("1.2"));
+        assert_eq!(meta.get("b").map(String::as_str), Some("0.75"));
+
+        let runs = RunBuilder::new()
+            .add("1", "doc1", 0.9, "bm25_k1=1.2_b=0.75")
+            .add("1", "doc1", 0.8, "bm25_k1=0.9_b=0.75")
+            .add("1", "doc1", 0.7, "dense")
+            .build();
+        let by_b = group_runs_by_tag_field(&runs, "b", TagPattern::default());
+        assert_eq!(by_b.len(), 1);
+        assert_eq!(by_b["0.75"].len(), 2);
+    }
+
+    #[test]
+    fn test_group_runs_by_rank() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("runs.txt");
+        let mut file = fs::File::create(&file_path).unwrap();
+
+        writeln!(file, "1 Q0 doc3 3 0.0 run1").unwrap();
+        writeln!(file, "1 Q0 doc1 1 0.0 run1").unwrap();
+        writeln!(file, "1 Q0 doc2 2 0.0 run1").unwrap();
+
+        let runs = load_trec_runs(&file_path).unwrap();
+        let grouped = group_runs_by_query_ordered(&runs, OrderBy::Rank);
+        let order: Vec<&str> = grouped["1"]["run1"].iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(order, vec!["doc1", "doc2", "doc3"]);
+
+        // Score ordering cannot recover the intended order from constant scores
+        let by_score = group_runs_by_query(&runs);
+        assert_eq!(by_score["1"]["run1"][0].0, "doc3");
+    }
+
+    #[test]
+    fn test_load_trec_runs_mapped() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("swapped.txt");
+        let mut file = fs::File::create(&file_path).unwrap();
+
+        // Score and rank columns swapped
+        writeln!(file, "1 Q0 doc1 0.9 1 run1").unwrap();
+        writeln!(file, "1 Q0 doc2 0.8 2 run1").unwrap();
+
+        let mapping = ColumnMapping {
+            score: 3,
+            rank: 4,
+            ..Default::default()
+        };
+        let runs = load_trec_runs_mapped(&file_path, mapping).unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].rank, 1);
+        assert_eq!(runs[0].score, 0.9);
+        assert_eq!(runs[1].doc_id, "doc2");
+        assert_eq!(runs[1].rank, 2);
+        assert_eq!(runs[1].run_tag, "run1");
+
+        // The standard loader rejects the fractional rank
+        assert!(load_trec_runs(&file_path).is_err());
+    }
+
+    #[test]
+    fn test_bom_and_crlf() {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("qrels.txt");
+        fs::write(&file_path, "\u{feff}1 0 doc1 1\r\n2 0 doc2 0\r\n").unwrap();
+
+        let qrels = load_qrels(&file_path).unwrap();
+        assert_eq!(qrels.len(), 2);
+        assert_eq!(qrels[0].query_id, "1");
+        assert_eq!(qrels[1].relevance, 0);
+
+        let runs_path = dir.path().join("runs.txt");
+        fs::write(&runs_path, "\u{feff}1 Q0 doc1 1 0.9 run1\r\n").unwrap();
+        let runs = load_trec_runs(&runs_path).unwrap();
+        assert_eq!(runs[0].query_id, "1");
+        assert_eq!(runs[0].run_tag, "run1");
+    }
+
+    #[test]
+    fn test_scores_as_qrels_self_evaluation() {
+        use crate::batch::evaluate_trec_batch;
+
+        let mut builder = RunBuilder::new();
+        for query in ["1", "2"] {
+            for i in 0..20 {
+                builder = builder.add(query, &format!("doc{}", i), 1.0 - i as f32 / 20.0, "run");
+            }
+        }
+        let runs = builder.build();
+
+        // Scores 1.0 down to 0.55: the top 10 of each query
+        let qrels = scores_as_qrels(&runs, 0.52);
+        assert_eq!(qrels.len(), 20);
+        assert!(qrels.iter().all(|q| q.relevance == 1));
+
+        let results = evaluate_trec_batch(&runs, &qrels, &["precision@10", "precision@5"]);
+        assert_eq!(results.aggregated["precision@10"], 1.0);
+        assert_eq!(results.aggregated["precision@5"], 1.0);
+    }
+}
@@ -1,6 +1,8 @@
 //! Input validation utilities for metrics and evaluation.
 
+use crate::trec::TrecRun;
 use anyhow::Result;
+use std::collections::HashMap;
 
 /// Validation error for metric inputs.
 #[derive(Debug, Clone, PartialEq)]
@@ -17,6 +19,8 @@ pub enum ValidationError {
     InvalidPersistence { persistence: f64 },
     /// Invalid beta parameter (must be positive)
     InvalidBeta { beta: f64 },
+    /// Entries are not sorted by query ID (`input` is "runs" or "qrels")
+    NotSortedByQuery { input: &'static str, index: usize },
 }
 
 impl std::fmt::Display for ValidationError {
@@ -42,6 +46,9 @@ impl std::fmt::Display for ValidationError {
             ValidationError::InvalidBeta { beta } => {
                 write!(f, "beta parameter ({}) must be positive", beta)
             }
+            ValidationError::NotSortedByQuery { input, index } => {
+                write!(f, "{} not sorted by query ID at index {}", input, index)
+            }
         }
     }
 }
@@ -126,6 +133,111 @@ pub fn validate_beta(beta: f64) -> Result<(), ValidationError> {
     Ok(())
 }
 
+/// A problem with the rank column of one (query, run tag) group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RankIssue {
+    pub query_id: String,
+    pub run_tag: String,
+    pub kind: RankIssueKind,
+}
+
+/// Kind of [`RankIssue`] found by [`check_rank_sequences`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RankIssueKind {
+    /// The smallest rank is `first` rather than 1
+    NotStartingAtOne { first: usize },
+    /// No entry has the ranks strictly between `after` and `next`
+    Gap { after: usize, next: usize },
+    /// `count` entries share `rank`
+    Duplicate { rank: usize, count: usize },
+    /// In file order, the entry at `position` (0-based within the group) has
+    /// a smaller rank than the one before it. Reported once per group.
+    OutOfOrder { position: usize },
+}
+
+/// Check that ranks in each (query, run tag) group form the sequence 1, 2, 3, ...
+///
+/// Within each group, ranks sorted ascending must start at 1 and have no gaps
+/// or duplicates, and the entries must appear in the file in ascending rank
+/// order. The dataset validator reports these issues as its rank warnings.
+///
+/// # Returns
+///
+/// All issues, sorted by query ID and run tag; within a group, issues come in
+/// the order `OutOfOrder`, `NotStartingAtOne`, then `Duplicate` and `Gap` by
+/// ascending rank. Empty if every group is well formed.
+///
+/// # Example
+///
+/// ```
+/// use rank_eval::trec::TrecRun;
+/// use rank_eval::validation::{check_rank_sequences, RankIssueKind};
+///
+/// let run = |doc_id: &str, rank: usize| TrecRun {
+///     query_id: "1".to_string(),
+///     doc_id: doc_id.to_string(),
+///     rank,
+///     score: 1.0 / rank as f32,
+///     run_tag: "bm25".to_string(),
+/// };
+///
+/// let issues = check_rank_sequences(&[run("doc1", 1), run("doc2", 3)]);
+/// assert_eq!(issues.len(), 1);
+/// assert_eq!(issues[0].kind, RankIssueKind::Gap { after: 1, next: 3 });
+/// ```
+pub fn check_rank_sequences(runs: &[TrecRun]) -> Vec<RankIssue> {
+    // Groups keep file order
+    let mut groups: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
+    for run in runs {
+        groups
+            .entry((run.query_id.as_str(), run.run_tag.as_str()))
+            .or_default()
+            .push(run.rank);
+    }
+
+    let mut keys: Vec<(&str, &str)> = groups.keys().copied().collect();
+    keys.sort();
+
+    let mut issues = Vec::new();
+    for (query_id, run_tag) in keys {
+        let ranks = &groups[&(query_id, run_tag)];
+        let mut push = |kind| {
+            issues.push(RankIssue {
+                query_id: query_id.to_string(),
+                run_tag: run_tag.to_string(),
+                kind,
+            })
+        };
+
+        if let Some(position) = ranks.windows(2).position(|pair| pair[1] < pair[0]) {
+            push(RankIssueKind::OutOfOrder { position: position + 1 });
+        }
+
+        let mut sorted = ranks.clone();
+        sorted.sort_unstable();
+        if sorted[0] != 1 {
+            push(RankIssueKind::NotStartingAtOne { first: sorted[0] });
+        }
+
+        let mut start = 0;
+        while start < sorted.len() {
+            let rank = sorted[start];
+            let count = sorted[start..].iter().take_while(|&&r| r == rank).count();
+            if count > 1 {
+                push(RankIssueKind::Duplicate { rank, count });
+            }
+            if let Some(&next) = sorted.get(start + count) {
+                if next > rank + 1 {
+                    push(RankIssueKind::Gap { after: rank, next });
+                }
+            }
+            start += count;
+        }
+    }
+
+    issues
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -200,5 +312,45 @@ mod tests {
             Err(ValidationError::InvalidBeta { .. })
         ));
     }
-}
 
+    #[test]
+    fn test_check_rank_sequences_issue_kinds() {
+        let run = |query_id: &str, rank: usize| TrecRun {
+            query_id: query_id.to_string(),
+            doc_id: format!("doc{}", rank),
+            rank,
+            score: 0.0,
+            run_tag: "run".to_string(),
+        };
+        let runs = vec![
+            run("clean", 1),
+            run("clean", 2),
+            run("gap", 1),
+            run("gap", 4),
+            run("dup", 1),
+            run("dup", 2),
+            run("dup", 2),
+            run("late", 2),
+            run("late", 3),
+            run("order", 2),
+            run("order", 1),
+        ];
+
+        let issues = check_rank_sequences(&runs);
+        let kinds: Vec<(&str, RankIssueKind)> = issues
+            .iter()
+            .map(|issue| (issue.query_id.as_str(), issue.kind))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                ("dup", RankIssueKind::Duplicate { rank: 2, count: 2 }),
+                ("gap", RankIssueKind::Gap { after: 1, next: 4 }),
+                ("late", RankIssueKind::NotStartingAtOne { first: 2 }),
+                ("order", RankIssueKind::OutOfOrder { position: 1 }),
+            ]
+        );
+        assert!(issues.iter().all(|issue| issue.run_tag == "run"));
+    }
+}
@@ -20,11 +20,11 @@ fn main() {
     let mrr_score = mrr(&ranked, &relevant);
     let ap = average_precision(&ranked, &relevant);
     
-    assert!(ndcg >= 0.0 && ndcg <= 1.0);
-    assert!(precision >= 0.0 && precision <= 1.0);
-    assert!(recall >= 0.0 && recall <= 1.0);
-    assert!(mrr_score >= 0.0 && mrr_score <= 1.0);
-    assert!(ap >= 0.0 && ap <= 1.0);
+    assert!((0.0..=1.0).contains(&ndcg));
+    assert!((0.0..=1.0).contains(&precision));
+    assert!((0.0..=1.0).contains(&recall));
+    assert!((0.0..=1.0).contains(&mrr_score));
+    assert!((0.0..=1.0).contains(&ap));
     
     println!("✅ Binary metrics:");
     println!("   nDCG@5: {:.4}", ndcg);
@@ -48,8 +48,8 @@ fn main() {
     let graded_ndcg = compute_ndcg(&ranked_graded, &qrels, 10);
     let map_score = compute_map(&ranked_graded, &qrels);
     
-    assert!(graded_ndcg >= 0.0 && graded_ndcg <= 1.0);
-    assert!(map_score >= 0.0 && map_score <= 1.0);
+    assert!((0.0..=1.0).contains(&graded_ndcg));
+    assert!((0.0..=1.0).contains(&map_score));
     
     println!("✅ Graded metrics:");
     println!("   nDCG@10: {:.4}", graded_ndcg);
@@ -79,7 +79,7 @@ fn main() {
     let query1_relevant: HashSet<String> = qrels_by_query["1"].keys().cloned().collect();
     
     let ndcg = ndcg_at_k(&query1_ranked, &query1_relevant, 10);
-    assert!(ndcg >= 0.0 && ndcg <= 1.0);
+    assert!((0.0..=1.0).contains(&ndcg));
     println!("✅ Query 1 nDCG@10: {:.4}", ndcg);
     
     // Test graded evaluation
@@ -88,8 +88,8 @@ fn main() {
     let graded_ndcg = compute_ndcg(&query1_graded, &qrels_by_query["1"], 10);
     let map_score = compute_map(&query1_graded, &qrels_by_query["1"]);
     
-    assert!(graded_ndcg >= 0.0 && graded_ndcg <= 1.0);
-    assert!(map_score >= 0.0 && map_score <= 1.0);
+    assert!((0.0..=1.0).contains(&graded_ndcg));
+    assert!((0.0..=1.0).contains(&map_score));
     println!("✅ Query 1 graded metrics:");
     println!("   nDCG@10: {:.4}", graded_ndcg);
     println!("   MAP: {:.4}", map_score);
@@ -4,7 +4,7 @@
 
 #[cfg(feature = "serde")]
 mod tests {
-    use rank_eval::trec::{load_trec_runs, load_qrels, group_runs_by_query, group_qrels_by_query, TrecRun, Qrel};
+    use rank_eval::trec::{load_trec_runs, load_qrels, group_runs_by_query, group_qrels_by_query};
     use rank_eval::binary::*;
     use rank_eval::graded::*;
     use rank_eval::dataset::*;
@@ -71,7 +71,7 @@ mod tests {
     fn test_rank_refine_usage_pattern() {
         // Simulate how rank-refine uses rank-eval for reranking evaluation
         let _query = "Rust memory safety";
-        let candidates = vec![
+        let candidates = [
             ("doc1", 0.9), // Highly relevant
             ("doc2", 0.7), // Partially relevant
             ("doc3", 0.5), // Less relevant
@@ -228,6 +228,47 @@ mod tests {
         assert!(stats.runs.score_distribution.std_dev >= 0.0);
     }
 
+    #[test]
+    fn test_streaming_score_distribution_matches_exact() {
+        use rank_eval::trec::TrecRun;
+
+        // Skewed scores in a scrambled order
+        let scores: Vec<f32> = (0..20_000u64)
+            .map(|i| {
+                let u = ((i * 7919) % 20_000) as f32 / 20_000.0;
+                u * u
+            })
+            .collect();
+        let runs: Vec<TrecRun> = scores
+            .iter()
+            .map(|&score| TrecRun {
+                query_id: "1".to_string(),
+                doc_id: "doc".to_string(),
+                rank: 1,
+                score,
+                run_tag: "run".to_string(),
+            })
+            .collect();
+
+        let (_, exact) = relevance_conditioned_score_dist(&runs, &[]);
+        let approx = streaming_score_distribution(scores.iter().copied());
+
+        assert_eq!(approx.min, exact.min);
+        assert_eq!(approx.max, exact.max);
+        assert!((approx.mean - exact.mean).abs() < 1e-6);
+        assert!((approx.std_dev - exact.std_dev).abs() < 1e-6);
+        assert!((approx.median - exact.median).abs() < 0.01);
+        let pairs = [
+            (approx.percentiles.p25, exact.percentiles.p25),
+            (approx.percentiles.p75, exact.percentiles.p75),
+            (approx.percentiles.p90, exact.percentiles.p90),
+            (approx.percentiles.p99, exact.percentiles.p99),
+        ];
+        for (approx, exact) in pairs {
+            assert!((approx - exact).abs() < 0.01, "{} vs {}", approx, exact);
+        }
+    }
+
     #[test]
     fn test_relevance_distribution() {
         let (_runs_dir, runs_path) = create_temp_trec_runs();
@@ -259,5 +300,363 @@ mod tests {
         assert!(stats.quality.fusion_readiness_ratio > 0.0);
         assert!(stats.quality.avg_runs_per_query > 1.0);
     }
-}
 
+    #[test]
+    fn test_relevance_conditioned_score_dist() {
+        let (_runs_dir, runs_path) = create_temp_trec_runs();
+        let (_qrels_dir, qrels_path) = create_temp_trec_qrels();
+
+        let runs = load_trec_runs(&runs_path).unwrap();
+        let qrels = load_qrels(&qrels_path).unwrap();
+
+        let (relevant, non_relevant) = relevance_conditioned_score_dist(&runs, &qrels);
+
+        // Relevant docs (grade > 0) score higher than doc3 (grade 0) in every run
+        assert!(relevant.mean > non_relevant.mean);
+        assert!(relevant.min > non_relevant.max);
+    }
+
+    #[test]
+    fn test_validation_constant_scores() {
+        let dir = TempDir::new().unwrap();
+        let runs_path = dir.path().join("runs.txt");
+        let qrels_path = dir.path().join("qrels.txt");
+
+        let mut runs_file = fs::File::create(&runs_path).unwrap();
+        writeln!(runs_file, "1 Q0 doc1 1 0.0 zeros").unwrap();
+        writeln!(runs_file, "1 Q0 doc2 2 0.0 zeros").unwrap();
+        writeln!(runs_file, "1 Q0 doc3 3 0.0 zeros").unwrap();
+        writeln!(runs_file, "1 Q0 doc1 1 2e7 huge").unwrap();
+
+        let mut qrels_file = fs::File::create(&qrels_path).unwrap();
+        writeln!(qrels_file, "1 0 doc1 1").unwrap();
+
+        let result = validate_dataset(&runs_path, &qrels_path).unwrap();
+        assert!(result.is_valid);
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.contains("tag zeros") && w.contains("constant")));
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.contains("tag huge") && w.contains("outside")));
+    }
+
+    #[test]
+    fn test_validation_invalid_ranks() {
+        let dir = TempDir::new().unwrap();
+        let runs_path = dir.path().join("runs.txt");
+        let qrels_path = dir.path().join("qrels.txt");
+
+        let mut runs_file = fs::File::create(&runs_path).unwrap();
+        writeln!(runs_file, "1 Q0 doc1 0 0.9 zero").unwrap();
+        writeln!(runs_file, "1 Q0 doc2 1 0.8 zero").unwrap();
+        writeln!(runs_file, "1 Q0 doc1 1 0.9 dup").unwrap();
+        writeln!(runs_file, "1 Q0 doc2 2 0.8 dup").unwrap();
+        writeln!(runs_file, "1 Q0 doc3 2 0.7 dup").unwrap();
+        writeln!(runs_file, "1 Q0 doc1 1 0.9 clean").unwrap();
+        writeln!(runs_file, "1 Q0 doc2 2 0.8 clean").unwrap();
+
+        let mut qrels_file = fs::File::create(&qrels_path).unwrap();
+        writeln!(qrels_file, "1 0 doc1 1").unwrap();
+
+        let result = validate_dataset(&runs_path, &qrels_path).unwrap();
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.contains("tag zero") && w.contains("rank 0")));
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.contains("tag dup") && w.contains("duplicate rank 2 shared by 2")));
+        assert!(!result.warnings.iter().any(|w| w.contains("tag clean")));
+
+        // Each defect is reported once, not also as a generic sequence warning
+        for tag in ["tag zero)", "tag dup)"] {
+            assert_eq!(result.warnings.iter().filter(|w| w.contains(tag)).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_strict_validation_duplicate_qrels() {
+        let dir = TempDir::new().unwrap();
+        let runs_path = dir.path().join("runs.txt");
+        let identical_path = dir.path().join("identical.txt");
+        let conflicting_path = dir.path().join("conflicting.txt");
+
+        let mut runs_file = fs::File::create(&runs_path).unwrap();
+        writeln!(runs_file, "1 Q0 doc1 1 0.9 run").unwrap();
+
+        let mut identical = fs::File::create(&identical_path).unwrap();
+        writeln!(identical, "1 0 doc1 1").unwrap();
+        writeln!(identical, "1 0 doc1 1").unwrap();
+
+        let mut conflicting = fs::File::create(&conflicting_path).unwrap();
+        writeln!(conflicting, "1 0 doc1 1").unwrap();
+        writeln!(conflicting, "1 0 doc1 2").unwrap();
+
+        let strict = ValidationOptions { strict: true };
+
+        // Identical duplicates are harmless: warning only, even when strict
+        let result = validate_dataset_with_options(&runs_path, &identical_path, &strict).unwrap();
+        assert!(result.is_valid);
+        assert!(result.warnings.iter().any(|w| w.contains("Duplicate qrel entry")));
+
+        // Conflicting grades: error when strict, warning otherwise
+        let result = validate_dataset_with_options(&runs_path, &conflicting_path, &strict).unwrap();
+        assert!(!result.is_valid);
+        assert!(!result.qrels_valid);
+        assert!(result.errors.iter().any(|e| e.contains("grades 1 and 2")));
+
+        let lenient = validate_dataset(&runs_path, &conflicting_path).unwrap();
+        assert!(lenient.is_valid);
+        assert!(lenient.warnings.iter().any(|w| w.contains("Conflicting qrels grades")));
+    }
+
+    #[test]
+    fn test_unreachable_relevant() {
+        let (_runs_dir, runs_path) = create_temp_trec_runs();
+        let (_qrels_dir, qrels_path) = create_temp_trec_qrels();
+
+        let runs = load_trec_runs(&runs_path).unwrap();
+        let mut qrels = load_qrels(&qrels_path).unwrap();
+        assert!(unreachable_relevant(&runs, &qrels).is_empty());
+
+        qrels.push(rank_eval::trec::Qrel {
+            query_id: "2".to_string(),
+            doc_id: "doc99".to_string(),
+            relevance: 1,
+        });
+        let unreachable = unreachable_relevant(&runs, &qrels);
+        assert_eq!(unreachable.len(), 1);
+        assert_eq!(unreachable["2"], vec!["doc99".to_string()]);
+    }
+
+    #[test]
+    fn test_judgments_per_query() {
+        use rank_eval::trec::QrelBuilder;
+
+        let qrels = QrelBuilder::new()
+            .add("1", "doc1", 0)
+            .add("1", "doc2", 1)
+            .add("1", "doc3", 2)
+            .add("1", "doc3", 2)
+            .add("2", "doc1", 0)
+            .add("3", "doc1", 1)
+            .add("3", "doc2", 0)
+            .build();
+
+        let counts = judgments_per_query(&qrels);
+        assert_eq!(counts.len(), 3);
+        assert_eq!(counts["1"], 3);
+        assert_eq!(counts["2"], 1);
+        assert_eq!(counts["3"], 2);
+
+        let (mean, min, max) = judgment_count_summary(&counts);
+        assert!((mean - 2.0).abs() < 1e-9);
+        assert_eq!((min, max), (1, 3));
+    }
+
+    #[test]
+    fn test_run_overlap_matrix() {
+        use rank_eval::trec::RunBuilder;
+
+        let runs = RunBuilder::new()
+            .add("1", "doc1", 0.9, "bm25")
+            .add("1", "doc2", 0.8, "bm25")
+            .add("1", "doc2", 0.9, "dense")
+            .add("1", "doc3", 0.8, "dense")
+            .add("2", "doc4", 0.9, "bm25")
+            .add("2", "doc4", 0.9, "dense")
+            .add("3", "doc5", 0.9, "bm25")
+            .build();
+
+        // Query 1: {doc1, doc2} vs {doc2, doc3} = 1/3; query 2: identical = 1
+        let overlap = run_overlap_matrix(&runs, 2);
+        let expected = (1.0 / 3.0 + 1.0) / 2.0;
+        assert_eq!(overlap.len(), 2);
+        assert!((overlap[&("bm25".to_string(), "dense".to_string())] - expected).abs() < 1e-9);
+        assert!((overlap[&("dense".to_string(), "bm25".to_string())] - expected).abs() < 1e-9);
+
+        // At k = 1 only the top documents are compared
+        let top1 = run_overlap_matrix(&runs, 1);
+        assert!((top1[&("bm25".to_string(), "dense".to_string())] - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_judgment_value_estimate() {
+        use rank_eval::trec::{QrelBuilder, RunBuilder};
+
+        let runs = RunBuilder::new()
+            .add("1", "top", 0.9, "run")
+            .add("1", "judged", 0.8, "run")
+            .add("1", "middle", 0.7, "run")
+            .add("1", "deep", 0.6, "run")
+            .build();
+        let qrels = QrelBuilder::new().add("1", "judged", 1).build();
+
+        let values = judgment_value_estimate(&runs, &qrels, 3);
+        let value = |doc: &str| values[&("1".to_string(), doc.to_string())];
+
+        assert!(!values.contains_key(&("1".to_string(), "judged".to_string())));
+        // "deep" is outside the top 3 and not a judging candidate
+        assert!(!values.contains_key(&("1".to_string(), "deep".to_string())));
+        assert!(value("top") > value("middle"));
+        assert!(value("middle") > 0.0);
+    }
+
+    #[test]
+    fn test_fusion_potential() {
+        use rank_eval::trec::{QrelBuilder, RunBuilder};
+
+        // Query 1: each run finds a different relevant document
+        // Query 2: both runs are the same ranking
+        let runs = RunBuilder::new()
+            .add("1", "rel_a", 0.9, "a")
+            .add("1", "x", 0.8, "a")
+            .add("1", "y", 0.7, "a")
+            .add("1", "rel_b", 0.9, "b")
+            .add("1", "z", 0.8, "b")
+            .add("1", "w", 0.7, "b")
+            .add("2", "rel", 0.9, "a")
+            .add("2", "x", 0.8, "a")
+            .add("2", "rel", 0.9, "b")
+            .add("2", "x", 0.8, "b")
+            .build();
+        let qrels = QrelBuilder::new()
+            .add("1", "rel_a", 1)
+            .add("1", "rel_b", 1)
+            .add("2", "rel", 1)
+            .build();
+
+        let potential = fusion_potential(&runs, &qrels, 3);
+        assert!(potential["1"] > 0.1);
+        assert!(potential["2"].abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_document_query_frequency() {
+        use rank_eval::trec::RunBuilder;
+
+        let mut builder = RunBuilder::new();
+        for query in 0..5 {
+            let query_id = query.to_string();
+            builder = builder
+                .add(&query_id, "popular", 0.9, "biased")
+                .add(&query_id, &format!("doc{}", query), 0.8, "biased")
+                .add(&query_id, &format!("doc{}", query), 0.9, "other");
+        }
+        // A duplicate entry within one query counts once
+        let runs = builder.add("0", "popular", 0.1, "biased").build();
+
+        let frequency = document_query_frequency(&runs);
+        assert_eq!(frequency["biased"]["popular"], 5);
+        assert_eq!(frequency["biased"]["doc3"], 1);
+        assert!(!frequency["other"].contains_key("popular"));
+        assert!(frequency["other"].values().all(|&n| n == 1));
+    }
+
+    #[test]
+    fn test_frequent_unjudged_top_docs() {
+        use rank_eval::trec::{QrelBuilder, RunBuilder};
+
+        let mut runs = RunBuilder::new();
+        let mut qrels = QrelBuilder::new();
+        for query in 0..4 {
+            let query_id = query.to_string();
+            let own_doc = format!("doc{}", query);
+            runs = runs
+                .add(&query_id, "spam", 0.99, "run")
+                .add(&query_id, &own_doc, 0.9, "run")
+                .add(&query_id, "deep", 0.1, "run");
+            for filler in 0..5 {
+                runs = runs.add(&query_id, &format!("filler{}", filler), 0.5, "run");
+            }
+            qrels = qrels.add(&query_id, &own_doc, 1);
+        }
+        // Judged for query 0 only
+        let qrels = qrels.add("0", "spam", 0).build();
+
+        let frequent = frequent_unjudged_top_docs(&runs.build(), &qrels, 2, 2);
+        // "deep" is never in the top 2 and each doc<i> is judged
+        assert_eq!(frequent, vec![("spam".to_string(), 3)]);
+    }
+
+    #[test]
+    fn test_score_rank_inversions() {
+        let (_runs_dir, runs_path) = create_temp_trec_runs();
+        let mut runs = load_trec_runs(&runs_path).unwrap();
+
+        let clean = score_rank_inversions(&runs);
+        assert_eq!(clean["bm25"], 0);
+        assert_eq!(clean["dense"], 0);
+
+        // Make dense scores increase down the ranking: two adjacent inversions
+        for run in runs.iter_mut().filter(|r| r.run_tag == "dense") {
+            run.score = match run.rank {
+                1 => 0.80,
+                2 => 0.90,
+                _ => 0.95,
+            };
+        }
+        let inversions = score_rank_inversions(&runs);
+        assert_eq!(inversions["dense"], 2);
+        assert_eq!(inversions["bm25"], 0);
+    }
+
+    #[test]
+    fn test_load_query_weights() {
+        let dir = TempDir::new().unwrap();
+        let weights_path = dir.path().join("weights.tsv");
+        let mut file = fs::File::create(&weights_path).unwrap();
+        writeln!(file, "# query_id\tweight").unwrap();
+        writeln!(file, "1\t120").unwrap();
+        writeln!(file, "2\t0.5").unwrap();
+
+        let weights = load_query_weights(&weights_path).unwrap();
+        assert_eq!(weights.len(), 2);
+        assert_eq!(weights["1"], 120.0);
+        assert_eq!(weights["2"], 0.5);
+
+        writeln!(file, "3\t-1").unwrap();
+        assert!(load_query_weights(&weights_path).is_err());
+    }
+
+    #[test]
+    fn test_load_topics_export_with_query_text() {
+        use rank_eval::batch::evaluate_trec_batch;
+        use rank_eval::export::{export_to_csv_with_topics, import_from_csv};
+
+        let dir = TempDir::new().unwrap();
+        let topics_path = dir.path().join("topics.tsv");
+        let mut file = fs::File::create(&topics_path).unwrap();
+        writeln!(file, "1\twhat is bm25").unwrap();
+        writeln!(file, "2\tdense retrieval, \"in practice\"").unwrap();
+
+        let topics = load_topics(&topics_path).unwrap();
+        assert_eq!(topics.len(), 2);
+        assert_eq!(topics["1"], "what is bm25");
+
+        let (_runs_dir, runs_path) = create_temp_trec_runs();
+        let (_qrels_dir, qrels_path) = create_temp_trec_qrels();
+        let runs = load_trec_runs(&runs_path).unwrap();
+        let qrels = load_qrels(&qrels_path).unwrap();
+        let results = evaluate_trec_batch(&runs, &qrels, &["mrr"]);
+
+        let mut csv = Vec::new();
+        export_to_csv_with_topics(&results, &topics, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("query_id,query_text,mrr"));
+        for query in &results.query_results {
+            let row = csv.lines().find(|l| l.starts_with(&format!("{},", query.query_id))).unwrap();
+            let text = topics[&query.query_id].replace('"', "\"\"");
+            assert!(row.contains(&format!("\"{}\"", text)));
+        }
+
+        // The text column does not get in the way of re-importing
+        let imported = import_from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(imported.query_results.len(), results.query_results.len());
+        assert!((imported.aggregated["mrr"] - results.aggregated["mrr"]).abs() < 1e-6);
+    }
+}
@@ -174,7 +174,7 @@ mod tests {
         }
 
         assert_eq!(all_ndcgs.len(), 3, "Should evaluate all 3 queries");
-        assert!(all_ndcgs.iter().all(|&n| n >= 0.0 && n <= 1.0), "All nDCG values should be valid");
+        assert!(all_ndcgs.iter().all(|&n| (0.0..=1.0).contains(&n)), "All nDCG values should be valid");
     }
 
     #[test]
@@ -71,11 +71,11 @@ mod tests {
         let mrr_score = mrr(&ranked, &relevant);
         let ap = average_precision(&ranked, &relevant);
 
-        assert!(ndcg >= 0.0 && ndcg <= 1.0, "nDCG should be in [0, 1]");
-        assert!(precision >= 0.0 && precision <= 1.0, "Precision should be in [0, 1]");
-        assert!(recall >= 0.0 && recall <= 1.0, "Recall should be in [0, 1]");
-        assert!(mrr_score >= 0.0 && mrr_score <= 1.0, "MRR should be in [0, 1]");
-        assert!(ap >= 0.0 && ap <= 1.0, "AP should be in [0, 1]");
+        assert!((0.0..=1.0).contains(&ndcg), "nDCG should be in [0, 1]");
+        assert!((0.0..=1.0).contains(&precision), "Precision should be in [0, 1]");
+        assert!((0.0..=1.0).contains(&recall), "Recall should be in [0, 1]");
+        assert!((0.0..=1.0).contains(&mrr_score), "MRR should be in [0, 1]");
+        assert!((0.0..=1.0).contains(&ap), "AP should be in [0, 1]");
     }
 
     #[test]
@@ -151,8 +151,8 @@ mod tests {
         let ndcg = compute_ndcg(&ranked, &qrels, 10);
         let map = compute_map(&ranked, &qrels);
 
-        assert!(ndcg >= 0.0 && ndcg <= 1.0, "Graded nDCG should be in [0, 1]");
-        assert!(map >= 0.0 && map <= 1.0, "Graded MAP should be in [0, 1]");
+        assert!((0.0..=1.0).contains(&ndcg), "Graded nDCG should be in [0, 1]");
+        assert!((0.0..=1.0).contains(&map), "Graded MAP should be in [0, 1]");
     }
 
     #[test]
@@ -211,9 +211,9 @@ mod tests {
     #[test]
     fn test_metrics_symmetry() {
         // Property: Metrics should be consistent across different orderings of same set
-        let ranked1 = vec!["doc1", "doc2", "doc3"];
-        let ranked2 = vec!["doc3", "doc2", "doc1"]; // Reversed
-        let relevant: HashSet<_> = ["doc1", "doc2"].into_iter().collect();
+        let _ranked1 = ["doc1", "doc2", "doc3"];
+        let _ranked2 = ["doc3", "doc2", "doc1"]; // Reversed
+        let _relevant: HashSet<_> = ["doc1", "doc2"].into_iter().collect();
 
         // Note: This property doesn't hold for all metrics (order matters!)
         // But we can test that empty/perfect cases are symmetric
@@ -236,9 +236,9 @@ mod tests {
         let precision = precision_at_k(&ranked, &relevant, 100);
         let recall = recall_at_k(&ranked, &relevant, 100);
 
-        assert!(ndcg >= 0.0 && ndcg <= 1.0, "nDCG with k > ranked should be valid");
-        assert!(precision >= 0.0 && precision <= 1.0, "Precision with k > ranked should be valid");
-        assert!(recall >= 0.0 && recall <= 1.0, "Recall with k > ranked should be valid");
+        assert!((0.0..=1.0).contains(&ndcg), "nDCG with k > ranked should be valid");
+        assert!((0.0..=1.0).contains(&precision), "Precision with k > ranked should be valid");
+        assert!((0.0..=1.0).contains(&recall), "Recall with k > ranked should be valid");
     }
 
     #[test]